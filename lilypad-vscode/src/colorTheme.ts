@@ -0,0 +1,168 @@
+import * as fs from "fs";
+import * as path from "path";
+import * as vscode from "vscode";
+
+/** The parts of a VS Code color theme that lilypad uses */
+export interface ColorThemeData {
+    name: string;
+    /** the built in lilypad theme to use for anything the theme doesn't set */
+    base: string;
+    tokenColors: TokenColor[];
+    colors: { [id: string]: string };
+}
+
+interface TokenColor {
+    scope?: string | string[];
+    settings: { foreground?: string };
+}
+
+// themes that include each other in a loop shouldn't hang the extension
+const maxIncludeDepth = 8;
+
+// the scopes that the simple settings in `editor.tokenColorCustomizations` apply to
+const customizationScopes: { [key: string]: string } = {
+    comments: "comment",
+    strings: "string",
+    keywords: "keyword.control",
+    numbers: "constant.numeric",
+    types: "entity.name.type",
+    functions: "entity.name.function",
+    variables: "variable",
+};
+
+/**
+ * The built in lilypad theme closest to the active VS Code theme
+ */
+export function builtInThemeForActive(): string {
+    switch (vscode.window.activeColorTheme.kind) {
+        case vscode.ColorThemeKind.Light:
+        case vscode.ColorThemeKind.HighContrastLight:
+            return "light";
+        case vscode.ColorThemeKind.HighContrast:
+            return "high_contrast";
+        default:
+            return "dark";
+    }
+}
+
+/**
+ * Reads the active color theme from the extension that contributes it,
+ * along with the user's color customizations.
+ * Returns undefined if the theme can't be found.
+ */
+export function loadActiveColorTheme(): ColorThemeData | undefined {
+    const name = vscode.workspace.getConfiguration("workbench").get<string>("colorTheme");
+    if (!name) {
+        return undefined;
+    }
+    const file = findThemeFile(name);
+    if (!file) {
+        return undefined;
+    }
+
+    const theme: ColorThemeData = {
+        name,
+        base: builtInThemeForActive(),
+        tokenColors: [],
+        colors: {},
+    };
+    try {
+        readThemeFile(file, theme, 0);
+    } catch (err) {
+        console.error(`Failed to read color theme ${file}: ${err}`);
+        return undefined;
+    }
+    applyCustomizations(theme);
+    return theme;
+}
+
+function findThemeFile(name: string): string | undefined {
+    for (const extension of vscode.extensions.all) {
+        const themes = extension.packageJSON?.contributes?.themes;
+        if (!Array.isArray(themes)) {
+            continue;
+        }
+        for (const theme of themes) {
+            if ((theme.id === name || theme.label === name) && typeof theme.path === "string") {
+                return path.join(extension.extensionPath, theme.path);
+            }
+        }
+    }
+    return undefined;
+}
+
+/** Themes can include a parent theme, which their own colors override */
+function readThemeFile(file: string, theme: ColorThemeData, depth: number) {
+    const json = parseJsonc(fs.readFileSync(file, "utf8"));
+    if (typeof json.include === "string" && depth < maxIncludeDepth) {
+        readThemeFile(path.join(path.dirname(file), json.include), theme, depth + 1);
+    }
+
+    // token colors can also be the path of a .tmTheme file, which isn't supported
+    if (Array.isArray(json.tokenColors)) {
+        theme.tokenColors.push(...json.tokenColors);
+    }
+    Object.assign(theme.colors, json.colors ?? {});
+}
+
+/** Add the user's customizations, both for all themes and for this theme (in a "[Theme Name]" section) */
+function applyCustomizations(theme: ColorThemeData) {
+    const workbenchConfig = vscode.workspace.getConfiguration("workbench");
+    const colorCustomizations = workbenchConfig.get<any>("colorCustomizations") ?? {};
+    const editorConfig = vscode.workspace.getConfiguration("editor");
+    const tokenCustomizations = editorConfig.get<any>("tokenColorCustomizations") ?? {};
+
+    for (const colors of [colorCustomizations, colorCustomizations[`[${theme.name}]`] ?? {}]) {
+        for (const [id, color] of Object.entries(colors)) {
+            if (typeof color === "string") {
+                theme.colors[id] = color;
+            }
+        }
+    }
+
+    for (const tokens of [tokenCustomizations, tokenCustomizations[`[${theme.name}]`] ?? {}]) {
+        for (const [key, scope] of Object.entries(customizationScopes)) {
+            const value = tokens[key];
+            const foreground = typeof value === "string" ? value : value?.foreground;
+            if (typeof foreground === "string") {
+                theme.tokenColors.push({ scope, settings: { foreground } });
+            }
+        }
+        if (Array.isArray(tokens.textMateRules)) {
+            theme.tokenColors.push(...tokens.textMateRules);
+        }
+    }
+}
+
+/** Parse JSON with comments and trailing commas, like VS Code's theme files */
+function parseJsonc(text: string): any {
+    let json = "";
+    let inString = false;
+    for (let i = 0; i < text.length; i++) {
+        const c = text[i];
+        if (inString) {
+            json += c;
+            if (c === "\\") {
+                json += text[++i] ?? "";
+            } else if (c === "\"") {
+                inString = false;
+            }
+        } else if (c === "\"") {
+            inString = true;
+            json += c;
+        } else if (c === "/" && text[i + 1] === "/") {
+            while (i < text.length && text[i] !== "\n") {
+                i++;
+            }
+            json += "\n";
+        } else if (c === "/" && text[i + 1] === "*") {
+            const end = text.indexOf("*/", i + 2);
+            i = end < 0 ? text.length : end + 1;
+        } else {
+            json += c;
+        }
+    }
+
+    // theme files only have scopes and colors in strings, so commas before a closing bracket are trailing
+    return JSON.parse(json.replace(/,(\s*[}\]])/g, "$1"));
+}
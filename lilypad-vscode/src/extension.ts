@@ -1,6 +1,7 @@
 import * as vscode from 'vscode';
 import { LilypadEditorProvider } from './lilypadEditor';
 import { CustomTelemetrySender } from './telemetry';
+import { importPalettesFile } from './palettes';
 
 export let logger: vscode.TelemetryLogger;
 export let activeLilypadEditor: vscode.Webview | null;
@@ -39,6 +40,140 @@ export function activate(context: vscode.ExtensionContext) {
 			}
 		})
 	);
+
+	// reindent the selection (or the whole document) in lilypad
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.reindent", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "reindent" });
+			}
+		})
+	);
+
+	// convert the indentation of the whole document in lilypad
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.convertIndentationToTabs", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "convert_indentation", useTabs: true });
+			}
+		})
+	);
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.convertIndentationToSpaces", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "convert_indentation", useTabs: false });
+			}
+		})
+	);
+
+	// copy the selection with its colors (and blocks) for pasting into documents and slides
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.copyAsHtml", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({
+					type: "copy_as_html",
+					blocks: vscode.workspace.getConfiguration("lilypad").get("copyBlockOutlines")
+				});
+			}
+		})
+	);
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.copyAsImage", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({
+					type: "copy_as_image",
+					blocks: vscode.workspace.getConfiguration("lilypad").get("copyBlockOutlines")
+				});
+			}
+		})
+	);
+
+	// share custom block palettes (like ones made for a lesson)
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.importPalettes", _ => {
+			if (activeLilypadEditor) {
+				importPalettesFile(activeLilypadEditor);
+			}
+		})
+	);
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.exportPalettes", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "export_palettes" });
+			}
+		})
+	);
+
+	// wrap code in blocks and take it back out
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.surroundWith", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "surround_with" });
+			}
+		})
+	);
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.unwrapBlock", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "unwrap_block" });
+			}
+		})
+	);
+
+	// local variables, found without the language server
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.goToLocalDefinition", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "go_to_local_definition" });
+			}
+		})
+	);
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.renameLocal", _ => {
+			if (activeLilypadEditor) {
+				activeLilypadEditor.postMessage({ type: "rename_local" });
+			}
+		})
+	);
+
+	// the outline is a setting so it stays open between files and sessions
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.toggleOutline", async _ => {
+			const config = vscode.workspace.getConfiguration("lilypad");
+			await config.update("showOutline", !config.get("showOutline"), vscode.ConfigurationTarget.Global);
+		})
+	);
+
+	// override the indentation of the file open in lilypad
+	context.subscriptions.push(
+		vscode.commands.registerCommand("lilypad.setIndentation", async _ => {
+			if (!activeLilypadEditor) {
+				return;
+			}
+			const editor = activeLilypadEditor;
+
+			const kind = await vscode.window.showQuickPick(["Tabs", "Spaces"], {
+				placeHolder: "Indent using"
+			});
+			if (!kind) {
+				return;
+			}
+			const size = await vscode.window.showQuickPick(
+				["1", "2", "3", "4", "5", "6", "7", "8"],
+				{ placeHolder: "Tab size" }
+			);
+			if (!size) {
+				return;
+			}
+
+			editor.postMessage({
+				type: "set_indent_style",
+				useTabs: kind === "Tabs",
+				tabSize: parseInt(size),
+				detect: false
+			});
+		})
+	);
 }
 
 export function deactivate() { }
@@ -0,0 +1,114 @@
+import * as fs from "fs";
+import * as os from "os";
+import * as path from "path";
+
+const fontExtensions = [".ttf", ".otf", ".ttc"];
+
+// font directories are shallow, so don't follow deep trees
+const maxDepth = 4;
+
+/**
+ * Reads a font given either its path or its name.
+ * Names are matched against the file names in the system font directories,
+ * preferring the regular style (so "SF Mono" finds SFMono-Regular.otf).
+ * Returns undefined if the font can't be found.
+ */
+export function loadFontData(family: string): Uint8Array | undefined {
+    // font families in settings can be quoted
+    family = family.trim().replace(/^['"]|['"]$/g, "");
+
+    const file = isFile(family) ? family : findFontFile(family);
+    if (!file) {
+        return undefined;
+    }
+
+    try {
+        return new Uint8Array(fs.readFileSync(file));
+    } catch (err) {
+        console.error(`Failed to read font ${file}: ${err}`);
+        return undefined;
+    }
+}
+
+function findFontFile(family: string): string | undefined {
+    const name = normalize(family);
+    if (name.length === 0) {
+        return undefined;
+    }
+
+    let best: { score: number, file: string } | undefined;
+    for (const dir of fontDirs()) {
+        visitFonts(dir, 0, file => {
+            const stem = normalize(path.basename(file, path.extname(file)));
+            if (!stem.startsWith(name)) {
+                return;
+            }
+
+            const style = stem.slice(name.length);
+            const score = (style === "" || style === "regular") ? 0 : style.length;
+            if (!best || score < best.score) {
+                best = { score, file };
+            }
+        });
+    }
+    return best?.file;
+}
+
+/** Lowercase with spaces and separators removed */
+function normalize(name: string): string {
+    return name.toLowerCase().replace(/[^\p{L}\p{N}]/gu, "");
+}
+
+function visitFonts(dir: string, depth: number, found: (file: string) => void) {
+    let entries: fs.Dirent[];
+    try {
+        entries = fs.readdirSync(dir, { withFileTypes: true });
+    } catch {
+        return;
+    }
+
+    for (const entry of entries) {
+        const entryPath = path.join(dir, entry.name);
+        if (entry.isDirectory()) {
+            if (depth < maxDepth) {
+                visitFonts(entryPath, depth + 1, found);
+            }
+        } else if (fontExtensions.includes(path.extname(entry.name).toLowerCase())) {
+            found(entryPath);
+        }
+    }
+}
+
+function fontDirs(): string[] {
+    const home = os.homedir();
+    switch (process.platform) {
+        case "darwin":
+            return [
+                "/System/Library/Fonts",
+                "/Library/Fonts",
+                path.join(home, "Library/Fonts"),
+                // apps like Terminal bundle fonts that aren't installed system-wide
+                "/System/Applications/Utilities/Terminal.app/Contents/Resources/Fonts",
+            ];
+        case "win32":
+            return [
+                path.join(process.env.WINDIR ?? "C:\\Windows", "Fonts"),
+                path.join(process.env.LOCALAPPDATA ?? path.join(home, "AppData", "Local"), "Microsoft\\Windows\\Fonts"),
+            ];
+        default:
+            return [
+                "/usr/share/fonts",
+                "/usr/local/share/fonts",
+                path.join(home, ".local/share/fonts"),
+                path.join(home, ".fonts"),
+            ];
+    }
+}
+
+function isFile(file: string): boolean {
+    try {
+        return fs.statSync(file).isFile();
+    } catch {
+        return false;
+    }
+}
@@ -1,6 +1,10 @@
 import * as vscode from "vscode";
 import { DebugProtocol } from "@vscode/debugprotocol";
 import { activeLilypadEditor, logger, setActiveLilypadEditor } from "./extension";
+import * as fs from "fs";
+import { builtInThemeForActive, loadActiveColorTheme } from "./colorTheme";
+import { loadFontData } from "./fonts";
+import { exportPalettesFile, importPalettesFile } from "./palettes";
 
 export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
     private internalEdit = false;
@@ -80,6 +84,114 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
             }
         });
 
+        // Send the indentation settings, which can be overridden per language
+        function setIndentStyle() {
+            const editorConfig = vscode.workspace.getConfiguration("editor", document);
+            webviewPanel.webview.postMessage({
+                type: "set_indent_style",
+                useTabs: !editorConfig.get("insertSpaces"),
+                tabSize: editorConfig.get("tabSize"),
+                detect: editorConfig.get("detectIndentation")
+            });
+        }
+
+        // Wrap long lines when VS Code's word wrap is on (any mode other than "off")
+        function setSoftWrap() {
+            const wordWrap = vscode.workspace.getConfiguration("editor", document).get("wordWrap");
+            webviewPanel.webview.postMessage({
+                type: "set_soft_wrap",
+                enabled: wordWrap !== "off"
+            });
+        }
+
+        function setStickyScroll() {
+            webviewPanel.webview.postMessage({
+                type: "set_sticky_scroll",
+                enabled: vscode.workspace.getConfiguration("editor", document).get("stickyScroll.enabled")
+            });
+        }
+
+        function setMinimap() {
+            webviewPanel.webview.postMessage({
+                type: "set_minimap_visible",
+                visible: vscode.workspace.getConfiguration("editor", document).get("minimap.enabled")
+            });
+        }
+
+        function setBlockShapeCues() {
+            webviewPanel.webview.postMessage({
+                type: "set_block_shape_cues",
+                enabled: vscode.workspace.getConfiguration("lilypad").get("blockShapeCues")
+            });
+        }
+
+        function setOutline() {
+            webviewPanel.webview.postMessage({
+                type: "set_outline_visible",
+                visible: vscode.workspace.getConfiguration("lilypad").get("showOutline")
+            });
+        }
+
+        // Speak the cursor's line and surroundings when VS Code's screen reader support is on
+        function setScreenReader() {
+            const support = vscode.workspace.getConfiguration("editor").get("accessibilitySupport");
+            webviewPanel.webview.postMessage({
+                type: "set_screen_reader",
+                enabled: support === "on"
+            });
+        }
+
+        // Use the theme file from the settings if there is one, otherwise the colors of the VS Code theme
+        // or a built in theme. The "auto" theme follows whether the VS Code theme is light, dark, or high contrast.
+        function setTheme() {
+            const lilypadConfig = vscode.workspace.getConfiguration("lilypad");
+            const themeFile = lilypadConfig.get<string>("themeFile");
+            if (themeFile) {
+                try {
+                    webviewPanel.webview.postMessage({
+                        type: "load_theme",
+                        fileName: themeFile,
+                        contents: fs.readFileSync(themeFile, "utf8")
+                    });
+                    return;
+                } catch (err) {
+                    vscode.window.showErrorMessage(`Failed to read Lilypad theme ${themeFile}: ${err}`);
+                }
+            }
+
+            let theme = lilypadConfig.get<string>("theme");
+            if (theme === "vscode") {
+                const colorTheme = loadActiveColorTheme();
+                if (colorTheme) {
+                    webviewPanel.webview.postMessage({
+                        type: "set_vscode_theme",
+                        theme: colorTheme
+                    });
+                    return;
+                }
+            }
+            if (theme === "auto" || theme === "vscode") {
+                theme = builtInThemeForActive();
+            }
+            webviewPanel.webview.postMessage({
+                type: "set_theme",
+                name: theme
+            });
+        }
+
+        // Send the font file, since the web view can't read it from the system.
+        // If it can't be found, lilypad keeps using its default font.
+        function sendFontData(fontFamily: string) {
+            const data = loadFontData(fontFamily);
+            if (data) {
+                webviewPanel.webview.postMessage({
+                    type: "add_font",
+                    family: fontFamily,
+                    data
+                });
+            }
+        }
+
         // Tracking settings changes
         const configSubscription = vscode.workspace.onDidChangeConfiguration(e => {
             if (e.affectsConfiguration("lilypad.blocksTheme")) {
@@ -88,6 +200,27 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                     type: "set_blocks_theme",
                     theme: newTheme
                 });
+            } else if (e.affectsConfiguration("lilypad.blockShapeCues")) {
+                setBlockShapeCues();
+            } else if (e.affectsConfiguration("lilypad.showOutline")) {
+                setOutline();
+            } else if (e.affectsConfiguration("editor.accessibilitySupport")) {
+                setScreenReader();
+            } else if (e.affectsConfiguration("lilypad.theme")
+                || e.affectsConfiguration("lilypad.themeFile")
+                || e.affectsConfiguration("workbench.colorCustomizations")
+                || e.affectsConfiguration("editor.tokenColorCustomizations")) {
+                setTheme();
+            } else if (e.affectsConfiguration("editor.insertSpaces", document)
+                || e.affectsConfiguration("editor.tabSize", document)
+                || e.affectsConfiguration("editor.detectIndentation", document)) {
+                setIndentStyle();
+            } else if (e.affectsConfiguration("editor.wordWrap", document)) {
+                setSoftWrap();
+            } else if (e.affectsConfiguration("editor.stickyScroll.enabled", document)) {
+                setStickyScroll();
+            } else if (e.affectsConfiguration("editor.minimap.enabled", document)) {
+                setMinimap();
             } else if (e.affectsConfiguration("editor.fontFamily") || e.affectsConfiguration("editor.fontSize")) {
                 // TODO: support fallback fonts instead of only sending the first
                 // TODO: could this be called as a part of started instead of using the hacky js pass through thing?
@@ -99,6 +232,14 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                     fontFamily,
                     fontSize
                 });
+                sendFontData(fontFamily);
+            }
+        });
+
+        const colorThemeSubscription = vscode.window.onDidChangeActiveColorTheme(_e => {
+            const theme = vscode.workspace.getConfiguration("lilypad").get("theme");
+            if (theme === "auto" || theme === "vscode") {
+                setTheme();
             }
         });
 
@@ -165,6 +306,7 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
             changeDiagnosticsSubscription.dispose();
             viewStateSubscription.dispose();
             configSubscription.dispose();
+            colorThemeSubscription.dispose();
             changeBreakpointsSubscription.dispose();
             stackItemSubscription.dispose();
         });
@@ -230,6 +372,20 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                         text: document.getText(),
                     });
 
+                    // send the indentation settings for the file's language
+                    setIndentStyle();
+                    setSoftWrap();
+                    setStickyScroll();
+                    setMinimap();
+                    setTheme();
+                    setBlockShapeCues();
+                    setOutline();
+                    setScreenReader();
+
+                    // send the font chosen in the settings
+                    const editorConfig = vscode.workspace.getConfiguration("editor");
+                    sendFontData((editorConfig.get("fontFamily") as string).split(',')[0]);
+
                     // send initial diagnostics
                     webviewPanel.webview.postMessage({
                         type: "set_diagnostics",
@@ -341,6 +497,14 @@ export class LilypadEditorProvider implements vscode.CustomTextEditorProvider {
                     }
                     break;
                 }
+                case "import_palettes": {
+                    importPalettesFile(webviewPanel.webview);
+                    break;
+                }
+                case "export_palettes": {
+                    exportPalettesFile(message.contents);
+                    break;
+                }
                 case "telemetry_log": {
                     logger.logUsage(message.cat, message.info);
                     break;
@@ -0,0 +1,33 @@
+import * as vscode from "vscode";
+
+// ask for a palettes file and send it to the editor to add its palettes
+export async function importPalettesFile(webview: vscode.Webview) {
+    const uris = await vscode.window.showOpenDialog({
+        canSelectMany: false,
+        filters: { "Palettes": ["toml", "json"] },
+        openLabel: "Import Palettes",
+    });
+    if (!uris || uris.length === 0) {
+        return;
+    }
+
+    const contents = new TextDecoder().decode(await vscode.workspace.fs.readFile(uris[0]));
+    webview.postMessage({
+        type: "import_palettes",
+        name: uris[0].path,
+        contents: contents,
+    });
+}
+
+// ask where to save the palettes exported by the editor
+export async function exportPalettesFile(contents: string) {
+    const uri = await vscode.window.showSaveDialog({
+        filters: { "TOML": ["toml"] },
+        saveLabel: "Export Palettes",
+    });
+    if (!uri) {
+        return;
+    }
+
+    await vscode.workspace.fs.writeFile(uri, new TextEncoder().encode(contents));
+}
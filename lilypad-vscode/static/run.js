@@ -71,6 +71,36 @@ export function registerBreakpoints(lines) {
   });
 }
 
+// put both the html and plain text on the clipboard, so apps can use whichever they support
+export function copyHtml(html, text) {
+  navigator.clipboard.write([
+    new ClipboardItem({
+      "text/html": new Blob([html], { type: "text/html" }),
+      "text/plain": new Blob([text], { type: "text/plain" }),
+    })
+  ]).catch(err => console.error("Failed to copy html", err));
+}
+
+export function copyImage(png) {
+  navigator.clipboard.write([
+    new ClipboardItem({
+      "image/png": new Blob([png], { type: "image/png" }),
+    })
+  ]).catch(err => console.error("Failed to copy image", err));
+}
+
+// the extension opens and saves palettes files
+export function importPalettes() {
+  vscode.postMessage({ type: "import_palettes" });
+}
+
+export function exportPalettes(contents) {
+  vscode.postMessage({
+    type: "export_palettes",
+    contents: contents
+  });
+}
+
 export function telemetryEvent(cat, info) {
   vscode.postMessage({
     type: "telemetry_log",
@@ -96,6 +126,9 @@ window.addEventListener("message", event => {
     case "set_font":
       handle.set_font(message.fontFamily, message.fontSize);
       break;
+    case "add_font":
+      handle.add_font(message.family, new Uint8Array(message.data));
+      break;
     case "apply_edit":
       handle.apply_edit(message.edit);
       break;
@@ -105,6 +138,27 @@ window.addEventListener("message", event => {
     case "set_blocks_theme":
       handle.set_blocks_theme(message.theme);
       break;
+    case "set_block_shape_cues":
+      handle.set_block_shape_cues(message.enabled);
+      break;
+    case "set_minimap_visible":
+      handle.set_minimap_visible(message.visible);
+      break;
+    case "set_outline_visible":
+      handle.set_outline_visible(message.visible);
+      break;
+    case "set_screen_reader":
+      handle.set_screen_reader(message.enabled);
+      break;
+    case "set_theme":
+      handle.set_theme(message.name);
+      break;
+    case "set_vscode_theme":
+      handle.set_vscode_theme(message.theme);
+      break;
+    case "load_theme":
+      handle.load_theme(message.fileName, message.contents);
+      break;
     case "set_breakpoints":
       handle.set_breakpoints(message.breakpoints);
       break;
@@ -126,6 +180,45 @@ window.addEventListener("message", event => {
     case "redo":
       handle.redo();
       break;
+    case "reindent":
+      handle.reindent();
+      break;
+    case "copy_as_html":
+      handle.copy_as_html(message.blocks);
+      break;
+    case "copy_as_image":
+      handle.copy_as_image(message.blocks);
+      break;
+    case "set_indent_style":
+      handle.set_indent_style(message.useTabs, message.tabSize, message.detect);
+      break;
+    case "convert_indentation":
+      handle.convert_indentation(message.useTabs);
+      break;
+    case "set_soft_wrap":
+      handle.set_soft_wrap(message.enabled);
+      break;
+    case "set_sticky_scroll":
+      handle.set_sticky_scroll(message.enabled);
+      break;
+    case "import_palettes":
+      handle.import_palettes(message.name, message.contents);
+      break;
+    case "export_palettes":
+      handle.export_palettes();
+      break;
+    case "surround_with":
+      handle.surround_with();
+      break;
+    case "unwrap_block":
+      handle.unwrap_block();
+      break;
+    case "go_to_local_definition":
+      handle.go_to_local_definition();
+      break;
+    case "rename_local":
+      handle.rename_local();
+      break;
     default:
       console.error("Unknown message type: " + message.type);
   }
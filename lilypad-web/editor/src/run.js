@@ -21,6 +21,39 @@ export function executeWorkspaceEdit(edit) { }
 export function registerBreakpoints(lines) { }
 export function telemetryEvent(cat, info) { }
 export function telemetryCrash(msg) { }
+export function copyHtml(html, text) {
+  navigator.clipboard.write([
+    new ClipboardItem({
+      "text/html": new Blob([html], { type: "text/html" }),
+      "text/plain": new Blob([text], { type: "text/plain" }),
+    })
+  ]).catch(err => console.error("Failed to copy html", err));
+}
+export function copyImage(png) {
+  navigator.clipboard.write([
+    new ClipboardItem({ "image/png": new Blob([png], { type: "image/png" }) })
+  ]).catch(err => console.error("Failed to copy image", err));
+}
+
+export function importPalettes() {
+  const input = document.createElement("input");
+  input.type = "file";
+  input.accept = ".toml,.json";
+  input.addEventListener("change", async () => {
+    const file = input.files[0];
+    if (file) {
+      handle.import_palettes(file.name, await file.text());
+    }
+  });
+  input.click();
+}
+export function exportPalettes(contents) {
+  const link = document.createElement("a");
+  link.href = URL.createObjectURL(new Blob([contents], { type: "application/toml" }));
+  link.download = "palettes.toml";
+  link.click();
+  URL.revokeObjectURL(link.href);
+}
 
 document.getElementById("language-picker").addEventListener("change", (e) => {
   const language = e.target.value;
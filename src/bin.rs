@@ -1,4 +1,5 @@
 mod block_editor;
+mod cli;
 mod file_picker;
 mod lang;
 mod lsp;
@@ -7,6 +8,7 @@ mod util_widgets;
 mod vscode;
 
 use block_editor::{BlockEditor, MonospaceFont};
+use clap::Parser;
 use egui::Frame;
 use file_picker::FilePicker;
 
@@ -49,6 +51,11 @@ impl eframe::App for LilypadNative {
 fn main() -> eframe::Result {
     env_logger::init();
 
+    // commands run without opening a window
+    if let Some(command) = cli::Cli::parse().command {
+        std::process::exit(command.run());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
@@ -46,6 +46,7 @@ impl Block {
         Some(Block {
             line: start_pos.row,
             col: std::cmp::min(start_pos.column, end_pos.column.saturating_sub(1)),
+            display_col: 0,
             height: end_pos.row - start_pos.row + 1,
             syntax_type,
             children: vec![],
@@ -65,6 +66,7 @@ impl BlockTrees {
         cursor: &mut TreeCursor,
         source: &ropey::Rope,
         lang: &LanguageConfig,
+        tab_width: usize,
     ) -> Self {
         let mut trees = tree_to_blocks(cursor, lang);
 
@@ -85,6 +87,8 @@ impl BlockTrees {
             adjust_block_starts(&mut trees);
         }
 
+        set_display_cols(&mut trees, source, tab_width);
+
         let padding = Padding::for_blocks(&trees, source.len_lines());
         BlockTrees { trees, padding }
     }
@@ -186,6 +190,7 @@ fn insert_divider(blocks: &mut Vec<Block>, line: usize) {
     let divider = Block {
         line,
         col: 0,
+        display_col: 0,
         height: 0,
         syntax_type: BlockType::Divider,
         children: vec![],
@@ -245,6 +250,17 @@ fn find_whitespace_chunks(source: &ropey::Rope, chunk_size: usize) -> Vec<usize>
     chunk_starts
 }
 
+/// Find where each block is drawn, accounting for tabs in the indent before it
+fn set_display_cols(blocks: &mut [Block], source: &ropey::Rope, tab_width: usize) {
+    for block in blocks {
+        // divider columns are nonsense
+        if block.syntax_type != BlockType::Divider {
+            block.display_col = source.line(block.line).display_col(block.col, tab_width);
+        }
+        set_display_cols(&mut block.children, source, tab_width);
+    }
+}
+
 /// Adjust the column and width of blocks so that all blocks contain their descendants
 fn adjust_block_starts(blocks: &mut Vec<Block>) -> usize {
     let mut max_col = usize::MAX;
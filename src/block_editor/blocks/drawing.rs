@@ -1,9 +1,15 @@
 use std::ops::RangeInclusive;
 
-use egui::{Painter, Pos2, Rect, Stroke, Vec2};
+use egui::{
+    epaint::{tessellator::path::rounded_rectangle, CornerRadiusF32},
+    Color32, FontId, Painter, Pos2, Rect, Shape, Stroke, Vec2,
+};
 
-use super::{Block, BlockTrees, BlockType, BLOCK_CONFIG};
-use crate::{block_editor::MonospaceFont, theme::blocks_theme::BlocksTheme};
+use super::{Block, BlockTrees, BlockType, Padding, BLOCK_CONFIG};
+use crate::{
+    block_editor::MonospaceFont,
+    theme::{self, blocks_theme::BlocksTheme},
+};
 
 impl BlockTrees {
     pub fn draw(
@@ -15,82 +21,96 @@ impl BlockTrees {
         font: &MonospaceFont,
         painter: &Painter,
     ) {
-        draw_blocks_helper(
-            &self.trees,
-            0,
-            0.0,
-            offset,
-            width,
-            visible_lines,
-            blocks_theme,
-            font,
-            painter,
-        );
+        for outline in block_outlines(&self.trees, &self.padding, width, font.size) {
+            let block = outline.block;
+            let block_visible = match &visible_lines {
+                Some(visible) => {
+                    block.line <= *visible.end() && *visible.start() <= block.line + block.height
+                }
+                None => true,
+            };
+
+            if block_visible {
+                draw_block(&outline, offset, blocks_theme, font, painter);
+            }
+        }
     }
 }
 
-fn draw_blocks_helper(
-    blocks: &Vec<Block>,
+impl BlockTrees {
+    /// The outlines of every block that fit in the width, children before their parents
+    pub fn outlines(&self, width: f32, char_size: Vec2) -> Vec<BlockOutline<'_>> {
+        block_outlines(&self.trees, &self.padding, width, char_size)
+    }
+}
+
+/// Where the border of a block is drawn, relative to the top left of the blocks area
+pub struct BlockOutline<'a> {
+    pub block: &'a Block,
+    /// how many blocks it is inside of
+    pub level: usize,
+    pub rect: Rect,
+    pub rounding: f32,
+}
+
+/// The outlines of the blocks (and all of their children) that fit in the width.
+/// Children come before their parents, so parents are drawn on top.
+pub fn block_outlines<'a>(
+    blocks: &'a [Block],
+    padding: &Padding,
+    width: f32,
+    char_size: Vec2,
+) -> Vec<BlockOutline<'a>> {
+    let mut outlines = vec![];
+    block_outlines_helper(blocks, 0, 0.0, padding, width, char_size, &mut outlines);
+    outlines
+}
+
+fn block_outlines_helper<'a>(
+    blocks: &'a [Block],
     level: usize,
     mut total_padding: f32,
-    offset: Vec2,
+    padding: &Padding,
     width: f32,
-    visible_lines: Option<RangeInclusive<usize>>,
-    blocks_theme: BlocksTheme,
-    font: &MonospaceFont,
-    painter: &Painter,
+    char_size: Vec2,
+    outlines: &mut Vec<BlockOutline<'a>>,
 ) -> f32 {
     // TODO: this probably could be changed to reuse the already calculated padding
     for block in blocks {
         if block.syntax_type == BlockType::Divider {
             // do not draw this block
-            total_padding = draw_blocks_helper(
+            total_padding = block_outlines_helper(
                 &block.children,
                 level,
                 total_padding,
-                offset,
+                padding,
                 width,
-                visible_lines.clone(),
-                blocks_theme,
-                font,
-                painter,
+                char_size,
+                outlines,
             );
         } else {
             total_padding += BLOCK_CONFIG.total_top_pad();
 
-            // draw children first to get total size
-            let inside_padding = draw_blocks_helper(
+            // find the children first to get total size
+            let inside_padding = block_outlines_helper(
                 &block.children,
                 level + 1,
                 total_padding,
-                offset,
+                padding,
                 width,
-                visible_lines.clone(),
-                blocks_theme,
-                font,
-                painter,
+                char_size,
+                outlines,
             ) - total_padding;
 
-            let block_visible = match visible_lines.clone() {
-                Some(visible) => {
-                    block.line <= *visible.end() && *visible.start() <= block.line + block.height
-                }
-                None => true,
-            };
-
-            if block_visible {
-                draw_block(
-                    block,
-                    level,
-                    total_padding,
-                    inside_padding,
-                    offset,
-                    width,
-                    blocks_theme,
-                    font,
-                    painter,
-                );
-            }
+            outlines.push(block_outline(
+                block,
+                level,
+                total_padding,
+                inside_padding,
+                padding,
+                width,
+                char_size,
+            ));
 
             total_padding += inside_padding;
             total_padding += BLOCK_CONFIG.total_inner_pad();
@@ -100,26 +120,22 @@ fn draw_blocks_helper(
     total_padding
 }
 
-fn draw_block(
-    block: &Block,
+fn block_outline<'a>(
+    block: &'a Block,
     level: usize,
     padding_above: f32,
     padding_inside: f32,
-    offset: Vec2,
+    padding: &Padding,
     width: f32,
-    blocks_theme: BlocksTheme,
-    font: &MonospaceFont,
-    painter: &Painter,
-) {
-    // No color for invisible nodes
-    let color = match (blocks_theme.color_for)(block.syntax_type, level) {
-        Some(color) => color,
-        None => return,
-    };
+    char_size: Vec2,
+) -> BlockOutline<'a> {
+    // wrapped lines take up multiple rows, so the block grows with them
+    let first_row = padding.first_row(block.line);
+    let row_count = padding.first_row(block.line + block.height) - first_row;
 
     let start_pt = Pos2::new(
-        (block.col as f32) * font.size.x - (BLOCK_CONFIG.stroke_width / 2.0),
-        (block.line as f32) * font.size.y
+        (block.display_col as f32) * char_size.x - (BLOCK_CONFIG.stroke_width / 2.0),
+        (first_row as f32) * char_size.y
             - (BLOCK_CONFIG.stroke_width / 2.0)
             - (BLOCK_CONFIG.inner_pad / 2.0)
             + padding_above,
@@ -131,7 +147,7 @@ fn draw_block(
     // get the size of the rectangle to draw
     let size = Vec2::new(
         width - start_pt.x - right_margin,
-        ((block.height as f32) * font.size.y) + (BLOCK_CONFIG.inner_pad * 2.0) + padding_inside,
+        ((row_count as f32) * char_size.y) + (BLOCK_CONFIG.inner_pad * 2.0) + padding_inside,
     );
 
     // nested corner radii should be r_inner = r_outer - distance
@@ -140,8 +156,113 @@ fn draw_block(
         BLOCK_CONFIG.min_corner_rad,
     );
 
+    BlockOutline {
+        block,
+        level,
+        rect: Rect::from_min_size(start_pt, size),
+        rounding,
+    }
+}
+
+fn draw_block(
+    outline: &BlockOutline,
+    offset: Vec2,
+    blocks_theme: BlocksTheme,
+    font: &MonospaceFont,
+    painter: &Painter,
+) {
+    let block = outline.block;
+    let rounding = outline.rounding;
+
+    // No color for invisible nodes
+    let color = match (blocks_theme.color_for)(block.syntax_type, outline.level) {
+        Some(color) => color,
+        None => return,
+    };
+
     // draw it
-    let rect = Rect::from_min_size(start_pt + offset, size);
+    let rect = outline.rect.translate(offset);
     let stroke = Stroke::new(BLOCK_CONFIG.stroke_width, color);
-    painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Inside);
+    if !blocks_theme.draws_shape_cues() {
+        painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Inside);
+        return;
+    }
+
+    match border_dashes(block.syntax_type) {
+        Some((dashes, gaps)) => {
+            // dashes are centered on the path, so inset it to keep them inside like the solid border
+            let mut path = vec![];
+            rounded_rectangle(
+                &mut path,
+                rect.shrink(BLOCK_CONFIG.stroke_width / 2.0),
+                CornerRadiusF32::same(rounding),
+            );
+            path.push(path[0]);
+            painter.extend(Shape::dashed_line_with_offset(
+                &path, stroke, dashes, gaps, 0.0,
+            ));
+        }
+        None => {
+            painter.rect_stroke(rect, rounding, stroke, egui::StrokeKind::Inside);
+        }
+    }
+    if let Some(label) = type_label(block.syntax_type) {
+        draw_type_label(label, rect, color, font, painter);
+    }
+}
+
+/// The dash and gap lengths of the border of each type of block (or None for a solid border),
+/// so that types with similar colors can be told apart
+fn border_dashes(block_type: BlockType) -> Option<(&'static [f32], &'static [f32])> {
+    use BlockType::*;
+
+    match block_type {
+        If => Some((&[6.0], &[3.0])),
+        For => Some((&[10.0, 2.0], &[3.0, 3.0])),
+        While => Some((&[12.0], &[4.0])),
+        Try => Some((&[2.0], &[3.0])),
+        Switch => Some((&[6.0, 2.0, 2.0], &[3.0, 3.0, 3.0])),
+        Always => Some((&[2.0, 2.0, 8.0], &[2.0, 2.0, 3.0])),
+        Object | FunctionDef | Generic | Error | Comment | Divider => None,
+    }
+}
+
+/// The name of the type of block shown on its top edge
+fn type_label(block_type: BlockType) -> Option<&'static str> {
+    use BlockType::*;
+
+    match block_type {
+        Object => Some("class"),
+        FunctionDef => Some("function"),
+        While => Some("while"),
+        If => Some("if"),
+        For => Some("for"),
+        Try => Some("try"),
+        Always => Some("always"),
+        Switch => Some("switch"),
+        Error => Some("error"),
+        Generic | Comment | Divider => None,
+    }
+}
+
+fn draw_type_label(
+    label: &str,
+    rect: Rect,
+    color: Color32,
+    font: &MonospaceFont,
+    painter: &Painter,
+) {
+    let label_font = FontId::new(font.id.size * 0.6, font.id.family.clone());
+    let galley = painter.layout_no_wrap(label.to_string(), label_font, color);
+
+    // sit on the top edge at the right, clear of the rounded corner and the code
+    let pos = Pos2::new(
+        rect.right() - BLOCK_CONFIG.outer_corner_rad - galley.size().x,
+        rect.top() - galley.size().y / 2.0,
+    );
+
+    // cut out the border behind the label
+    let background = Rect::from_min_size(pos, galley.size()).expand2(Vec2::new(2.0, 0.0));
+    painter.rect_filled(background, 2.0, theme::current().editor.background);
+    painter.galley(pos, galley, color);
 }
@@ -1,7 +1,13 @@
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use super::{soft_wrap::SoftWrap, text_range::TextPoint, MonospaceFont};
+
 mod creation;
 mod drawing;
 mod padding;
 
+pub use drawing::block_outlines;
 pub use padding::Padding;
 
 pub struct BlockTrees {
@@ -20,46 +26,165 @@ impl BlockTrees {
         }
     }
 
-    pub fn trees(&self) -> &[Block] {
-        &self.trees
-    }
-
     pub fn padding(&self) -> &Padding {
         &self.padding
     }
 
+    pub fn set_soft_wrap(&mut self, soft_wrap: SoftWrap) {
+        self.padding.set_soft_wrap(soft_wrap);
+    }
+
+    /// The chain of blocks containing the point, outermost first.
+    /// Each level is the list of siblings and the index of the block within it.
+    /// Dividers are walked through (so their children are included) but are not part of the path.
+    pub fn path_to(&self, point: TextPoint, source: &Rope) -> Vec<(&[Block], usize)> {
+        let mut path = vec![];
+        let mut curr_level: &[Block] = &self.trees;
+        'outer: while !curr_level.is_empty() {
+            for (idx, block) in curr_level.iter().enumerate() {
+                if block.text_range().contains(point, source) {
+                    // walk divider blocks but do not return them
+                    // dividers can also have nonsense columns walk them even if
+                    // the column is less than the point's
+                    if block.syntax_type != BlockType::Divider {
+                        // check that the column of the block is less than the point's.
+                        // this because the block's text range includes the indents but we want
+                        // clicking on indents to select the scope above
+                        if point.col < block.col {
+                            continue;
+                        }
+
+                        path.push((curr_level, idx));
+                    }
+
+                    curr_level = &block.children;
+                    continue 'outer;
+                }
+            }
+            break;
+        }
+        path
+    }
+
+    /// The blocks directly inside the parent (or at the top level if there is no parent).
+    /// Dividers are looked through, so their children are included instead of them.
+    pub fn children_of<'a>(&'a self, parent: Option<&'a Block>) -> Vec<&'a Block> {
+        fn helper<'a>(blocks: &'a [Block], children: &mut Vec<&'a Block>) {
+            for block in blocks {
+                if block.syntax_type == BlockType::Divider {
+                    helper(&block.children, children);
+                } else {
+                    children.push(block);
+                }
+            }
+        }
+
+        let mut children = vec![];
+        helper(parent.map_or(&self.trees, |p| &p.children), &mut children);
+        children
+    }
+
+    /// The blocks containing the line, outermost first, with how many blocks each is inside of.
+    /// Dividers are looked through, so they are not included or counted.
+    pub fn enclosing(&self, line: usize) -> Vec<(&Block, usize)> {
+        let mut enclosing = vec![];
+        let mut curr_level: &[Block] = &self.trees;
+        'outer: while !curr_level.is_empty() {
+            for block in curr_level {
+                if line < block.line || line >= block.line + block.height {
+                    continue;
+                }
+
+                if block.syntax_type != BlockType::Divider {
+                    enclosing.push((block, enclosing.len()));
+                }
+
+                curr_level = &block.children;
+                continue 'outer;
+            }
+            break;
+        }
+        enclosing
+    }
+
+    /// The innermost block with its left border under the x position on the line.
+    /// The x position is relative to the left edge of the blocks area.
+    pub fn block_with_border_at(
+        &self,
+        line: usize,
+        x: f32,
+        font: &MonospaceFont,
+    ) -> Option<&Block> {
+        let mut found = None;
+        let mut curr_level: &[Block] = &self.trees;
+        'outer: while !curr_level.is_empty() {
+            for block in curr_level {
+                if line < block.line || line >= block.line + block.height {
+                    continue;
+                }
+
+                if block.syntax_type != BlockType::Divider {
+                    // the stroke is centered on the block's column,
+                    // with some extra room on the outside to make it easier to hit
+                    let border_x = block.display_col as f32 * font.size.x;
+                    let min_x = border_x - BLOCK_CONFIG.inner_pad - BLOCK_CONFIG.stroke_width / 2.0;
+                    let max_x = border_x + BLOCK_CONFIG.stroke_width / 2.0;
+                    if min_x <= x && x <= max_x {
+                        found = Some(block);
+                    }
+                }
+
+                curr_level = &block.children;
+                continue 'outer;
+            }
+            break;
+        }
+        found
+    }
+
     #[allow(dead_code)]
     pub fn print_debug(&self) {
-        fn print_blocks_debug_helper(blocks: &[Block], indent: &str, last: bool) {
+        print!("{}", self.debug_tree());
+    }
+
+    /// The blocks drawn as a tree, one per line with their type and range
+    pub fn debug_tree(&self) -> String {
+        fn debug_tree_helper(blocks: &[Block], indent: &str, last: bool, tree: &mut String) {
             let join_symbol = if last { "└─ " } else { "├─ " };
 
             let new_indent = format!("{}{}", indent, if last { "    " } else { "│  " });
             for (idx, block) in blocks.iter().enumerate() {
                 let last_child = idx == blocks.len() - 1;
-                println!(
-                    "{}{}{:?} ({:?})",
+                tree.push_str(&format!(
+                    "{}{}{:?} ({:?})\n",
                     indent,
                     join_symbol,
                     block.syntax_type,
                     block.text_range()
-                );
-                print_blocks_debug_helper(&block.children, &new_indent, last_child);
+                ));
+                debug_tree_helper(&block.children, &new_indent, last_child, tree);
             }
         }
 
-        print_blocks_debug_helper(&self.trees, "", true);
+        let mut tree = String::new();
+        debug_tree_helper(&self.trees, "", true, &mut tree);
+        tree
     }
 }
 
+#[derive(Serialize)]
 pub struct Block {
     pub line: usize,
     pub col: usize,
+    /// the column the block is drawn at, with tabs expanded
+    pub display_col: usize,
     pub height: usize,
     pub syntax_type: BlockType,
     pub children: Vec<Block>,
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BlockType {
     Object,
     FunctionDef,
@@ -75,7 +200,7 @@ pub enum BlockType {
     Divider,
 }
 
-struct BlockConfig {
+pub(super) struct BlockConfig {
     pub outer_corner_rad: f32,
     pub min_corner_rad: f32,
     pub stroke_width: f32,
@@ -93,7 +218,7 @@ impl BlockConfig {
     }
 }
 
-const BLOCK_CONFIG: BlockConfig = BlockConfig {
+pub(super) const BLOCK_CONFIG: BlockConfig = BlockConfig {
     outer_corner_rad: 6.0,
     min_corner_rad: 1.5,
     stroke_width: 1.5,
@@ -1,15 +1,20 @@
 use log::error;
 
 use super::{Block, BlockType, BLOCK_CONFIG};
+use crate::block_editor::{soft_wrap::SoftWrap, text_range::TextPoint};
 
+/// The vertical layout of the lines: the space added around them by blocks
+/// and the visual rows they are wrapped onto
 pub struct Padding {
     cumulative: Vec<f32>,
+    soft_wrap: SoftWrap,
 }
 
 impl Padding {
     pub fn default() -> Self {
         Self {
             cumulative: vec![0.0],
+            soft_wrap: SoftWrap::none(),
         }
     }
 
@@ -45,6 +50,24 @@ impl Padding {
         self.cumulative.len()
     }
 
+    pub fn soft_wrap(&self) -> &SoftWrap {
+        &self.soft_wrap
+    }
+
+    pub fn set_soft_wrap(&mut self, soft_wrap: SoftWrap) {
+        self.soft_wrap = soft_wrap;
+    }
+
+    /// The visual row the line starts on (which is the line number unless soft wrap is on)
+    pub fn first_row(&self, line: usize) -> usize {
+        self.soft_wrap.first_row(line)
+    }
+
+    /// The visual row a point is displayed on
+    pub fn row_of(&self, point: TextPoint) -> usize {
+        self.soft_wrap.first_row(point.line) + self.soft_wrap.row_in_line(point)
+    }
+
     pub fn for_blocks(blocks: &Vec<Block>, mut line_count: usize) -> Self {
         // empty file still gets one line in the editor
         line_count = line_count.max(1);
@@ -63,6 +86,7 @@ impl Padding {
 
         Padding {
             cumulative: padding,
+            soft_wrap: SoftWrap::none(),
         }
     }
 
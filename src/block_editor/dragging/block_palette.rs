@@ -1,77 +1,230 @@
 use std::collections::HashMap;
 
-use egui::{Align2, FontId, Rect, Response, ScrollArea, Sense, Stroke, Ui, Vec2, Widget};
+use egui::{
+    Align2, Button, FontId, Key, Modifiers, PointerButton, Rect, Response, ScrollArea, Sense,
+    Stroke, StrokeKind, TextEdit, Ui, Vec2, Widget, WidgetInfo, WidgetType,
+};
 
-use super::loose_block::LooseBlock;
+use super::{
+    custom_palettes::{CustomPalettes, PalettesFormat},
+    loose_block::LooseBlock,
+    palette_search::snippet_score,
+};
 use crate::block_editor::{DragSession, MonospaceFont};
+use crate::lang::Language;
+use crate::theme;
 use crate::theme::blocks_theme::BlocksTheme;
 use crate::vscode;
-use crate::{
-    lang::{config::Snippet, Language},
-    theme,
-};
 
 pub struct BlockPalette {
     shown: bool,
     selected_palette: usize,
-    palette_names: Vec<&'static str>,
-    items: Vec<Vec<PaletteItem>>,
+    sections: Vec<PaletteSection>,
+
+    /// the palettes made by the user, for every language
+    custom: CustomPalettes,
+
+    /// the language the palette was populated for
+    language: &'static str,
+
+    /// set when the custom palettes change in a way that needs the blocks to be rebuilt
+    needs_populate: bool,
+
+    /// filters the blocks of every palette when not empty
+    search: String,
+
+    /// the index of the search result that enter inserts
+    search_selected: usize,
+
+    /// a name being typed that hasn't been saved yet.
+    /// It is saved once the name loses focus or its menu closes, instead of on every keystroke.
+    unsaved_rename: Option<UnsavedRename>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum UnsavedRename {
+    /// the selected palette
+    Palette,
+
+    /// a block in the selected palette, by index
+    Snippet(usize),
+}
+
+/// One of the palettes that can be picked from
+struct PaletteSection {
+    name: String,
+    items: Vec<PaletteItem>,
+
+    /// the index of the palette in the custom palettes, if it was made by the user
+    custom: Option<usize>,
 }
 
 struct PaletteItem {
-    id: &'static str,
+    id: String,
     block: LooseBlock,
 }
 
 impl PaletteItem {
-    fn new(snippet: &Snippet, lang: &mut Language, font: &MonospaceFont) -> Self {
+    fn new(id: &str, source: &str, lang: &mut Language, font: &MonospaceFont) -> Self {
         Self {
-            id: snippet.id,
-            block: LooseBlock::new(snippet.source, 10.0, lang, font),
+            id: id.to_string(),
+            block: LooseBlock::new(source, 10.0, lang, font),
         }
     }
 }
 
+/// Something the palette asks the editor to do
+pub enum PaletteAction {
+    /// insert the block at the cursor
+    Insert(String),
+
+    /// the dragged block was saved to the palette, so it should go back to where it was dragged from
+    Saved(DragSession),
+
+    /// the dragged block was dropped on the palette to delete it
+    Deleted(DragSession),
+}
+
+/// The palette blocks dragged out of the editor are saved to when a built in palette is selected
+const SAVED_BLOCKS_PALETTE: &str = "My Blocks";
+
 impl BlockPalette {
     pub fn new() -> Self {
         Self {
             shown: true,
             selected_palette: 0,
-            palette_names: vec![],
-            items: vec![],
+            sections: vec![],
+            custom: CustomPalettes::load(),
+            language: "",
+            needs_populate: false,
+            search: String::new(),
+            search_selected: 0,
+            unsaved_rename: None,
         }
     }
 
     pub fn populate(&mut self, lang: &mut Language, font: &MonospaceFont) {
-        self.items = lang
-            .config
+        // the blocks are about to be replaced, so their menus won't close normally
+        self.save_rename();
+
+        let config = lang.config;
+        let mut sections: Vec<PaletteSection> = config
             .palettes
             .iter()
-            .map(|palette| {
-                palette
+            .map(|palette| PaletteSection {
+                name: palette.name.to_string(),
+                items: palette
                     .snippets
                     .iter()
-                    .map(|s| PaletteItem::new(s, lang, font))
-                    .collect()
+                    .map(|s| PaletteItem::new(s.id, s.source, lang, font))
+                    .collect(),
+                custom: None,
             })
             .collect();
-        self.palette_names = lang.config.palettes.iter().map(|p| p.name).collect();
-        self.selected_palette = 0;
+
+        // the user's palettes come after the built in ones
+        for (idx, palette) in self.custom.palettes.iter().enumerate() {
+            if palette.language == config.name {
+                sections.push(PaletteSection {
+                    name: palette.name.clone(),
+                    items: palette
+                        .snippets
+                        .iter()
+                        .map(|s| PaletteItem::new(&s.id, &s.source, lang, font))
+                        .collect(),
+                    custom: Some(idx),
+                });
+            }
+        }
+
+        // keep the selected palette when the blocks are rebuilt for the same language
+        if self.language != config.name {
+            self.selected_palette = 0;
+        }
+        self.selected_palette = self.selected_palette.min(sections.len().saturating_sub(1));
+        self.sections = sections;
+        self.language = config.name;
+        self.needs_populate = false;
     }
 
     pub fn is_populated(&self) -> bool {
-        !self.items.is_empty()
+        !self.sections.is_empty() && !self.needs_populate
+    }
+
+    /// Add the palettes in a palettes file (TOML or JSON, based on the file name)
+    pub fn import_palettes(&mut self, file_name: &str, contents: &str) {
+        match CustomPalettes::parse(contents, PalettesFormat::for_file(file_name)) {
+            Ok(imported) => {
+                self.custom.merge(imported);
+                self.custom.save();
+                self.needs_populate = true;
+            }
+            Err(err) => log::error!("Failed to import palettes from {file_name}: {err}"),
+        }
+    }
+
+    pub fn export_palettes(&self) {
+        self.custom.export();
+    }
+
+    fn is_searching(&self) -> bool {
+        !self.search.trim().is_empty()
+    }
+
+    /// The blocks to show (as section and item indices): the selected palette's,
+    /// or when searching, the matches from every palette with the best first
+    fn shown_items(&self) -> Vec<(usize, usize)> {
+        if !self.is_searching() {
+            let item_count = self
+                .sections
+                .get(self.selected_palette)
+                .map_or(0, |s| s.items.len());
+            return (0..item_count)
+                .map(|item| (self.selected_palette, item))
+                .collect();
+        }
+
+        let mut matches: Vec<(u32, (usize, usize))> = self
+            .sections
+            .iter()
+            .enumerate()
+            .flat_map(|(section_idx, section)| {
+                section
+                    .items
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(idx, item)| {
+                        let score = snippet_score(&self.search, &item.id, item.block.text())?;
+                        Some((score, (section_idx, idx)))
+                    })
+            })
+            .collect();
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// The index the custom palette will have in the sections once they are populated
+    fn section_index(&self, custom_idx: usize) -> usize {
+        let built_in = self.sections.iter().filter(|s| s.custom.is_none()).count();
+        let custom_before = self.custom.palettes[..custom_idx]
+            .iter()
+            .filter(|p| p.language == self.language)
+            .count();
+        built_in + custom_before
     }
 }
 
 const H_PADDING: f32 = 10.0;
 const V_PADDING: f32 = 8.0;
 const HEADING_HEIGHT: f32 = 30.0;
+const SAVE_ZONE_HEIGHT: f32 = 40.0;
+const SEARCH_HEIGHT: f32 = 22.0;
 
 impl BlockPalette {
     pub fn widget<'a>(
         &'a mut self,
         dragged_block: &'a mut Option<DragSession>,
+        action: &'a mut Option<PaletteAction>,
         blocks_theme: BlocksTheme,
         font: &'a MonospaceFont,
     ) -> impl Widget + 'a {
@@ -87,15 +240,20 @@ impl BlockPalette {
                     let response = ui.interact(rect, id, Sense::click());
 
                     let painter = ui.painter();
-                    painter.rect_filled(painter.clip_rect(), 0.0, theme::POPUP_BACKGROUND);
+                    painter.rect_filled(
+                        painter.clip_rect(),
+                        0.0,
+                        theme::current().editor.popup_background,
+                    );
 
+                    let mut save_zone = None;
                     if self.shown {
                         ui.painter().text(
                             rect.min + Vec2::new(H_PADDING, V_PADDING),
                             Align2::LEFT_TOP,
                             "Palette:",
                             FontId::proportional(15.0),
-                            theme::INTERFACE_TEXT,
+                            theme::current().editor.interface_text,
                         );
 
                         ui.put(
@@ -106,17 +264,16 @@ impl BlockPalette {
                             |ui: &mut Ui| -> Response {
                                 egui::ComboBox::from_id_salt("palette_selector")
                                     .selected_text(
-                                        *self
-                                            .palette_names
+                                        self.sections
                                             .get(self.selected_palette)
-                                            .unwrap_or(&""),
+                                            .map_or("", |s| s.name.as_str()),
                                     )
                                     .show_ui(ui, |ui| {
-                                        for (i, palette) in self.palette_names.iter().enumerate() {
+                                        for (i, section) in self.sections.iter().enumerate() {
                                             ui.selectable_value(
                                                 &mut self.selected_palette,
                                                 i,
-                                                *palette,
+                                                &section.name,
                                             );
                                         }
                                     })
@@ -124,25 +281,65 @@ impl BlockPalette {
                             },
                         );
 
+                        self.add_menu(ui, rect);
                         self.add_arrow(ui, rect);
 
-                        self.add_blocks(
-                            (rect.min + Vec2::new(H_PADDING, HEADING_HEIGHT)).to_vec2(),
+                        let mut offset =
+                            (rect.min + Vec2::new(H_PADDING, HEADING_HEIGHT)).to_vec2();
+                        if let Some(text) = self.add_search(offset, content_size.x, ui) {
+                            *action = Some(PaletteAction::Insert(text));
+                        }
+                        offset.y += SEARCH_HEIGHT + V_PADDING;
+
+                        if dragged_block
+                            .as_ref()
+                            .is_some_and(|drag| !drag.from_palette)
+                        {
+                            let zone = Rect::from_min_size(
+                                offset.to_pos2(),
+                                Vec2::new(content_size.x - (H_PADDING * 3.0), SAVE_ZONE_HEIGHT),
+                            );
+                            if let Some(drag) = self.add_save_zone(zone, ui, dragged_block) {
+                                *action = Some(PaletteAction::Saved(drag));
+                            }
+                            save_zone = Some(zone);
+                            offset.y += SAVE_ZONE_HEIGHT + V_PADDING;
+                        }
+
+                        if let Some(text) = self.add_blocks(
+                            offset,
                             content_size.x,
                             ui,
                             dragged_block,
                             blocks_theme,
                             font,
-                        );
+                        ) {
+                            *action = Some(PaletteAction::Insert(text));
+                        }
                     } else {
                         self.add_arrow(ui, rect);
                     }
 
+                    // blocks moved out of the editor are deleted when dropped on the palette
+                    // (outside of the save zone)
+                    let over_save_zone =
+                        save_zone.is_some_and(|zone| ui.rect_contains_pointer(zone));
+                    let trashing = response.contains_pointer()
+                        && !over_save_zone
+                        && dragged_block
+                            .as_ref()
+                            .is_some_and(|drag| drag.removed_from_source());
+                    if trashing {
+                        Self::draw_trash_zone(ui);
+                    }
+
                     // if mouse released in the block palette, cancel the drag
                     if response.contains_pointer() {
                         let mouse_released = ui.input(|i| i.pointer.primary_released());
-                        if mouse_released {
-                            *dragged_block = None;
+                        if let Some(drag) = dragged_block.take_if(|_| mouse_released) {
+                            if trashing {
+                                *action = Some(PaletteAction::Deleted(drag));
+                            }
                         }
                     }
 
@@ -166,6 +363,209 @@ impl BlockPalette {
         }
     }
 
+    /// The menu for making, renaming, deleting, importing and exporting custom palettes
+    fn add_menu(&mut self, ui: &mut Ui, rect: Rect) {
+        let menu_rect = Rect::from_min_size(
+            rect.min + Vec2::new(H_PADDING + 176.0, V_PADDING - 3.0),
+            Vec2::splat(24.0),
+        );
+        ui.put(menu_rect, |ui: &mut Ui| -> Response {
+            let menu = ui.menu_button("⋯", |ui| {
+                if ui.button("New palette").clicked() {
+                    let name = format!("Custom {}", self.sections.len() + 1);
+                    let idx = self.custom.add_palette(self.language, &name);
+                    self.custom.save();
+                    self.selected_palette = self.section_index(idx);
+                    self.needs_populate = true;
+                    ui.close_menu();
+                }
+
+                // the user's own palettes can be renamed and deleted
+                let selected = self.sections.get_mut(self.selected_palette);
+                if let Some((section, custom_idx)) =
+                    selected.and_then(|s| s.custom.map(|idx| (s, idx)))
+                {
+                    ui.separator();
+                    let name = ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        let palette = &mut self.custom.palettes[custom_idx];
+                        let response = ui.text_edit_singleline(&mut palette.name);
+                        if response.changed() {
+                            section.name = palette.name.clone();
+                            self.unsaved_rename = Some(UnsavedRename::Palette);
+                        }
+                        response
+                    });
+                    if name.inner.lost_focus() {
+                        self.save_rename();
+                    }
+                    if ui.button("Delete palette").clicked() {
+                        self.custom.palettes.remove(custom_idx);
+                        self.custom.save();
+                        self.selected_palette = 0;
+                        self.needs_populate = true;
+                        ui.close_menu();
+                    }
+                }
+
+                ui.separator();
+                if ui.button("Import palettes…").clicked() {
+                    if self.custom.import() {
+                        self.needs_populate = true;
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("Export palettes…").clicked() {
+                    self.custom.export();
+                    ui.close_menu();
+                }
+            });
+            if menu.inner.is_none() && self.unsaved_rename == Some(UnsavedRename::Palette) {
+                self.save_rename();
+            }
+            menu.response.on_hover_text("Custom palettes")
+        });
+    }
+
+    /// Save a name that was typed, if it hasn't been saved yet
+    fn save_rename(&mut self) {
+        if self.unsaved_rename.take().is_some() {
+            self.custom.save();
+        }
+    }
+
+    /// The search box, which filters the blocks of every palette.
+    /// Returns the text of the block picked with enter.
+    fn add_search(&mut self, offset: Vec2, width: f32, ui: &mut Ui) -> Option<String> {
+        let search_rect = Rect::from_min_size(
+            offset.to_pos2(),
+            Vec2::new(width - (H_PADDING * 3.0), SEARCH_HEIGHT),
+        );
+        let id = ui.make_persistent_id("palette_search");
+
+        // pick a result with the arrow keys while typing
+        // (before the text edit sees them, since it would move the cursor)
+        if ui.memory(|m| m.has_focus(id)) {
+            let (up, down) = ui.input_mut(|i| {
+                (
+                    i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                    i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                )
+            });
+            if up {
+                self.search_selected = self.search_selected.saturating_sub(1);
+            }
+            if down {
+                self.search_selected += 1;
+            }
+        }
+
+        let response = ui.put(
+            search_rect,
+            TextEdit::singleline(&mut self.search)
+                .id(id)
+                .hint_text("Search blocks"),
+        );
+        if response.changed() {
+            self.search_selected = 0;
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Escape)) {
+            self.search.clear();
+        }
+
+        let results = self.shown_items();
+        if !self.is_searching() || results.is_empty() {
+            return None;
+        }
+        self.search_selected = self.search_selected.min(results.len() - 1);
+
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            let (section, idx) = results[self.search_selected];
+            let item = &self.sections[section].items[idx];
+            vscode::log_event(
+                "palette-block-search-insert",
+                HashMap::from([("type", item.id.as_str())]),
+            );
+            let text = item.block.text().to_string();
+            self.search.clear();
+            return Some(text);
+        }
+        None
+    }
+
+    /// Highlight the palette as a place to throw away the dragged block
+    fn draw_trash_zone(ui: &Ui) {
+        let error = theme::current().diagnostic.error;
+        let painter = ui.painter();
+        let visible = painter.clip_rect();
+        painter.rect_filled(visible, 0.0, error.gamma_multiply(0.15));
+        painter.rect_stroke(
+            visible,
+            0.0,
+            Stroke::new(2.0_f32, error),
+            StrokeKind::Inside,
+        );
+        painter.text(
+            visible.center_bottom() - Vec2::new(0.0, 40.0),
+            Align2::CENTER_CENTER,
+            "🗑 Drop to delete",
+            FontId::proportional(15.0),
+            error,
+        );
+    }
+
+    /// A place to drop blocks dragged from the editor to save them to the palette.
+    /// Returns the drag if the block was saved.
+    fn add_save_zone(
+        &mut self,
+        zone: Rect,
+        ui: &mut Ui,
+        dragged_block: &mut Option<DragSession>,
+    ) -> Option<DragSession> {
+        let custom_idx = self
+            .sections
+            .get(self.selected_palette)
+            .and_then(|s| s.custom);
+        let palette_name = match custom_idx {
+            Some(idx) => self.custom.palettes[idx].name.clone(),
+            None => SAVED_BLOCKS_PALETTE.to_string(),
+        };
+
+        let hovered = ui.rect_contains_pointer(zone);
+        let color = if hovered {
+            theme::current().editor.interface_text
+        } else {
+            theme::current().editor.line_numbers
+        };
+        let painter = ui.painter();
+        painter.rect_stroke(zone, 4.0, Stroke::new(1.5_f32, color), StrokeKind::Inside);
+        painter.text(
+            zone.center(),
+            Align2::CENTER_CENTER,
+            format!("Drop to save to {palette_name}"),
+            FontId::proportional(13.0),
+            color,
+        );
+
+        if !hovered || !ui.input(|i| i.pointer.primary_released()) {
+            return None;
+        }
+        let drag = dragged_block.take()?;
+
+        vscode::log_event(
+            "palette-block-save",
+            HashMap::from([("lang", self.language)]),
+        );
+        self.custom
+            .add_snippet(self.language, &palette_name, &drag.text);
+        self.custom.save();
+        // show the palette it was saved to
+        let idx = self.custom.add_palette(self.language, &palette_name);
+        self.selected_palette = self.section_index(idx);
+        self.needs_populate = true;
+        Some(drag)
+    }
+
     fn add_blocks(
         &mut self,
         mut offset: Vec2,
@@ -174,43 +574,149 @@ impl BlockPalette {
         dragged_block: &mut Option<DragSession>,
         blocks_theme: BlocksTheme,
         font: &MonospaceFont,
-    ) {
-        for item in self.items.get(self.selected_palette).unwrap_or(&vec![]) {
+    ) -> Option<String> {
+        let searching = self.is_searching();
+        let shown = self.shown_items();
+
+        let mut inserted_block = None;
+        let mut edit = None;
+        let mut save_rename = false;
+        for (shown_idx, &(section_idx, idx)) in shown.iter().enumerate() {
+            let section = &mut self.sections[section_idx];
+            let item_count = section.items.len();
+            let item = &mut section.items[idx];
             let block_rect = Rect::from_min_size(
                 offset.to_pos2(),
                 Vec2::new(width - (H_PADDING * 3.0), item.block.min_size().y),
             );
             let response = ui.put(block_rect, item.block.widget(blocks_theme, font));
-            if dragged_block.is_none() {
+            response.widget_info(|| {
+                WidgetInfo::labeled(WidgetType::Button, true, format!("Insert {}", item.id))
+            });
+
+            // items can be focused with tab and inserted at the cursor with space or enter
+            // (clicks by the mouse start a drag instead)
+            // as is the search result that enter inserts
+            let search_selected = searching && shown_idx == self.search_selected;
+            if response.has_focus() || search_selected {
+                ui.painter().rect_stroke(
+                    block_rect.expand(2.0),
+                    0.0,
+                    ui.visuals().selection.stroke,
+                    StrokeKind::Outside,
+                );
+            }
+            if response.clicked() && !response.clicked_by(PointerButton::Primary) {
+                vscode::log_event(
+                    "palette-block-insert",
+                    HashMap::from([("type", item.id.as_str())]),
+                );
+                inserted_block = Some(item.block.text().to_string());
+            }
+
+            // the user's own blocks can be renamed, reordered and deleted with a right click
+            // (when they are shown in order)
+            if section.custom.is_some() && !searching {
+                let menu = response.context_menu(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        let name = ui.text_edit_singleline(&mut item.id);
+                        if name.changed() {
+                            edit = Some(SnippetEdit::Rename(idx, item.id.clone()));
+                        }
+                        save_rename |= name.lost_focus();
+                    });
+                    if ui.add_enabled(idx > 0, Button::new("Move up")).clicked() {
+                        edit = Some(SnippetEdit::Move(idx, idx - 1));
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(idx + 1 < item_count, Button::new("Move down"))
+                        .clicked()
+                    {
+                        edit = Some(SnippetEdit::Move(idx, idx + 1));
+                        ui.close_menu();
+                    }
+                    if ui.button("Delete").clicked() {
+                        edit = Some(SnippetEdit::Delete(idx));
+                        ui.close_menu();
+                    }
+                });
+                save_rename |=
+                    menu.is_none() && self.unsaved_rename == Some(UnsavedRename::Snippet(idx));
+            }
+
+            let primary_down = ui.input(|i| i.pointer.primary_down());
+            if dragged_block.is_none() && primary_down {
                 if let Some(pointer_pos) = response.interact_pointer_pos() {
-                    vscode::log_event("palette-blog-drag", HashMap::from([("type", item.id)]));
+                    vscode::log_event(
+                        "palette-blog-drag",
+                        HashMap::from([("type", item.id.as_str())]),
+                    );
 
                     *dragged_block = Some(DragSession {
                         text: item.block.text().to_string(),
                         offset: pointer_pos - block_rect.min.to_vec2(),
+                        from_palette: true,
+                        copy: false,
                     });
                 }
             }
 
             offset.y += item.block.min_size().y + V_PADDING;
         }
+
+        let selected = self.sections.get_mut(self.selected_palette);
+        let custom = selected.and_then(|s| s.custom.map(|idx| (s, idx)));
+        if let (Some(edit), Some((section, custom_idx))) = (edit, custom) {
+            let snippets = &mut self.custom.palettes[custom_idx].snippets;
+            match edit {
+                SnippetEdit::Rename(idx, id) => {
+                    snippets[idx].id = id;
+                    self.unsaved_rename = Some(UnsavedRename::Snippet(idx));
+                }
+                SnippetEdit::Move(from, to) => {
+                    snippets.swap(from, to);
+                    section.items.swap(from, to);
+                    self.custom.save();
+                }
+                SnippetEdit::Delete(idx) => {
+                    snippets.remove(idx);
+                    section.items.remove(idx);
+                    self.custom.save();
+                }
+            }
+        }
+        if save_rename {
+            self.save_rename();
+        }
+
+        inserted_block
     }
 
     pub fn find_size(&self) -> Vec2 {
         if self.shown {
-            let mut size = Vec2::ZERO;
-            for item in self.items.get(self.selected_palette).unwrap_or(&vec![]) {
+            let mut size = Vec2::new(0.0, HEADING_HEIGHT + SEARCH_HEIGHT + V_PADDING);
+            for (section, idx) in self.shown_items() {
+                let item = &self.sections[section].items[idx];
                 size.x = f32::max(size.x, item.block.min_size().x);
                 size.y += item.block.min_size().y + V_PADDING;
             }
             size.x += H_PADDING * 3.0;
-            size.max(Vec2::new(220.0, 50.0))
+            size.max(Vec2::new(250.0, 50.0))
         } else {
             Vec2::new(40.0, 30.0)
         }
     }
 }
 
+/// A change to one of the blocks of a custom palette
+enum SnippetEdit {
+    Rename(usize, String),
+    Move(usize, usize),
+    Delete(usize),
+}
+
 struct ArrowButton {
     direction: Vec2,
 }
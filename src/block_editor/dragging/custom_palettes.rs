@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+
+/// Palettes made by users (like an instructor tailoring the blocks to a lesson),
+/// shown after the built in palettes of their language.
+///
+/// In a file they look like:
+/// ```toml
+/// [[palettes]]
+/// language = "python"
+/// name = "Lesson 3"
+///
+/// [[palettes.snippets]]
+/// id = "count"
+/// source = "for i in range(10):\n    print(i)\n"
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct CustomPalettes {
+    #[serde(default)]
+    pub palettes: Vec<CustomPalette>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomPalette {
+    /// the name of the language the palette is for (see `LanguageConfig::name`)
+    pub language: String,
+    pub name: String,
+    #[serde(default)]
+    pub snippets: Vec<CustomSnippet>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CustomSnippet {
+    pub id: String,
+
+    /// the code of the block, ending with a newline
+    pub source: String,
+}
+
+/// The format of a palettes file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PalettesFormat {
+    Json,
+    Toml,
+}
+
+impl PalettesFormat {
+    /// The format of a palettes file based on its extension (TOML if it isn't JSON)
+    pub fn for_file(file_name: &str) -> Self {
+        if file_name.to_lowercase().ends_with(".json") {
+            PalettesFormat::Json
+        } else {
+            PalettesFormat::Toml
+        }
+    }
+}
+
+impl CustomPalettes {
+    pub fn parse(contents: &str, format: PalettesFormat) -> Result<CustomPalettes, String> {
+        let mut palettes: CustomPalettes = match format {
+            PalettesFormat::Json => serde_json::from_str(contents).map_err(|err| err.to_string()),
+            PalettesFormat::Toml => toml::from_str(contents).map_err(|err| err.to_string()),
+        }?;
+
+        // blocks are dropped a line at a time, so make sure each ends with one
+        for palette in &mut palettes.palettes {
+            for snippet in &mut palette.snippets {
+                if !snippet.source.ends_with('\n') {
+                    snippet.source.push('\n');
+                }
+            }
+        }
+        Ok(palettes)
+    }
+
+    pub fn to_string(&self, format: PalettesFormat) -> String {
+        match format {
+            PalettesFormat::Json => {
+                serde_json::to_string_pretty(self).expect("palettes can be serialized")
+            }
+            PalettesFormat::Toml => toml::to_string(self).expect("palettes can be serialized"),
+        }
+    }
+
+    /// Add the imported palettes, replacing palettes with the same language and name
+    pub fn merge(&mut self, imported: CustomPalettes) {
+        for palette in imported.palettes {
+            let existing = self
+                .palettes
+                .iter_mut()
+                .find(|p| p.language == palette.language && p.name == palette.name);
+            match existing {
+                Some(existing) => *existing = palette,
+                None => self.palettes.push(palette),
+            }
+        }
+    }
+
+    /// The index of the palette of the language with the name, creating it if it doesn't exist yet
+    pub fn add_palette(&mut self, language: &str, name: &str) -> usize {
+        let existing = self
+            .palettes
+            .iter()
+            .position(|p| p.language == language && p.name == name);
+        existing.unwrap_or_else(|| {
+            self.palettes.push(CustomPalette {
+                language: language.to_string(),
+                name: name.to_string(),
+                snippets: vec![],
+            });
+            self.palettes.len() - 1
+        })
+    }
+
+    /// Save a block to a palette of the language, creating the palette if it doesn't exist yet
+    pub fn add_snippet(&mut self, language: &str, palette_name: &str, source: &str) {
+        let idx = self.add_palette(language, palette_name);
+
+        let mut source = source.to_string();
+        if !source.ends_with('\n') {
+            source.push('\n');
+        }
+        self.palettes[idx].snippets.push(CustomSnippet {
+            id: snippet_id(&source),
+            source,
+        });
+    }
+
+    /// Load the palettes saved by the native app
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> CustomPalettes {
+        let Some(path) = storage::file() else {
+            return CustomPalettes::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                CustomPalettes::parse(&contents, PalettesFormat::Toml).unwrap_or_else(|err| {
+                    log::error!("Failed to load palettes from {}: {err}", path.display());
+                    CustomPalettes::default()
+                })
+            }
+            Err(_) => CustomPalettes::default(),
+        }
+    }
+
+    /// Custom palettes aren't kept by the web editor (they are imported and exported instead)
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> CustomPalettes {
+        CustomPalettes::default()
+    }
+
+    /// Save the palettes so the native app has them the next time it is opened
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        let Some(path) = storage::file() else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, self.to_string(PalettesFormat::Toml)));
+        if let Err(err) = result {
+            log::error!("Failed to save palettes to {}: {err}", path.display());
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {}
+
+    /// Ask for a palettes file and add its palettes
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn import(&mut self) -> bool {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Palettes", &["toml", "json"])
+            .pick_file()
+        else {
+            return false;
+        };
+
+        let file_name = path.to_string_lossy();
+        let imported = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                CustomPalettes::parse(&contents, PalettesFormat::for_file(&file_name))
+            });
+        match imported {
+            Ok(imported) => {
+                self.merge(imported);
+                self.save();
+                true
+            }
+            Err(err) => {
+                log::error!("Failed to import palettes from {file_name}: {err}");
+                false
+            }
+        }
+    }
+
+    /// The host opens the file, and sends it back with `ExternalCommand::ImportPalettes`
+    #[cfg(target_arch = "wasm32")]
+    pub fn import(&mut self) -> bool {
+        crate::vscode::import_palettes();
+        false
+    }
+
+    /// Ask where to save the palettes and write them there
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("TOML", &["toml"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("palettes.toml")
+            .save_file()
+        else {
+            return;
+        };
+
+        let format = PalettesFormat::for_file(&path.to_string_lossy());
+        if let Err(err) = std::fs::write(&path, self.to_string(format)) {
+            log::error!("Failed to export palettes to {}: {err}", path.display());
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn export(&self) {
+        crate::vscode::export_palettes(&self.to_string(PalettesFormat::Toml));
+    }
+}
+
+/// A name for a saved block, from the start of its first line
+fn snippet_id(source: &str) -> String {
+    const MAX_CHARS: usize = 24;
+
+    let first_line = source.lines().map(str::trim).find(|line| !line.is_empty());
+    let first_line = first_line.unwrap_or("block");
+    let mut id: String = first_line.chars().take(MAX_CHARS).collect();
+    if first_line.chars().count() > MAX_CHARS {
+        id.push('…');
+    }
+    id
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    /// Where the native app keeps custom palettes
+    pub fn file() -> Option<PathBuf> {
+        let dir = if cfg!(target_os = "macos") {
+            PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support/Lilypad")
+        } else if cfg!(target_os = "windows") {
+            PathBuf::from(std::env::var_os("APPDATA")?).join("Lilypad")
+        } else {
+            match std::env::var_os("XDG_CONFIG_HOME") {
+                Some(config) => PathBuf::from(config).join("lilypad"),
+                None => PathBuf::from(std::env::var_os("HOME")?).join(".config/lilypad"),
+            }
+        };
+        Some(dir.join("palettes.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() {
+        let toml = r#"
+            [[palettes]]
+            language = "python"
+            name = "Lesson 3"
+
+            [[palettes.snippets]]
+            id = "count"
+            source = "for i in range(10):\n    print(i)"
+        "#;
+        let json = r#"{"palettes": [{"language": "python", "name": "Lesson 3", "snippets": [
+            {"id": "count", "source": "for i in range(10):\n    print(i)"}
+        ]}]}"#;
+
+        let from_toml = CustomPalettes::parse(toml, PalettesFormat::Toml).unwrap();
+        let from_json = CustomPalettes::parse(json, PalettesFormat::Json).unwrap();
+        assert_eq!(from_toml, from_json);
+
+        // a newline is added to the end of the source
+        assert_eq!(
+            from_toml.palettes[0].snippets[0].source,
+            "for i in range(10):\n    print(i)\n"
+        );
+
+        // and it survives a round trip
+        let saved = from_toml.to_string(PalettesFormat::Toml);
+        assert_eq!(
+            CustomPalettes::parse(&saved, PalettesFormat::Toml).unwrap(),
+            from_toml
+        );
+    }
+
+    #[test]
+    fn test_add_and_merge() {
+        let mut palettes = CustomPalettes::default();
+        palettes.add_snippet("python", "Saved", "\nwhile True:\n    pass");
+        palettes.add_snippet("python", "Saved", "x = 1\n");
+        assert_eq!(palettes.palettes.len(), 1);
+        assert_eq!(palettes.palettes[0].snippets[0].id, "while True:");
+        assert_eq!(palettes.palettes[0].snippets[1].id, "x = 1");
+
+        let mut imported = CustomPalettes::default();
+        imported.add_snippet("python", "Saved", "y = 2\n");
+        imported.add_snippet("java", "Saved", "int y = 2;\n");
+        palettes.merge(imported);
+
+        // the palette with the same language and name is replaced
+        assert_eq!(palettes.palettes.len(), 2);
+        assert_eq!(palettes.palettes[0].snippets.len(), 1);
+        assert_eq!(palettes.palettes[0].snippets[0].id, "y = 2");
+    }
+}
@@ -2,7 +2,12 @@ use egui::{Painter, Response, Sense, Ui, Vec2, Widget};
 use ropey::Rope;
 
 use crate::{
-    block_editor::{blocks::BlockTrees, rope_ext::RopeExt, text_drawer::TextDrawer, MonospaceFont},
+    block_editor::{
+        blocks::BlockTrees,
+        rope_ext::{RopeExt, RopeSliceExt},
+        text_drawer::TextDrawer,
+        MonospaceFont,
+    },
     lang::{tree_manager::TreeManager, Language},
     theme::blocks_theme::BlocksTheme,
 };
@@ -69,11 +74,19 @@ impl LooseBlock {
             .highlight(self.tree_manager.get_cursor().node(), &rope, lang);
 
         // find blocks
-        self.blocks =
-            BlockTrees::for_ts_tree(&mut self.tree_manager.get_cursor(), &rope, lang.config);
+        self.blocks = BlockTrees::for_ts_tree(
+            &mut self.tree_manager.get_cursor(),
+            &rope,
+            lang.config,
+            font.tab_width,
+        );
 
         // find dimensions
-        let max_chars = rope.lines().map(|l| l.len_chars()).max().unwrap_or(0);
+        let max_chars = rope
+            .lines()
+            .map(|l| l.display_col(l.len_chars(), font.tab_width))
+            .max()
+            .unwrap_or(0);
         let width = max_chars as f32 * font.size.x + self.interior_padding;
         let line_count = rope.len_lines() - if rope.ends_with('\n') { 1 } else { 0 };
         let height = (font.size.y * line_count as f32) + self.blocks.padding().total();
@@ -101,4 +114,9 @@ impl LooseBlock {
     pub fn text(&self) -> &str {
         &self.text
     }
+
+    /// The number of outermost blocks (more than one when a group of blocks is dragged)
+    pub fn block_count(&self) -> usize {
+        self.blocks.children_of(None).len()
+    }
 }
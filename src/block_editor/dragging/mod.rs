@@ -1,2 +1,4 @@
 pub mod block_palette;
+pub mod custom_palettes;
 pub mod loose_block;
+mod palette_search;
@@ -0,0 +1,76 @@
+/// How well the query matches the text, if every character of the query is found in the text
+/// in order (ignoring case). Higher is better: characters matched one after another and at the
+/// starts of words count for more, so `wh` matches `while` better than `with`.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let mut query_chars = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    query_chars.peek()?;
+
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev_char: Option<char> = None;
+    for c in text.chars() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            query_chars.next();
+            score += 1;
+            if prev_matched {
+                score += 4;
+            }
+            if prev_char.is_none_or(|prev| !prev.is_alphanumeric()) {
+                score += 3;
+            }
+        }
+        prev_matched = matched;
+        prev_char = Some(c);
+    }
+
+    query_chars.peek().is_none().then_some(score)
+}
+
+/// How well the query matches a palette snippet.
+/// Matches in the name count for more than ones in the code.
+pub fn snippet_score(query: &str, id: &str, source: &str) -> Option<u32> {
+    let id_score = fuzzy_score(query, id).map(|score| score * 2 + 10);
+    let source_score = fuzzy_score(query, source);
+    id_score.max(source_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "while"), None);
+        assert_eq!(fuzzy_score("wz", "while"), None);
+        assert!(fuzzy_score("WH", "while").is_some());
+        assert!(fuzzy_score("f loop", "for loop").is_some());
+
+        // consecutive characters score higher than scattered ones
+        assert!(fuzzy_score("wh", "while") > fuzzy_score("wh", "with"));
+
+        // as do characters at the start of words
+        assert!(fuzzy_score("fl", "for loop") > fuzzy_score("fl", "if else"));
+    }
+
+    #[test]
+    fn test_snippet_score() {
+        // a match in the name beats one in the code
+        let by_id = snippet_score("print", "print", "x = 1\n");
+        let by_source = snippet_score("print", "expression", "print(x)\n");
+        assert!(by_id > by_source);
+        assert!(by_source.is_some());
+        assert_eq!(
+            snippet_score("class", "while", "while x:\n    pass\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use egui::ColorImage;
+
+/// Copies html and images, which egui's clipboard doesn't support.
+/// On native the system clipboard is kept open, since on Linux what's copied
+/// is only available to paste while the clipboard that copied it is.
+pub struct Clipboard {
+    #[cfg(not(target_arch = "wasm32"))]
+    clipboard: Option<arboard::Clipboard>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clipboard {
+    pub fn new() -> Self {
+        Clipboard { clipboard: None }
+    }
+
+    /// Put the html on the clipboard, along with its plain text for apps that don't support html
+    pub fn copy_html(&mut self, html: &str, text: &str) {
+        let result = self
+            .system()
+            .and_then(|clipboard| clipboard.set_html(html, Some(text)));
+        if let Err(err) = result {
+            log::error!("Failed to copy html: {err}");
+        }
+    }
+
+    pub fn copy_image(&mut self, image: &ColorImage) {
+        let bytes: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+            .collect();
+        let image_data = arboard::ImageData {
+            width: image.size[0],
+            height: image.size[1],
+            bytes: bytes.into(),
+        };
+        let result = self
+            .system()
+            .and_then(|clipboard| clipboard.set_image(image_data));
+        if let Err(err) = result {
+            log::error!("Failed to copy image: {err}");
+        }
+    }
+
+    /// The system clipboard, opened the first time something is copied
+    fn system(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+}
+
+/// The web view writes to the clipboard, since VS Code's clipboard api only supports plain text
+#[cfg(target_arch = "wasm32")]
+impl Clipboard {
+    pub fn new() -> Self {
+        Clipboard {}
+    }
+
+    pub fn copy_html(&mut self, html: &str, text: &str) {
+        crate::vscode::copy_html(html, text);
+    }
+
+    pub fn copy_image(&mut self, image: &ColorImage) {
+        match super::image::encode_png(image) {
+            Ok(png) => crate::vscode::copy_image(&png),
+            Err(err) => log::error!("{err}"),
+        }
+    }
+}
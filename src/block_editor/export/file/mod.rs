@@ -0,0 +1,68 @@
+//! Exporting code to image files, for the command line of the native app
+
+use std::ops::Range;
+
+use ab_glyph::{Font, ScaleFont};
+
+use super::{image::encode_png, to_image, CodeView, ExportFont};
+use crate::{
+    block_editor::{blocks::BlockTrees, source::Source, text_drawer::TextDrawer},
+    theme::blocks_theme::BlocksTheme,
+};
+
+pub mod svg;
+
+impl CodeView {
+    /// A view of whole lines of a source that isn't open in an editor, highlighting it and finding its blocks
+    pub fn for_source(
+        source: &mut Source,
+        lines: Option<Range<usize>>,
+        blocks_theme: Option<BlocksTheme>,
+    ) -> CodeView {
+        let tab_width = source.indent_style().width;
+        let blocks = BlockTrees::for_ts_tree(
+            &mut source.get_tree_cursor(),
+            source.text(),
+            source.lang.config,
+            tab_width,
+        );
+        let mut highlights = TextDrawer::new();
+        highlights.highlight_source(source);
+
+        // leave out the empty line after the final linebreak
+        let text = source.text();
+        let mut line_count = text.len_lines();
+        if line_count > 1 && text.line(line_count - 1).len_chars() == 0 {
+            line_count -= 1;
+        }
+        let lines = lines.map_or(0..line_count, |lines| {
+            lines.start..lines.end.min(line_count)
+        });
+        CodeView::for_lines(
+            lines,
+            source.text(),
+            &highlights,
+            &blocks,
+            blocks_theme,
+            tab_width,
+        )
+    }
+
+    /// Number the lines (with their line numbers in the source)
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> CodeView {
+        self.line_numbers = line_numbers;
+        self
+    }
+}
+
+impl ExportFont {
+    /// The distance from the top of a line to its baseline
+    fn ascent(&self) -> f32 {
+        self.font.as_scaled(self.size).ascent()
+    }
+}
+
+/// A PNG file of the view, drawn with `scale` pixels per point
+pub fn to_png(view: &CodeView, font: &ExportFont, scale: f32) -> Result<Vec<u8>, String> {
+    encode_png(&to_image(view, font, scale))
+}
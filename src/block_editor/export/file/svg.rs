@@ -0,0 +1,179 @@
+use std::fmt::Write;
+
+use egui::Color32;
+
+use crate::block_editor::{
+    blocks::BLOCK_CONFIG,
+    export::{html::escape, CodeView, ExportFont},
+    rope_ext::{display_widths, is_invisible_in_cluster},
+};
+
+/// A standalone SVG image of the view, with the text kept as text so it stays sharp and searchable.
+/// The layout matches `to_image` with the same font.
+pub fn to_svg(view: &CodeView, font: &ExportFont) -> String {
+    let char_size = font.char_size();
+    let layout = view.layout(char_size);
+    let size = layout.size.ceil();
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = size.x,
+        h = size.y,
+    );
+    let _ = writeln!(
+        svg,
+        "<rect width=\"100%\" height=\"100%\"{}/>",
+        paint("fill", view.background)
+    );
+
+    // blocks (svg strokes are centered on the edge, so shrink the rects to keep the stroke inside)
+    if !layout.outlines.is_empty() {
+        let inset = BLOCK_CONFIG.stroke_width / 2.0;
+        let _ = writeln!(
+            svg,
+            "<g fill=\"none\" stroke-width=\"{}\">",
+            BLOCK_CONFIG.stroke_width
+        );
+        for outline in &layout.outlines {
+            let rect = outline.rect.shrink(inset);
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{:.2}\"{}/>",
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                (outline.rounding - inset).max(0.0),
+                paint("stroke", outline.color),
+            );
+        }
+        svg.push_str("</g>\n");
+    }
+
+    // text, with every word placed in its column so it lines up even if the font is substituted
+    let _ = writeln!(
+        svg,
+        "<g font-family=\"{}, Menlo, Consolas, monospace\" font-size=\"{}\" xml:space=\"preserve\">",
+        escape(&font.name),
+        font.size,
+    );
+    let ascent = font.ascent();
+    for (line, chunks) in view.lines.iter().enumerate() {
+        let baseline = layout.text_origin.y + layout.line_tops[line] + ascent;
+
+        if let Some(right) = layout.line_numbers_right {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"end\"{}>{}</text>",
+                right,
+                baseline,
+                paint("fill", view.line_number_color),
+                view.line_number(line),
+            );
+        }
+
+        let words = words(chunks);
+        if words.is_empty() {
+            continue;
+        }
+        let _ = write!(svg, "<text y=\"{baseline:.2}\">");
+        for (col, word, color) in words {
+            let x = layout.text_origin.x + col as f32 * char_size.x;
+            let _ = write!(
+                svg,
+                "<tspan x=\"{:.2}\"{}>{}</tspan>",
+                x,
+                paint("fill", color),
+                escape(&word)
+            );
+        }
+        svg.push_str("</text>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+
+    svg
+}
+
+/// The runs of non-whitespace characters of the same color in the line, with the column each starts at
+fn words(chunks: &[(String, Color32)]) -> Vec<(usize, String, Color32)> {
+    let chars = chunks
+        .iter()
+        .flat_map(|(text, color)| text.chars().map(move |c| (c, *color)));
+    let widths = display_widths(chunks.iter().flat_map(|(text, _)| text.chars()), 1);
+
+    let mut words: Vec<(usize, String, Color32)> = vec![];
+    let mut in_word = false;
+    let mut col = 0;
+    for ((c, color), (_, width)) in chars.zip(widths) {
+        if c.is_whitespace() {
+            in_word = false;
+        } else if is_invisible_in_cluster(c, width) && in_word {
+            // combining characters stay with the character before them
+            words.last_mut().unwrap().1.push(c);
+        } else {
+            match words.last_mut() {
+                Some((_, word, last_color)) if in_word && *last_color == color => word.push(c),
+                _ => words.push((col, c.to_string(), color)),
+            }
+            in_word = true;
+        }
+        col += width;
+    }
+    words
+}
+
+/// A fill or stroke attribute for the color, with its opacity separate since not every viewer supports rgba
+fn paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = format!(" {attribute}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a != 255 {
+        let _ = write!(paint, " {attribute}-opacity=\"{:.3}\"", a as f32 / 255.0);
+    }
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_editor::export::tests::{block, view};
+
+    #[test]
+    fn test_words() {
+        let red = Color32::RED;
+        let blue = Color32::BLUE;
+        let chunks = vec![
+            ("  if".to_string(), red),
+            (" x".to_string(), blue),
+            ("==".to_string(), red),
+            ("y :".to_string(), blue),
+        ];
+        assert_eq!(
+            words(&chunks),
+            vec![
+                (2, "if".to_string(), red),
+                (5, "x".to_string(), blue),
+                (6, "==".to_string(), red),
+                (8, "y".to_string(), blue),
+                (10, ":".to_string(), blue),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let view = view(
+            &["if x < 1:", "    y"],
+            vec![block(0, 0, 2, vec![block(1, 4, 1, vec![])])],
+        )
+        .with_line_numbers(true);
+        let svg = to_svg(&view, &ExportFont::monospace(14.0));
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("<rect x=").count(), 2);
+        assert!(svg.contains("&lt;"));
+        assert!(svg.contains("text-anchor=\"end\" fill=\"#"));
+        assert!(svg.contains(">2</text>"));
+    }
+}
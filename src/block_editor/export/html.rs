@@ -0,0 +1,180 @@
+use std::{fmt::Write, ops::Range};
+
+use egui::Color32;
+
+use super::CodeView;
+use crate::block_editor::blocks::{Block, BLOCK_CONFIG};
+
+/// HTML for the view, styled inline so it keeps its colors when pasted into documents and slides.
+/// Blocks are nested boxes with the same borders as in the editor.
+pub fn to_html(view: &CodeView, font_family: &str) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<div style=\"background-color: {}; color: {}; font-family: '{}', Menlo, Consolas, monospace; \
+        white-space: pre; line-height: 1.4; padding: 8px;\">",
+        css_color(view.background),
+        css_color(view.foreground),
+        escape(font_family),
+    );
+
+    let blocks: &[Block] = match view.blocks_theme {
+        Some(_) => &view.blocks,
+        None => &[],
+    };
+    write_lines(view, 0..view.lines.len(), blocks, 0, 0, &mut html);
+
+    html.push_str("</div>");
+    html
+}
+
+/// Write the lines, wrapping the blocks in boxes.
+/// The boxes are already indented by `indent` columns, so that much of each line's indent is left out.
+fn write_lines(
+    view: &CodeView,
+    lines: Range<usize>,
+    blocks: &[Block],
+    level: usize,
+    indent: usize,
+    html: &mut String,
+) {
+    let mut line = lines.start;
+    for block in blocks {
+        while line < block.line {
+            write_line(view, line, indent, html);
+            line += 1;
+        }
+
+        let block_lines = block.line..(block.line + block.height).min(lines.end);
+        let color = view
+            .blocks_theme
+            .and_then(|theme| (theme.color_for)(block.syntax_type, level));
+        match color {
+            Some(color) => {
+                let rounding = f32::max(
+                    BLOCK_CONFIG.outer_corner_rad - (level as f32 * BLOCK_CONFIG.inner_pad),
+                    BLOCK_CONFIG.min_corner_rad,
+                );
+                let _ = write!(
+                    html,
+                    "<div style=\"border: {}px solid {}; border-radius: {}px; \
+                    padding: {}px 0; margin: {}px 0; margin-left: {}ch;\">",
+                    BLOCK_CONFIG.stroke_width,
+                    css_color(color),
+                    rounding,
+                    BLOCK_CONFIG.inner_pad,
+                    BLOCK_CONFIG.top_outer_pad,
+                    block.display_col.saturating_sub(indent),
+                );
+                write_lines(
+                    view,
+                    block_lines,
+                    &block.children,
+                    level + 1,
+                    indent.max(block.display_col),
+                    html,
+                );
+                html.push_str("</div>");
+            }
+            // blocks without a color are only there to hold their children
+            None => write_lines(view, block_lines, &block.children, level + 1, indent, html),
+        }
+        line = block.line + block.height;
+    }
+
+    while line < lines.end {
+        write_line(view, line, indent, html);
+        line += 1;
+    }
+}
+
+fn write_line(view: &CodeView, line: usize, indent: usize, html: &mut String) {
+    html.push_str("<div>");
+
+    let mut indent = indent;
+    let mut empty = true;
+    for (text, color) in &view.lines[line] {
+        let skip = text.chars().take_while(|c| *c == ' ').count().min(indent);
+        indent -= skip;
+        let text = &text[skip..];
+        if text.is_empty() {
+            continue;
+        }
+
+        empty = false;
+        let _ = write!(
+            html,
+            "<span style=\"color: {};\">{}</span>",
+            css_color(*color),
+            escape(text)
+        );
+    }
+
+    // empty divs collapse, so give blank lines some content
+    if empty {
+        html.push_str("<br>");
+    }
+    html.push_str("</div>");
+}
+
+fn css_color(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a == 255 {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    } else {
+        format!("rgba({r}, {g}, {b}, {:.3})", a as f32 / 255.0)
+    }
+}
+
+pub(super) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_editor::export::tests::{block, view};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a < b && c > \"d\""),
+            "a &lt; b &amp;&amp; c &gt; &quot;d&quot;"
+        );
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let view = view(
+            &["x = 1", "if x:", "    if y:", "        z", "w"],
+            vec![block(1, 0, 3, vec![block(2, 4, 2, vec![])])],
+        );
+        let html = to_html(&view, "Hack");
+
+        // the inner block is indented relative to the outer one, and its lines relative to it
+        assert_eq!(html.matches("<div style=\"border").count(), 2);
+        assert!(html.contains("margin-left: 4ch;"));
+        assert!(html.contains("<div><span style=\"color: #ffffff;\">    z</span></div>"));
+        assert!(html.contains("<div><span style=\"color: #ffffff;\">if y:</span></div>"));
+
+        // lines after the blocks are outside of them
+        assert!(
+            html.ends_with("</div></div><div><span style=\"color: #ffffff;\">w</span></div></div>")
+        );
+    }
+
+    #[test]
+    fn test_without_blocks() {
+        let mut view = view(&["if x:", "", "    y"], vec![block(0, 0, 3, vec![])]);
+        view.blocks_theme = None;
+        let html = to_html(&view, "Hack");
+
+        assert!(!html.contains("border"));
+        assert!(html.contains("<div><br></div>"));
+        assert!(html.contains("<span style=\"color: #ffffff;\">    y</span>"));
+    }
+}
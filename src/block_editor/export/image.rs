@@ -0,0 +1,222 @@
+use ab_glyph::{point, Font, ScaleFont};
+use egui::{Color32, ColorImage, Pos2, Rect, Vec2};
+
+use super::{CodeView, ExportFont};
+use crate::block_editor::{
+    blocks::BLOCK_CONFIG,
+    rope_ext::{display_widths, is_invisible_in_cluster},
+};
+
+/// Render the view to an image with `scale` pixels per point (2 or more looks sharp on most screens)
+pub fn to_image(view: &CodeView, font: &ExportFont, scale: f32) -> ColorImage {
+    let char_size = font.char_size();
+    let layout = view.layout(char_size);
+
+    let size = (layout.size * scale).ceil();
+    let mut image = ColorImage::new([size.x as usize, size.y as usize], view.background);
+
+    // blocks
+    for outline in &layout.outlines {
+        stroke_rounded_rect(
+            &mut image,
+            Rect::from_min_max(outline.rect.min * scale, outline.rect.max * scale),
+            outline.rounding * scale,
+            BLOCK_CONFIG.stroke_width * scale,
+            outline.color,
+        );
+    }
+
+    // line numbers
+    if let Some(right) = layout.line_numbers_right {
+        for line in 0..view.lines.len() {
+            let number = view.line_number(line);
+            let x = right - number.len() as f32 * char_size.x;
+            let top = layout.text_origin.y + layout.line_tops[line];
+            let chunks = [(number, view.line_number_color)];
+            draw_line(&mut image, font, &chunks, Pos2::new(x, top), scale);
+        }
+    }
+
+    // text
+    for (line, chunks) in view.lines.iter().enumerate() {
+        let top = layout.text_origin.y + layout.line_tops[line];
+        draw_line(
+            &mut image,
+            font,
+            chunks,
+            Pos2::new(layout.text_origin.x, top),
+            scale,
+        );
+    }
+
+    image
+}
+
+/// Draw a line of text with its top left corner at `pos` (in points), keeping every character in its column
+fn draw_line(
+    image: &mut ColorImage,
+    font: &ExportFont,
+    chunks: &[(String, Color32)],
+    pos: Pos2,
+    scale: f32,
+) {
+    let char_width = font.char_size().x;
+    let scaled_font = font.font.as_scaled(font.size * scale);
+    let baseline = pos.y * scale + scaled_font.ascent();
+
+    let chars = chunks
+        .iter()
+        .flat_map(|(text, color)| text.chars().map(move |c| (c, *color)));
+    let widths = display_widths(chunks.iter().flat_map(|(text, _)| text.chars()), 1);
+    let mut col = 0;
+    for ((c, color), (_, width)) in chars.zip(widths) {
+        if !c.is_whitespace() && !is_invisible_in_cluster(c, width) {
+            let x = (pos.x + col as f32 * char_width) * scale;
+            let glyph = scaled_font.scaled_glyph(c);
+            let glyph = ab_glyph::Glyph {
+                position: point(x, baseline),
+                ..glyph
+            };
+            if let Some(outlined) = font.font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, coverage| {
+                    let x = bounds.min.x as i64 + x as i64;
+                    let y = bounds.min.y as i64 + y as i64;
+                    blend(image, x, y, color, coverage);
+                });
+            }
+        }
+        col += width;
+    }
+}
+
+/// Encode the image as a PNG file
+pub fn encode_png(image: &ColorImage) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, image.size[0] as u32, image.size[1] as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let rgba: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .map_err(|err| format!("Failed to encode PNG: {err}"))?;
+
+    Ok(bytes)
+}
+
+/// Draw the border of a rounded rectangle, inside of the rectangle
+fn stroke_rounded_rect(
+    image: &mut ColorImage,
+    rect: Rect,
+    rounding: f32,
+    stroke_width: f32,
+    color: Color32,
+) {
+    let rounding = rounding.min(rect.width() / 2.0).min(rect.height() / 2.0);
+    let min_x = rect.min.x.floor().max(0.0) as i64;
+    let min_y = rect.min.y.floor().max(0.0) as i64;
+    let max_x = rect.max.x.ceil() as i64;
+    let max_y = rect.max.y.ceil() as i64;
+
+    // only pixels within the stroke (and the rounding, at the corners) of an edge can be covered,
+    // so the middle of each row is skipped unless the row is at the top or bottom
+    let band = (stroke_width + rounding).ceil() as i64 + 1;
+    for y in min_y..max_y {
+        let columns = if y < min_y + band || y >= max_y - band {
+            [min_x..max_x, max_x..max_x]
+        } else {
+            let left_end = (min_x + band).min(max_x);
+            [min_x..left_end, (max_x - band).max(left_end)..max_x]
+        };
+        for x in columns.into_iter().flatten() {
+            // antialias by how much of the pixel is between the outer and inner edges
+            let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let distance = rounded_rect_distance(center, rect, rounding);
+            let outer = (0.5 - distance).clamp(0.0, 1.0);
+            let inner = (0.5 - (distance + stroke_width)).clamp(0.0, 1.0);
+            let coverage = outer - inner;
+            if coverage > 0.0 {
+                blend(image, x, y, color, coverage);
+            }
+        }
+    }
+}
+
+/// The signed distance from the point to the edge of a rounded rectangle (negative inside of it)
+fn rounded_rect_distance(point: Pos2, rect: Rect, rounding: f32) -> f32 {
+    let half_size = rect.size() / 2.0;
+    let from_center = (point - rect.center()).abs();
+    let q = from_center - (half_size - Vec2::splat(rounding));
+    let outside = q.max(Vec2::ZERO).length();
+    let inside = q.x.max(q.y).min(0.0);
+    outside + inside - rounding
+}
+
+/// Blend the color over the pixel, with the coverage scaling its opacity
+fn blend(image: &mut ColorImage, x: i64, y: i64, color: Color32, coverage: f32) {
+    let [width, height] = image.size;
+    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+        return;
+    }
+
+    let pixel = &mut image.pixels[y as usize * width + x as usize];
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let [dst_r, dst_g, dst_b, dst_a] = pixel.to_srgba_unmultiplied();
+    let alpha = coverage.clamp(0.0, 1.0) * (a as f32 / 255.0);
+    let mix = |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round() as u8;
+    let out_a = (255.0 * alpha + dst_a as f32 * (1.0 - alpha)).round() as u8;
+    *pixel = Color32::from_rgba_unmultiplied(mix(r, dst_r), mix(g, dst_g), mix(b, dst_b), out_a);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounded_rect_distance() {
+        let rect = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(20.0, 10.0));
+
+        // along the straight edges
+        assert_eq!(rounded_rect_distance(Pos2::new(10.0, 5.0), rect, 2.0), -5.0);
+        assert_eq!(rounded_rect_distance(Pos2::new(10.0, 0.0), rect, 2.0), 0.0);
+        assert_eq!(rounded_rect_distance(Pos2::new(25.0, 5.0), rect, 2.0), 5.0);
+
+        // the corner is cut off by the rounding
+        assert!(rounded_rect_distance(Pos2::new(0.0, 0.0), rect, 2.0) > 0.0);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let image = ColorImage::new([3, 2], Color32::RED);
+        let png = encode_png(&image).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_stroke_rounded_rect() {
+        let mut image = ColorImage::new([40, 30], Color32::TRANSPARENT);
+        let rect = Rect::from_min_max(Pos2::new(5.0, 5.0), Pos2::new(35.0, 25.0));
+        stroke_rounded_rect(&mut image, rect, 4.0, 2.0, Color32::WHITE);
+        let painted = |x: usize, y: usize| image.pixels[y * 40 + x] != Color32::TRANSPARENT;
+
+        // every edge is drawn
+        assert!(painted(20, 5));
+        assert!(painted(20, 24));
+        assert!(painted(5, 15));
+        assert!(painted(34, 15));
+        assert!(painted(6, 15));
+
+        // but not inside of the stroke, past the rounded corners, or outside
+        assert!(!painted(20, 15));
+        assert!(!painted(8, 15));
+        assert!(!painted(31, 15));
+        assert!(!painted(5, 5));
+        assert!(!painted(2, 15));
+    }
+}
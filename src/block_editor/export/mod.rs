@@ -0,0 +1,499 @@
+use std::ops::Range;
+
+use ab_glyph::{Font, FontArc, ScaleFont};
+use egui::{Color32, FontDefinitions, Rect, Vec2};
+use ropey::Rope;
+
+use super::{
+    blocks::{block_outlines, Block, BlockTrees, BlockType, Padding, BLOCK_CONFIG},
+    rope_ext::{display_widths, RopeSliceExt},
+    text_drawer::TextDrawer,
+    text_range::TextRange,
+    OUTER_PAD, TEXT_L_PAD,
+};
+use crate::theme::{self, blocks_theme::BlocksTheme};
+
+pub mod clipboard;
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)] // only used by the command line of the native app
+pub mod file;
+mod html;
+mod image;
+
+pub use html::to_html;
+pub use image::to_image;
+
+/// The formats code can be copied in, besides plain text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyFormat {
+    Html,
+    Image,
+}
+
+/// Some lines of code with their syntax colors and blocks, to be rendered outside of the editor.
+/// Tabs are expanded to spaces and the indent shared by all of the lines is removed.
+pub struct CodeView {
+    /// the text of each line (without the linebreak), split into runs of the same color
+    lines: Vec<Vec<(String, Color32)>>,
+
+    /// the blocks entirely within the lines, with their lines and columns relative to the view
+    blocks: Vec<Block>,
+
+    /// colors for the blocks, or None to leave them out
+    blocks_theme: Option<BlocksTheme>,
+
+    /// the colors of the theme when the view was made
+    background: Color32,
+    foreground: Color32,
+    line_number_color: Color32,
+
+    /// the line in the source the view starts at
+    first_line: usize,
+
+    /// if line numbers are drawn in a gutter to the left of the code
+    line_numbers: bool,
+}
+
+impl CodeView {
+    /// A view of the selection. Selections within a single line are shown as just the selected text,
+    /// otherwise every line the selection touches is shown (so the blocks can be).
+    pub fn for_selection(
+        selection: TextRange,
+        source: &Rope,
+        highlights: &TextDrawer,
+        blocks: &BlockTrees,
+        blocks_theme: Option<BlocksTheme>,
+        tab_width: usize,
+    ) -> CodeView {
+        let selection = selection.ordered();
+        if selection.start.line == selection.end.line {
+            let cols = selection.start.col..selection.end.col;
+            let line = slice_chunks(highlights.line_chunks(selection.start.line), cols);
+            return CodeView::new(
+                vec![line],
+                selection.start.line,
+                vec![],
+                blocks_theme,
+                tab_width,
+            );
+        }
+
+        // a selection ending at the start of a line doesn't include it
+        let end_line = if selection.end.col == 0 {
+            selection.end.line
+        } else {
+            selection.end.line + 1
+        };
+        CodeView::for_lines(
+            selection.start.line..end_line,
+            source,
+            highlights,
+            blocks,
+            blocks_theme,
+            tab_width,
+        )
+    }
+
+    /// A view of whole lines of the source
+    pub fn for_lines(
+        lines: Range<usize>,
+        source: &Rope,
+        highlights: &TextDrawer,
+        blocks: &BlockTrees,
+        blocks_theme: Option<BlocksTheme>,
+        tab_width: usize,
+    ) -> CodeView {
+        let lines = lines.start..lines.end.min(source.len_lines());
+        let line_chunks = lines
+            .clone()
+            .map(|line| {
+                let len = source.line(line).len_chars_no_linebreak();
+                slice_chunks(highlights.line_chunks(line), 0..len)
+            })
+            .collect();
+        let blocks = blocks_within(blocks, None, &lines);
+
+        CodeView::new(line_chunks, lines.start, blocks, blocks_theme, tab_width)
+    }
+
+    fn new(
+        lines: Vec<Vec<(String, Color32)>>,
+        first_line: usize,
+        mut blocks: Vec<Block>,
+        blocks_theme: Option<BlocksTheme>,
+        tab_width: usize,
+    ) -> CodeView {
+        let mut lines: Vec<Vec<(String, Color32)>> = lines
+            .into_iter()
+            .map(|line| expand_tabs(line, tab_width))
+            .collect();
+
+        // remove the indent shared by every non-blank line
+        let indent = lines
+            .iter()
+            .filter_map(|line| {
+                let text: String = line.iter().map(|(text, _)| text.as_str()).collect();
+                let trimmed = text.trim_start_matches(' ');
+                (!trimmed.is_empty()).then_some(text.len() - trimmed.len())
+            })
+            .min()
+            .unwrap_or(0);
+        for line in &mut lines {
+            remove_indent(line, indent);
+        }
+        remove_block_indent(&mut blocks, indent);
+
+        CodeView {
+            lines,
+            blocks,
+            blocks_theme,
+            background: theme::current().editor.background,
+            foreground: theme::current().syntax.default,
+            line_number_color: theme::current().editor.line_numbers,
+            first_line,
+            line_numbers: false,
+        }
+    }
+
+    /// The number shown next to the line of the view
+    fn line_number(&self, line: usize) -> String {
+        (self.first_line + line + 1).to_string()
+    }
+
+    /// The plain text of the view, with a newline after each line
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| {
+                let mut text: String = line.iter().map(|(text, _)| text.as_str()).collect();
+                text.push('\n');
+                text
+            })
+            .collect()
+    }
+
+    /// The number of columns in the longest line
+    fn max_cols(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|line| {
+                display_widths(line.iter().flat_map(|(text, _)| text.chars()), 1)
+                    .map(|(_, width)| width)
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Where everything is drawn when each character takes up `char_size`
+    fn layout(&self, char_size: Vec2) -> Layout {
+        // without blocks there is no space to leave around them
+        let no_blocks = vec![];
+        let blocks = match self.blocks_theme {
+            Some(_) => &self.blocks,
+            None => &no_blocks,
+        };
+        let padding = Padding::for_blocks(blocks, self.lines.len());
+        let line_tops = (0..self.lines.len())
+            .map(|line| line as f32 * char_size.y + padding.cumulative(line))
+            .collect();
+
+        // leave room for the right edge of every nested block
+        let text_width = TEXT_L_PAD + self.max_cols() as f32 * char_size.x;
+        let blocks_width = text_width + (depth(&self.blocks) as f32 + 1.0) * block_margin();
+
+        // the line numbers are right aligned in a gutter as wide as the longest one
+        let gutter_width = if self.line_numbers && !self.lines.is_empty() {
+            let digits = self.line_number(self.lines.len() - 1).len();
+            digits as f32 * char_size.x + OUTER_PAD
+        } else {
+            0.0
+        };
+        let origin = Vec2::new(MARGIN + gutter_width, MARGIN);
+
+        let mut outlines = vec![];
+        if let Some(blocks_theme) = self.blocks_theme {
+            for outline in block_outlines(&self.blocks, &padding, blocks_width, char_size) {
+                let Some(color) =
+                    (blocks_theme.color_for)(outline.block.syntax_type, outline.level)
+                else {
+                    continue;
+                };
+                outlines.push(Outline {
+                    rect: outline.rect.translate(origin),
+                    rounding: outline.rounding,
+                    color,
+                });
+            }
+        }
+
+        let width = if self.blocks_theme.is_some() {
+            blocks_width
+        } else {
+            text_width
+        };
+        let height = self.lines.len() as f32 * char_size.y + padding.total();
+        Layout {
+            size: Vec2::new(width + gutter_width, height) + Vec2::splat(MARGIN * 2.0),
+            text_origin: origin + Vec2::new(TEXT_L_PAD, 0.0),
+            line_numbers_right: (gutter_width > 0.0).then_some(MARGIN + gutter_width - OUTER_PAD),
+            line_tops,
+            outlines,
+        }
+    }
+}
+
+/// Space around the code in rendered images
+const MARGIN: f32 = 8.0;
+
+/// The space between the right edges of nested blocks
+fn block_margin() -> f32 {
+    BLOCK_CONFIG.total_inner_pad()
+}
+
+/// Where the parts of a view are drawn
+struct Layout {
+    size: Vec2,
+
+    /// where the first character of the first line is drawn
+    text_origin: Vec2,
+
+    /// the x position line numbers are right aligned to, if they are drawn
+    line_numbers_right: Option<f32>,
+
+    /// the top of each line, relative to the text origin
+    line_tops: Vec<f32>,
+
+    /// block borders, in the order they are drawn
+    outlines: Vec<Outline>,
+}
+
+struct Outline {
+    rect: Rect,
+    rounding: f32,
+    color: Color32,
+}
+
+/// The font used to render images, so they look the same wherever they are made
+pub struct ExportFont {
+    font: FontArc,
+
+    /// the name of the font, for formats that refer to it instead of drawing it
+    #[cfg(not(target_arch = "wasm32"))]
+    name: String,
+
+    /// the font size in pixels
+    size: f32,
+}
+
+impl ExportFont {
+    /// The default monospace font of the editor at the size
+    pub fn monospace(size: f32) -> ExportFont {
+        let definitions = FontDefinitions::default();
+        let name = definitions
+            .families
+            .get(&egui::FontFamily::Monospace)
+            .and_then(|names| names.first())
+            .expect("default fonts include a monospace font");
+        let data = &definitions.font_data[name];
+        let font = match &data.font {
+            std::borrow::Cow::Borrowed(bytes) => FontArc::try_from_slice(bytes),
+            std::borrow::Cow::Owned(bytes) => FontArc::try_from_vec(bytes.clone()),
+        }
+        .expect("default monospace font is valid");
+
+        ExportFont {
+            font,
+            #[cfg(not(target_arch = "wasm32"))]
+            name: name.clone(),
+            size,
+        }
+    }
+
+    /// The width of a column and the height of a line
+    fn char_size(&self) -> Vec2 {
+        let scaled = self.font.as_scaled(self.size);
+        Vec2::new(
+            scaled.h_advance(self.font.glyph_id('A')),
+            (scaled.height() + scaled.line_gap()).ceil(),
+        )
+    }
+}
+
+/// The part of each chunk within the char columns, with each chunk's color from the current theme
+fn slice_chunks(
+    chunks: &[(String, theme::SyntaxColor)],
+    cols: Range<usize>,
+) -> Vec<(String, Color32)> {
+    let syntax = &theme::current().syntax;
+    let mut sliced = vec![];
+    let mut col = 0;
+    for (text, color) in chunks {
+        let len = text.chars().count();
+        let start = cols.start.clamp(col, col + len) - col;
+        let end = cols.end.clamp(col, col + len) - col;
+        if start < end {
+            let part: String = text.chars().skip(start).take(end - start).collect();
+            sliced.push((part, syntax.get(*color)));
+        }
+        col += len;
+    }
+    sliced
+}
+
+/// Replace tabs with spaces up to the next tab stop
+fn expand_tabs(line: Vec<(String, Color32)>, tab_width: usize) -> Vec<(String, Color32)> {
+    let chars = line
+        .iter()
+        .flat_map(|(text, color)| text.chars().map(move |c| (c, *color)));
+    let widths = display_widths(line.iter().flat_map(|(text, _)| text.chars()), tab_width);
+
+    let mut expanded: Vec<(String, Color32)> = vec![];
+    for ((c, color), (_, width)) in chars.zip(widths) {
+        if expanded.last().is_none_or(|(_, last)| *last != color) {
+            expanded.push((String::new(), color));
+        }
+        let text = &mut expanded.last_mut().unwrap().0;
+        if c == '\t' {
+            text.extend(std::iter::repeat_n(' ', width));
+        } else {
+            text.push(c);
+        }
+    }
+    expanded
+}
+
+/// Remove the first `indent` spaces of the line (which has no tabs)
+fn remove_indent(line: &mut Vec<(String, Color32)>, mut indent: usize) {
+    for (text, _) in line.iter_mut() {
+        let spaces = text.chars().take_while(|c| *c == ' ').count().min(indent);
+        text.drain(..spaces);
+        indent -= spaces;
+        if !text.is_empty() || indent == 0 {
+            break;
+        }
+    }
+    line.retain(|(text, _)| !text.is_empty());
+}
+
+fn remove_block_indent(blocks: &mut [Block], indent: usize) {
+    for block in blocks {
+        block.display_col = block.display_col.saturating_sub(indent);
+        block.col = block.col.saturating_sub(indent);
+        remove_block_indent(&mut block.children, indent);
+    }
+}
+
+/// Copies of the blocks inside the parent that are entirely within the lines, moved to start at the first line.
+/// The blocks within ones that are only partly in the lines are included instead of them.
+fn blocks_within(trees: &BlockTrees, parent: Option<&Block>, lines: &Range<usize>) -> Vec<Block> {
+    let mut within = vec![];
+    for block in trees.children_of(parent) {
+        // skip blocks that don't overlap at all
+        if block.line + block.height <= lines.start || lines.end <= block.line {
+            continue;
+        }
+
+        let children = blocks_within(trees, Some(block), lines);
+        if lines.start <= block.line && block.line + block.height <= lines.end {
+            within.push(Block {
+                line: block.line - lines.start,
+                col: block.col,
+                display_col: block.display_col,
+                height: block.height,
+                syntax_type: block.syntax_type,
+                children,
+            });
+        } else {
+            within.extend(children);
+        }
+    }
+    within
+}
+
+/// The most blocks nested inside each other
+fn depth(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .map(|block| {
+            let own = usize::from(block.syntax_type != BlockType::Divider);
+            own + depth(&block.children)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// A view of white lines on black, with blocks in grayscale
+    pub fn view(lines: &[&str], blocks: Vec<Block>) -> CodeView {
+        CodeView {
+            lines: lines
+                .iter()
+                .map(|line| vec![(line.to_string(), Color32::WHITE)])
+                .collect(),
+            blocks,
+            blocks_theme: Some(BlocksTheme::for_str("depth_grayscale")),
+            background: Color32::BLACK,
+            foreground: Color32::WHITE,
+            line_number_color: Color32::GRAY,
+            first_line: 0,
+            line_numbers: false,
+        }
+    }
+
+    pub fn block(line: usize, display_col: usize, height: usize, children: Vec<Block>) -> Block {
+        Block {
+            line,
+            col: display_col,
+            display_col,
+            height,
+            syntax_type: BlockType::If,
+            children,
+        }
+    }
+
+    #[test]
+    fn test_expand_tabs_and_remove_indent() {
+        let red = Color32::RED;
+        let blue = Color32::BLUE;
+
+        let mut line = expand_tabs(
+            vec![("\tif".to_string(), red), ("\tx".to_string(), blue)],
+            4,
+        );
+        assert_eq!(
+            line,
+            vec![("    if".to_string(), red), ("  x".to_string(), blue)]
+        );
+
+        remove_indent(&mut line, 2);
+        assert_eq!(
+            line,
+            vec![("  if".to_string(), red), ("  x".to_string(), blue)]
+        );
+
+        // indent split across chunks
+        let mut line = vec![(" ".to_string(), red), ("  x".to_string(), blue)];
+        remove_indent(&mut line, 2);
+        assert_eq!(line, vec![(" x".to_string(), blue)]);
+    }
+
+    #[test]
+    fn test_slice_chunks() {
+        use theme::SyntaxColor;
+
+        let chunks = vec![
+            ("def".to_string(), SyntaxColor::Keyword),
+            (" ".to_string(), SyntaxColor::Default),
+            ("main".to_string(), SyntaxColor::Function),
+        ];
+        let sliced: Vec<String> = slice_chunks(&chunks, 2..6)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(sliced, vec!["f", " ", "ma"]);
+    }
+}
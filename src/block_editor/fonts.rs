@@ -0,0 +1,248 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+};
+
+use egui::{Context, FontData, FontDefinitions, FontFamily};
+use log::warn;
+
+/// Custom fonts registered with egui, each as its own family named after the font.
+/// Glyphs missing from a custom font fall back to the default monospace fonts.
+pub struct FontRegistry {
+    /// the data of each loaded font by family name
+    loaded: BTreeMap<String, Arc<FontData>>,
+
+    /// families that have been looked for on the system (whether or not they were found)
+    searched: HashSet<String>,
+
+    /// searches of the system font directories still running in the background,
+    /// which send the font's data (or None if it wasn't found) when they finish
+    searches: Vec<(String, Receiver<Option<Vec<u8>>>)>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        Self {
+            loaded: BTreeMap::new(),
+            searched: HashSet::new(),
+            searches: vec![],
+        }
+    }
+
+    /// Load a font by name or path from the system font directories, if it hasn't been tried already.
+    /// The directories are searched on a background thread (so the editor isn't held up while they are),
+    /// and the font is added once it is found.
+    /// Does nothing on web, where the host supplies the font data instead.
+    pub fn load_system_font(&mut self, ctx: &Context, family: &str) {
+        self.finish_searches(ctx);
+        if cfg!(target_arch = "wasm32")
+            || self.loaded.contains_key(family)
+            || !self.searched.insert(family.to_string())
+        {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let name = family.to_string();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // the editor may have closed by the time it is found
+            let _ = sender.send(system::read_font(&name));
+            ctx.request_repaint();
+        });
+        self.searches.push((family.to_string(), receiver));
+    }
+
+    /// Add the fonts from the searches that have finished
+    fn finish_searches(&mut self, ctx: &Context) {
+        let mut found = vec![];
+        self.searches
+            .retain(|(family, receiver)| match receiver.try_recv() {
+                Ok(data) => {
+                    found.extend(data.map(|data| (family.clone(), data)));
+                    false
+                }
+                Err(TryRecvError::Empty) => true,
+                Err(TryRecvError::Disconnected) => false,
+            });
+
+        for (family, data) in found {
+            // the host may have supplied the font while it was being searched for
+            if !self.loaded.contains_key(&family) {
+                self.add_font(ctx, &family, data);
+            }
+        }
+    }
+
+    /// Register the data of a TTF or OTF font under the family name.
+    /// Invalid fonts are ignored so the default font keeps being used.
+    pub fn add_font(&mut self, ctx: &Context, family: &str, data: Vec<u8>) {
+        if let Err(err) = ab_glyph::FontRef::try_from_slice(&data) {
+            warn!("Failed to load font {family}: {err}");
+            return;
+        }
+
+        self.loaded
+            .insert(family.to_string(), Arc::new(FontData::from_owned(data)));
+        ctx.set_fonts(self.definitions());
+    }
+
+    fn definitions(&self) -> FontDefinitions {
+        let mut definitions = FontDefinitions::default();
+        let fallbacks = definitions
+            .families
+            .get(&FontFamily::Monospace)
+            .cloned()
+            .unwrap_or_default();
+
+        for (name, data) in &self.loaded {
+            definitions.font_data.insert(name.clone(), data.clone());
+
+            let mut fonts = vec![name.clone()];
+            fonts.extend(fallbacks.iter().cloned());
+            definitions
+                .families
+                .insert(FontFamily::Name(name.as_str().into()), fonts);
+        }
+
+        definitions
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod system {
+    use std::path::{Path, PathBuf};
+
+    use log::{info, warn};
+
+    /// Find and read the file for a font, given either its path or its name
+    pub fn read_font(family: &str) -> Option<Vec<u8>> {
+        let Some(path) = find_font(family) else {
+            info!("Font {family} not found on the system, using the default monospace font");
+            return None;
+        };
+        match std::fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                warn!("Failed to read font {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Find the file for a font, given either its path or its name.
+    /// Names are matched against the file names in the system font directories,
+    /// preferring the regular style (so "SF Mono" finds `SFMono-Regular.otf`).
+    fn find_font(family: &str) -> Option<PathBuf> {
+        let path = Path::new(family);
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+
+        let name = normalize(family);
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<(usize, PathBuf)> = None;
+        for dir in font_dirs() {
+            visit_fonts(&dir, 0, &mut |file| {
+                let Some(stem) = file.file_stem().and_then(|s| s.to_str()) else {
+                    return;
+                };
+                let Some(score) = match_score(&name, &normalize(stem)) else {
+                    return;
+                };
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_score, _)| score < *best_score)
+                {
+                    best = Some((score, file.to_path_buf()));
+                }
+            });
+        }
+        best.map(|(_, path)| path)
+    }
+
+    /// How well a font file matches the name (lower is better), or None if it doesn't match
+    fn match_score(name: &str, stem: &str) -> Option<usize> {
+        let style = stem.strip_prefix(name)?;
+        match style {
+            "" | "regular" => Some(0),
+            _ => Some(style.len()),
+        }
+    }
+
+    /// Lowercase with spaces and separators removed
+    fn normalize(name: &str) -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    fn visit_fonts(dir: &Path, depth: usize, found: &mut impl FnMut(&Path)) {
+        // font directories are shallow, so don't follow deep trees
+        const MAX_DEPTH: usize = 4;
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < MAX_DEPTH {
+                    visit_fonts(&path, depth + 1, found);
+                }
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ["ttf", "otf", "ttc"].contains(&ext.to_lowercase().as_str()))
+            {
+                found(&path);
+            }
+        }
+    }
+
+    fn font_dirs() -> Vec<PathBuf> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let mut dirs = vec![];
+
+        if cfg!(target_os = "macos") {
+            dirs.push(PathBuf::from("/System/Library/Fonts"));
+            dirs.push(PathBuf::from("/Library/Fonts"));
+            if let Some(home) = &home {
+                dirs.push(home.join("Library/Fonts"));
+            }
+            // apps like Terminal bundle fonts that aren't installed system-wide
+            dirs.push(PathBuf::from(
+                "/System/Applications/Utilities/Terminal.app/Contents/Resources/Fonts",
+            ));
+        } else if cfg!(target_os = "windows") {
+            let windir = std::env::var_os("WINDIR").unwrap_or("C:\\Windows".into());
+            dirs.push(PathBuf::from(windir).join("Fonts"));
+            if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+                dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+            }
+        } else {
+            dirs.push(PathBuf::from("/usr/share/fonts"));
+            dirs.push(PathBuf::from("/usr/local/share/fonts"));
+            if let Some(home) = &home {
+                dirs.push(home.join(".local/share/fonts"));
+                dirs.push(home.join(".fonts"));
+            }
+        }
+
+        dirs
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod system {
+    pub fn read_font(_: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
@@ -0,0 +1,254 @@
+//! What the editor sees in a source (its blocks and highlights), for tools that run without it
+
+use ropey::Rope;
+use serde_json::{json, Value};
+
+use super::{
+    blocks::{BlockTrees, BlockType},
+    source::{IndentStyle, Source},
+    text_range::TextRange,
+};
+use crate::{
+    lang::{
+        config::{LanguageConfig, Snippet},
+        highlighter::HighlightEvent,
+    },
+    theme::SyntaxColor,
+};
+
+/// The blocks of the source
+fn block_trees(source: &Source) -> BlockTrees {
+    BlockTrees::for_ts_tree(
+        &mut source.get_tree_cursor(),
+        source.text(),
+        source.lang.config,
+        source.indent_style().width,
+    )
+}
+
+/// The blocks of the source drawn as a tree
+pub fn blocks_tree(source: &Source) -> String {
+    block_trees(source).debug_tree()
+}
+
+/// The blocks of the source as a JSON array of the outermost blocks, each with its children
+pub fn blocks_json(source: &Source) -> Value {
+    json!(block_trees(source).children_of(None))
+}
+
+/// A range of the source matched by a highlight query capture
+pub struct CaptureSpan {
+    pub range: TextRange,
+    pub capture: &'static str,
+    pub color: SyntaxColor,
+}
+
+/// Every span captured by the language's highlight query, in the order they start.
+/// Captures inside of others are listed after the ones containing them.
+pub fn capture_spans(source: &Source) -> Vec<CaptureSpan> {
+    let text = source.text();
+    let highlight = source.lang.config.highlight;
+    let mut highlighter = source.lang.highlighter.borrow_mut();
+    let highlight_config = source.lang.highlight_config.borrow();
+    let node = source.get_tree_cursor().node();
+
+    let mut spans = vec![];
+    let mut open = vec![];
+    let mut byte = 0;
+    for event in highlighter.highlight_existing_tree(text.slice(..), node, &highlight_config) {
+        match event {
+            HighlightEvent::Source { start: _, end } => byte = end,
+            HighlightEvent::HighlightStart(capture) => open.push((capture, byte)),
+            HighlightEvent::HighlightEnd => {
+                let Some((capture, start)) = open.pop() else {
+                    continue;
+                };
+                let (name, color) = highlight[capture.0];
+                let chars = text.byte_to_char(start)..text.byte_to_char(byte);
+                spans.push((
+                    start..byte,
+                    CaptureSpan {
+                        range: TextRange::from_char_range_in(text, chars),
+                        capture: name,
+                        color,
+                    },
+                ));
+            }
+        }
+    }
+
+    spans.sort_by_key(|(bytes, _)| (bytes.start, std::cmp::Reverse(bytes.end)));
+    spans.into_iter().map(|(_, span)| span).collect()
+}
+
+/// A palette snippet that doesn't parse into the block it should
+pub struct SnippetProblem {
+    pub language: &'static str,
+    pub palette: &'static str,
+    pub snippet: &'static str,
+    pub problem: String,
+}
+
+/// Parse every snippet in every language's palettes,
+/// returning the number of snippets checked and the problems with them
+pub fn check_palettes() -> (usize, Vec<SnippetProblem>) {
+    let mut checked = 0;
+    let mut problems = vec![];
+    for config in LanguageConfig::all() {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&config.tree_sitter())
+            .expect("languages are compatible with tree sitter");
+
+        for palette in config.palettes {
+            for snippet in palette.snippets {
+                checked += 1;
+                let snippet_problems = check_snippet(config, snippet, &mut parser);
+                problems.extend(snippet_problems.into_iter().map(|problem| SnippetProblem {
+                    language: config.name,
+                    palette: palette.name,
+                    snippet: snippet.id,
+                    problem,
+                }));
+            }
+        }
+    }
+    (checked, problems)
+}
+
+fn check_snippet(
+    config: &LanguageConfig,
+    snippet: &Snippet,
+    parser: &mut tree_sitter::Parser,
+) -> Vec<String> {
+    let mut problems = vec![];
+    if !snippet.source.ends_with('\n') {
+        problems.push("does not end with a newline".to_string());
+    }
+
+    let Some(tree) = parser.parse(snippet.source, None) else {
+        return vec!["could not be parsed".to_string()];
+    };
+    let text = Rope::from_str(snippet.source);
+    if let Some(error_byte) = first_error(tree.root_node()) {
+        let error_char = text.byte_to_char(error_byte);
+        let line = text.char_to_line(error_char);
+        let col = error_char - text.line_to_char(line);
+        problems.push(format!("has a syntax error at {}:{}", line + 1, col + 1));
+    }
+
+    // comments (like a docstring above a function) aren't what the snippet is for
+    let blocks =
+        BlockTrees::for_ts_tree(&mut tree.walk(), &text, config, IndentStyle::DEFAULT.width);
+    let outermost = blocks
+        .children_of(None)
+        .into_iter()
+        .map(|block| block.syntax_type)
+        .find(|block_type| *block_type != BlockType::Comment);
+    match (outermost, snippet.block_type) {
+        (Some(found), Some(expected)) if found != expected => {
+            problems.push(format!("is a {found:?} block instead of {expected:?}"))
+        }
+        (Some(found), None) => problems.push(format!(
+            "is a {found:?} block but is marked as not having one"
+        )),
+        (None, Some(_)) => problems.push("has no blocks".to_string()),
+        _ => {}
+    }
+
+    problems
+}
+
+/// The byte where the first ERROR or missing node in the tree starts
+fn first_error(node: tree_sitter::Node) -> Option<usize> {
+    if !node.has_error() {
+        return None;
+    }
+    if node.is_error() || node.is_missing() {
+        return Some(node.start_byte());
+    }
+
+    let mut cursor = node.walk();
+    let children: Vec<_> = node.children(&mut cursor).collect();
+    children.into_iter().find_map(first_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::Language;
+
+    fn python(code: &str) -> Source {
+        Source::new(Rope::from_str(code), Language::for_file("test.py"))
+    }
+
+    #[test]
+    fn test_capture_spans() {
+        let source = python("def f():\n    return 1\n");
+        let spans: Vec<(String, &str)> = capture_spans(&source)
+            .into_iter()
+            .map(|span| {
+                let chars = span.range.char_range_in(source.text());
+                (source.text().slice(chars).to_string(), span.capture)
+            })
+            .collect();
+
+        assert!(spans.contains(&("def".to_string(), "keyword")));
+        assert!(spans.contains(&("return".to_string(), "keyword")));
+
+        // in the order they start
+        let def = spans.iter().position(|(text, _)| text == "def").unwrap();
+        let ret = spans.iter().position(|(text, _)| text == "return").unwrap();
+        assert!(def < ret);
+    }
+
+    #[test]
+    fn test_check_snippet() {
+        let config = LanguageConfig::for_file("test.py");
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&config.tree_sitter()).unwrap();
+        let mut check = |snippet: &Snippet| check_snippet(config, snippet, &mut parser);
+
+        let good = Snippet::new("while", "while x:\n    pass\n").of_type(BlockType::While);
+        assert!(check(&good).is_empty());
+
+        let wrong_type = Snippet::new("while", "while x:\n    pass\n").of_type(BlockType::For);
+        assert_eq!(check(&wrong_type), vec!["is a While block instead of For"]);
+
+        let broken = Snippet::new("broken", "if x\n    pass");
+        let problems = check(&broken);
+        assert!(problems.contains(&"does not end with a newline".to_string()));
+        assert!(problems
+            .iter()
+            .any(|p| p.starts_with("has a syntax error at 1:")));
+
+        // the column counts characters, not bytes
+        let after_unicode = Snippet::new("unicode", "print(\"é\", ])\n");
+        assert!(check(&after_unicode).contains(&"has a syntax error at 1:12".to_string()));
+    }
+
+    #[test]
+    fn test_palettes_are_valid() {
+        let (_, problems) = check_palettes();
+        let problems: Vec<String> = problems
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} / {} / {}: {}",
+                    p.language, p.palette, p.snippet, p.problem
+                )
+            })
+            .collect();
+        assert!(problems.is_empty(), "{problems:#?}");
+    }
+
+    #[test]
+    fn test_blocks_json() {
+        let source = python("if x:\n    y = 1\n");
+        let json = blocks_json(&source);
+
+        assert_eq!(json[0]["syntax_type"], "if");
+        assert_eq!(json[0]["height"], 2);
+        assert_eq!(json[0]["children"][0]["syntax_type"], "generic");
+    }
+}
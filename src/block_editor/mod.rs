@@ -1,33 +1,47 @@
 use egui::{
-    text::Fonts, CentralPanel, FontFamily, FontId, Frame, Pos2, Rect, Sense, SidePanel, Widget,
+    text::Fonts, Align2, CentralPanel, FontFamily, FontId, Frame, Pos2, Rect, Sense, SidePanel,
+    Stroke, StrokeKind, Widget,
 };
 use egui::{Event, Key, Modifiers, Vec2};
+use log::info;
 use ropey::Rope;
 use source::Source;
 
 use crate::lang::Language;
 use crate::lsp::completion::VSCodeCompletionItem;
-use crate::theme::blocks_theme::BlocksTheme;
+use crate::theme::{blocks_theme::BlocksTheme, Theme};
 use crate::{theme, vscode};
 
 mod blocks;
 mod dragging;
-mod rope_ext;
+pub mod export;
+mod fonts;
+#[allow(dead_code)] // only used by the command line of the native app
+pub mod inspect;
+mod outline;
+pub mod rope_ext;
 mod search;
+mod soft_wrap;
 pub mod source;
 mod text_drawer;
 pub mod text_editor;
 pub mod text_range;
+mod undo_toast;
 
 pub use blocks::BlockType;
 
-use self::dragging::block_palette::BlockPalette;
+use self::dragging::block_palette::{BlockPalette, PaletteAction};
 use self::dragging::loose_block::LooseBlock;
+use self::export::CopyFormat;
+use self::fonts::FontRegistry;
+use self::outline::Outline;
 use self::search::SearchPopup;
-use self::source::TextEdit;
+use self::source::{IndentStyle, TextEdit};
+use self::text_editor::minimap::MINIMAP_WIDTH;
 use self::text_editor::StackFrameLines;
 use self::text_editor::TextEditor;
 use self::text_range::TextRange;
+use self::undo_toast::{ToastResult, UndoToast};
 use crate::lsp::diagnostics::{Diagnostic, VSCodeCodeAction};
 
 /// padding around edges of entire editor
@@ -42,6 +56,9 @@ const GUTTER_WIDTH: f32 = 30.0;
 /// convenience constant for all the padding that impacts text layout
 const TOTAL_TEXT_X_OFFSET: f32 = OUTER_PAD + GUTTER_WIDTH + TEXT_L_PAD;
 
+/// height of the bar above the editor showing the blocks around the cursor
+const BREADCRUMBS_HEIGHT: f32 = 22.0;
+
 const SHOW_ERROR_BLOCK_OUTLINES: bool = false;
 
 pub struct BlockEditor {
@@ -54,6 +71,9 @@ pub struct BlockEditor {
     /// the font used for code
     font: MonospaceFont,
 
+    /// custom fonts that have been loaded
+    font_registry: FontRegistry,
+
     /// the editor widget
     text_editor: TextEditor,
 
@@ -68,6 +88,18 @@ pub struct BlockEditor {
 
     /// the popup for the currently dragged block
     dragging_popup: Option<LooseBlock>,
+
+    /// offers to undo the last block deleted by dropping it on the palette
+    undo_toast: Option<UndoToast>,
+
+    /// the tree of definitions in the file
+    outline: Outline,
+
+    /// if the outline is shown beside the editor
+    show_outline: bool,
+
+    /// if the minimap of the document is shown beside the editor
+    show_minimap: bool,
 }
 
 #[derive(Debug)]
@@ -76,6 +108,19 @@ pub struct DragSession {
 
     /// point within the block that it is dragged by
     offset: Pos2,
+
+    /// if the block was dragged out of the palette (instead of the editor)
+    from_palette: bool,
+
+    /// if the blocks were copied instead of moved out of the editor (by holding command with alt)
+    copy: bool,
+}
+
+impl DragSession {
+    /// If the dragged text was removed from the source when the drag started
+    pub(crate) fn removed_from_source(&self) -> bool {
+        !self.from_palette && !self.copy
+    }
 }
 
 #[allow(dead_code)]
@@ -84,7 +129,13 @@ pub enum ExternalCommand {
     SetText(String),
     SetFile { name: String, contents: String },
     SetBlocksTheme(BlocksTheme),
+    SetTheme(Theme),
+    SetBlockShapeCues(bool),
     SetFont(String, f32),
+    AddFont { family: String, data: Vec<u8> },
+    SetSoftWrap(bool),
+    SetStickyScroll(bool),
+    SetScreenReader(bool),
 
     // external edits
     ApplyEdit(TextEdit<'static>),
@@ -102,31 +153,74 @@ pub enum ExternalCommand {
     // undo/redo
     Undo,
     Redo,
+
+    // copying with colors (and optionally the blocks) for other apps
+    CopyRich { format: CopyFormat, blocks: bool },
+
+    // formatting
+    Reindent,
+    SetIndentStyle { style: IndentStyle, detect: bool },
+    ConvertIndentation { use_tabs: bool },
+
+    // wrapping code in blocks
+    SurroundWith,
+    UnwrapBlock,
+
+    // local variables
+    GoToLocalDefinition,
+    RenameLocal,
+
+    // outline of the file's definitions
+    SetOutlineVisible(bool),
+    SetMinimapVisible(bool),
+
+    // custom palettes
+    ImportPalettes { name: String, contents: String },
+    ExportPalettes,
 }
 
 pub struct MonospaceFont {
     /// The font size and family
     id: FontId,
 
+    /// The name (or path) of the font to use once it has been loaded
+    family: String,
+
     /// The size in pixels of a single character
     size: Vec2,
+
+    /// The number of columns between tab stops
+    tab_width: usize,
 }
 
 impl MonospaceFont {
     /// Create a new monospace font.
     /// Note: does not calculate the size yet. Must also call `calculate_size` before using..
-    pub fn new(_family: &str, size: f32) -> Self {
-        // TODO: support custom font families
+    pub fn new(family: &str, size: f32) -> Self {
         let id = FontId::new(size, FontFamily::Monospace);
         Self {
             id,
+            family: family.to_string(),
             size: Vec2::ZERO,
+            tab_width: IndentStyle::DEFAULT.width,
         }
     }
 
     pub fn calculate_size(&mut self, fonts: &Fonts) {
+        // use the custom font once it has been registered, until then use the default monospace font
+        let custom = FontFamily::Name(self.family.as_str().into());
+        self.id.family = if fonts.families().contains(&custom) {
+            custom
+        } else {
+            FontFamily::Monospace
+        };
+
         self.size = Vec2::new(fonts.glyph_width(&self.id, 'A'), fonts.row_height(&self.id));
     }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+    }
 }
 
 impl BlockEditor {
@@ -140,11 +234,16 @@ impl BlockEditor {
             source: Source::new(Rope::new(), lang),
             blocks_theme: BlocksTheme::for_str(blocks_theme),
             font,
+            font_registry: FontRegistry::new(),
             text_editor: TextEditor::new(),
             block_palette: BlockPalette::new(),
             search_popup: SearchPopup::new(),
             drag_block: None,
             dragging_popup: None,
+            undo_toast: None,
+            outline: Outline::new(),
+            show_outline: false,
+            show_minimap: true,
         }
     }
 
@@ -167,21 +266,64 @@ impl BlockEditor {
                     }
                     ExternalCommand::SetFile { name, contents } => {
                         let language = Language::for_file(name);
+                        let (indent_style, detect) = self.source.indent_style_config();
                         self.source = Source::new(Rope::from_str(contents), language);
+                        self.outline.clear();
+                        self.source.configure_indent_style(indent_style, detect);
                         self.block_palette
                             .populate(&mut self.source.lang, &self.font)
                     }
                     ExternalCommand::SetBlocksTheme(theme) => {
-                        self.blocks_theme = *theme;
+                        self.blocks_theme = theme.with_shape_cues(self.blocks_theme.shape_cues);
+                    }
+                    ExternalCommand::SetBlockShapeCues(enabled) => {
+                        self.blocks_theme.shape_cues = *enabled;
+                    }
+                    ExternalCommand::SetTheme(theme) => {
+                        info!("Using theme {}", theme.name);
+                        ui.ctx().set_visuals(if theme.dark {
+                            egui::Visuals::dark()
+                        } else {
+                            egui::Visuals::light()
+                        });
+                        theme::set_current(theme.clone());
                     }
                     ExternalCommand::SetFont(font_name, font_size) => {
                         self.font = MonospaceFont::new(font_name, *font_size);
                         ui.fonts(|f| self.font.calculate_size(f));
                     }
+                    ExternalCommand::AddFont { family, data } => {
+                        self.font_registry.add_font(ui.ctx(), family, data.clone());
+                    }
+                    ExternalCommand::SetIndentStyle { style, detect } => {
+                        self.source.configure_indent_style(*style, *detect);
+                    }
+                    ExternalCommand::SetScreenReader(enabled) => {
+                        ui.ctx().options_mut(|o| o.screen_reader = *enabled);
+                    }
+                    ExternalCommand::ImportPalettes { name, contents } => {
+                        self.block_palette.import_palettes(name, contents);
+                    }
+                    ExternalCommand::ExportPalettes => {
+                        self.block_palette.export_palettes();
+                    }
+                    ExternalCommand::SetOutlineVisible(visible) => {
+                        self.show_outline = *visible;
+                    }
+                    ExternalCommand::SetMinimapVisible(visible) => {
+                        self.show_minimap = *visible;
+                    }
                     _ => {}
                 }
             }
 
+            // look for the font on the system (it is used from the next frame once loaded)
+            self.font_registry
+                .load_system_font(ui.ctx(), &self.font.family);
+
+            // lay out tabs to match the indent width of the file
+            self.font.set_tab_width(self.source.indent_style().width);
+
             if !self.block_palette.is_populated() {
                 self.block_palette
                     .populate(&mut self.source.lang, &self.font);
@@ -206,11 +348,28 @@ impl BlockEditor {
             .resizable(false)
             .frame(Frame::NONE)
             .show(ui.ctx(), |ui| {
+                let mut action: Option<PaletteAction> = None;
                 ui.add(self.block_palette.widget(
                     &mut self.drag_block,
+                    &mut action,
                     self.blocks_theme,
                     &self.font,
                 ));
+                match action {
+                    Some(PaletteAction::Insert(text)) => {
+                        self.text_editor.insert_block(text, &mut self.source);
+                    }
+                    Some(PaletteAction::Saved(drag)) => {
+                        self.text_editor
+                            .restore_dragged_block(&drag, &mut self.source);
+                    }
+                    Some(PaletteAction::Deleted(drag)) => {
+                        self.text_editor
+                            .discard_dragged_block(&drag, &mut self.source);
+                        self.undo_toast = Some(UndoToast::new("Block deleted", &self.source));
+                    }
+                    None => {}
+                }
             });
 
         // trigger find popup if command-f is pressed
@@ -227,12 +386,53 @@ impl BlockEditor {
                 if modifiers.matches_logically(Modifiers::COMMAND) && key == Key::F {
                     self.search_popup.show();
                 }
+                if modifiers.matches_logically(Modifiers::COMMAND | Modifiers::ALT) && key == Key::O
+                {
+                    self.show_outline = !self.show_outline;
+                }
             }
         }
 
+        if self.show_outline {
+            self.outline.update(&self.source);
+            SidePanel::left("outline_panel")
+                .resizable(true)
+                .default_width(180.0)
+                .frame(
+                    Frame::NONE
+                        .fill(theme::current().editor.background)
+                        .inner_margin(6.0),
+                )
+                .show(ui.ctx(), |ui| {
+                    let cursor = self.text_editor.cursor();
+                    if let Some(range) = self.outline.show(ui, cursor, self.blocks_theme) {
+                        self.text_editor.select_and_reveal(range, &mut self.source);
+                    }
+                });
+        }
+
+        if self.show_minimap {
+            SidePanel::right("minimap_panel")
+                .resizable(false)
+                .exact_width(MINIMAP_WIDTH)
+                .frame(Frame::NONE)
+                .show(ui.ctx(), |ui| {
+                    ui.add(self.text_editor.minimap(
+                        &self.search_popup.results,
+                        self.blocks_theme,
+                        &self.font,
+                    ));
+                });
+        }
+
         CentralPanel::default()
             .frame(Frame::NONE)
             .show(ui.ctx(), |ui| {
+                // leave room for the breadcrumbs, which are drawn after the editor has
+                // handled this frame's input so that they match the cursor
+                let (_, breadcrumbs_rect) =
+                    ui.allocate_space(Vec2::new(ui.available_width(), BREADCRUMBS_HEIGHT));
+
                 // draw the editor
                 let editor_response = ui.add(self.text_editor.widget(
                     &mut self.source,
@@ -243,16 +443,33 @@ impl BlockEditor {
                     &self.font,
                 ));
 
+                // draw the breadcrumbs
+                ui.put(
+                    breadcrumbs_rect,
+                    self.text_editor.breadcrumbs(&self.source, &self.font),
+                );
+
                 // draw the find popup
                 ui.put(
                     Rect::from_min_size(
-                        ui.max_rect().right_top() + Vec2::new(-205.0, 5.0),
+                        ui.max_rect().right_top() + Vec2::new(-205.0, BREADCRUMBS_HEIGHT + 5.0),
                         Vec2::new(200.0, 30.0),
                     ),
                     self.search_popup
                         .widget(&self.source, editor_response.changed()),
                 );
             });
+
+        if let Some(toast) = &mut self.undo_toast {
+            match toast.show(ui.ctx(), &self.source) {
+                ToastResult::Keep => {}
+                ToastResult::Undo => {
+                    toast.undo(&mut self.text_editor, &mut self.source);
+                    self.undo_toast = None;
+                }
+                ToastResult::Close => self.undo_toast = None,
+            }
+        }
     }
 
     fn draw_dragged_block(&mut self, ui: &mut egui::Ui) {
@@ -275,10 +492,12 @@ impl BlockEditor {
                 if let Some(mouse_pos) = ui.input(|i| i.pointer.hover_pos()) {
                     let painter = ui.painter();
                     let origin = mouse_pos - drag_block.offset;
+                    let popup_rect =
+                        Rect::from_min_size(origin.to_pos2(), dragging_popup.min_size());
                     painter.rect_filled(
-                        Rect::from_min_size(origin.to_pos2(), dragging_popup.min_size()),
+                        popup_rect,
                         0.0,
-                        theme::BACKGROUND.gamma_multiply(0.75),
+                        theme::current().editor.background.gamma_multiply(0.75),
                     );
                     dragging_popup.draw(
                         origin,
@@ -286,7 +505,30 @@ impl BlockEditor {
                         self.blocks_theme,
                         &self.font,
                         painter,
-                    )
+                    );
+
+                    // outline a group of blocks so it reads as being dragged together
+                    if dragging_popup.block_count() > 1 {
+                        painter.rect_stroke(
+                            popup_rect.expand(2.0),
+                            4.0,
+                            Stroke::new(2.0_f32, theme::current().editor.cursor),
+                            StrokeKind::Outside,
+                        );
+                    }
+
+                    // and mark copies with a plus, like file managers do
+                    if drag_block.copy {
+                        let badge = popup_rect.right_top() + Vec2::new(-4.0, 4.0);
+                        painter.circle_filled(badge, 8.0, theme::current().editor.cursor);
+                        painter.text(
+                            badge,
+                            Align2::CENTER_CENTER,
+                            "+",
+                            FontId::proportional(14.0),
+                            theme::current().editor.background,
+                        );
+                    }
                 }
             }
         } else {
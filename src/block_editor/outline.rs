@@ -0,0 +1,305 @@
+use std::ops::Range;
+
+use egui::{RichText, ScrollArea, Sense, Ui, Vec2};
+use ropey::Rope;
+use tree_sitter::InputEdit;
+
+use super::{
+    source::Source,
+    text_range::{TextPoint, TextRange},
+    BlockType,
+};
+use crate::{
+    lang::{tags::Tag, tree_manager::TreeChange},
+    theme::{self, blocks_theme::BlocksTheme},
+};
+
+/// the space each level of nesting is indented by
+const INDENT: f32 = 12.0;
+
+/// the size of the block color swatch before each name
+const SWATCH_SIZE: f32 = 10.0;
+
+/// The classes, functions, and modules of the file as a tree, found with the language's tags query
+pub struct Outline {
+    /// the version of the syntax tree the entries were found for
+    tree_version: Option<usize>,
+
+    /// the definitions the entries were made from, kept to update after each edit
+    tags: Vec<Tag>,
+
+    entries: Vec<OutlineEntry>,
+
+    /// the entry the cursor was in last frame, to scroll to it when it changes
+    current: Option<usize>,
+}
+
+struct OutlineEntry {
+    name: String,
+    kind: String,
+    block_type: Option<BlockType>,
+
+    /// the number of entries this one is nested in
+    depth: usize,
+
+    range: TextRange,
+    name_range: TextRange,
+}
+
+impl Outline {
+    pub fn new() -> Self {
+        Outline {
+            tree_version: None,
+            tags: vec![],
+            entries: vec![],
+            current: None,
+        }
+    }
+
+    /// Find every definition again next time (like after switching files)
+    pub fn clear(&mut self) {
+        self.tree_version = None;
+    }
+
+    /// Find the definitions again if the syntax tree changed since they were last found.
+    /// After a single edit, only the definitions around the parts that changed are found again.
+    pub fn update(&mut self, source: &Source) {
+        let version = source.tree_version();
+        if self.tree_version == Some(version) {
+            return;
+        }
+        let last_version = self.tree_version.replace(version);
+
+        let Some(query) = &source.lang.tags_query else {
+            self.tags.clear();
+            self.entries.clear();
+            return;
+        };
+        let root = source.get_tree_cursor().node();
+        let text = source.text();
+        let config = source.lang.config;
+        self.tags = match source.last_tree_change() {
+            Some(change) if last_version == Some(version - 1) => {
+                update_tags(std::mem::take(&mut self.tags), change, |bytes| {
+                    query.definitions_in(root, text, config, bytes)
+                })
+            }
+            _ => query.definitions(root, text, config),
+        };
+        self.entries = outline_entries(&self.tags, text);
+    }
+
+    /// The innermost entry containing the cursor
+    fn entry_at(&self, cursor: TextPoint) -> Option<usize> {
+        self.entries
+            .iter()
+            .rposition(|entry| entry.range.start <= cursor && cursor <= entry.range.end)
+    }
+
+    /// Show the outline, highlighting the entry with the cursor.
+    /// Returns the range of the name of the entry that was clicked, to select it.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        cursor: TextPoint,
+        blocks_theme: BlocksTheme,
+    ) -> Option<TextRange> {
+        ui.label(RichText::new("Outline").color(theme::current().editor.interface_text));
+        ui.separator();
+
+        if self.entries.is_empty() {
+            ui.weak("No definitions found");
+            return None;
+        }
+
+        let current = self.entry_at(cursor);
+        let current_changed = current != self.current;
+        self.current = current;
+
+        let mut clicked = None;
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (idx, entry) in self.entries.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(entry.depth as f32 * INDENT);
+
+                        // the color of the definition's block
+                        let (swatch, _) =
+                            ui.allocate_exact_size(Vec2::splat(SWATCH_SIZE), Sense::hover());
+                        if let Some(color) = entry.block_type.and_then(|block_type| {
+                            (blocks_theme.color_for)(block_type, entry.depth)
+                        }) {
+                            ui.painter().rect_filled(swatch, 2.0, color);
+                        }
+
+                        let is_current = Some(idx) == current;
+                        let response = ui
+                            .selectable_label(is_current, &entry.name)
+                            .on_hover_text(&entry.kind);
+                        if response.clicked() {
+                            clicked = Some(entry.name_range);
+                        }
+                        if is_current && current_changed {
+                            response.scroll_to_me(None);
+                        }
+                    });
+                }
+            });
+        clicked
+    }
+}
+
+/// Move the definitions found before the edit to where their text is now,
+/// and find the ones around the edit and where the syntax changed again
+fn update_tags(
+    mut tags: Vec<Tag>,
+    change: &TreeChange,
+    find: impl FnOnce(Range<usize>) -> Vec<Tag>,
+) -> Vec<Tag> {
+    for tag in &mut tags {
+        tag.range = shift_range(&tag.range, &change.edit);
+        tag.name_range = shift_range(&tag.name_range, &change.edit);
+    }
+
+    // include the bytes just around the edit, since a block ending right before it can grow to include it
+    let edit = &change.edit;
+    let mut bytes = change
+        .changed_ranges
+        .iter()
+        .fold(edit.start_byte..edit.new_end_byte, |bytes, changed| {
+            bytes.start.min(changed.start)..bytes.end.max(changed.end)
+        });
+    bytes = bytes.start.saturating_sub(1)..bytes.end + 1;
+
+    // definitions overlapping the changes are found again whole, since their ends or names may have moved
+    loop {
+        let grown = tags
+            .iter()
+            .filter(|tag| overlaps(&tag.range, &bytes))
+            .fold(bytes.clone(), |bytes, tag| {
+                bytes.start.min(tag.range.start)..bytes.end.max(tag.range.end)
+            });
+        if grown == bytes {
+            break;
+        }
+        bytes = grown;
+    }
+
+    tags.retain(|tag| !overlaps(&tag.range, &bytes));
+    tags.extend(find(bytes));
+    tags.sort_by_key(|tag| (tag.range.start, std::cmp::Reverse(tag.range.end)));
+    tags
+}
+
+/// Where the byte range is after the edit. Positions inside the edited text move to its end.
+fn shift_range(range: &Range<usize>, edit: &InputEdit) -> Range<usize> {
+    let shift = |byte: usize| {
+        if byte <= edit.start_byte {
+            byte
+        } else if byte >= edit.old_end_byte {
+            byte - edit.old_end_byte + edit.new_end_byte
+        } else {
+            edit.new_end_byte
+        }
+    };
+    shift(range.start)..shift(range.end)
+}
+
+/// If the ranges overlap or touch
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// The entries for the definitions, which are in the order they start
+fn outline_entries(tags: &[Tag], text: &Rope) -> Vec<OutlineEntry> {
+    let to_range = |bytes: &Range<usize>| {
+        TextRange::from_char_range_in(
+            text,
+            text.byte_to_char(bytes.start)..text.byte_to_char(bytes.end),
+        )
+    };
+
+    // the definitions enclosing each are the ones that haven't ended yet
+    let mut enclosing: Vec<TextPoint> = vec![];
+    tags.iter()
+        .map(|tag| {
+            let range = to_range(&tag.range);
+            while enclosing.last().is_some_and(|end| *end <= range.start) {
+                enclosing.pop();
+            }
+            let depth = enclosing.len();
+            enclosing.push(range.end);
+
+            OutlineEntry {
+                name: tag.name.clone(),
+                kind: tag.kind.clone(),
+                block_type: tag.block_type,
+                depth,
+                range,
+                name_range: to_range(&tag.name_range),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{
+        block_editor::{
+            source::{TextEdit, UndoStopCondition},
+            text_editor::selections::Selections,
+        },
+        lang::Language,
+    };
+
+    #[test]
+    fn test_update_after_edits() {
+        let text = "class A:\n    def f(self):\n        pass\n\ndef g():\n    pass\n";
+        let mut source = Source::new(Rope::from_str(text), Language::for_file("test.py"));
+        let mut outline = Outline::new();
+        outline.update(&source);
+
+        let edits = [
+            // rename a method
+            ((1, 8), (1, 9), "method"),
+            // add a method to the end of the class
+            ((3, 0), (3, 0), "    def h(self):\n        pass\n"),
+            // add a function before the others
+            ((0, 0), (0, 0), "def first():\n    pass\n"),
+            // remove the class line, so its methods are in the function above
+            ((2, 0), (3, 0), ""),
+        ];
+        for (start, end, new_text) in edits {
+            let range = TextRange::new(
+                TextPoint::new(start.0, start.1),
+                TextPoint::new(end.0, end.1),
+            );
+            source.apply_edit(
+                &TextEdit::new(Cow::Borrowed(new_text), range),
+                UndoStopCondition::Always,
+                true,
+                &mut Selections::new(),
+            );
+            outline.update(&source);
+
+            // the same as finding every definition again
+            let query = source.lang.tags_query.as_ref().unwrap();
+            let tags = query.definitions(
+                source.get_tree_cursor().node(),
+                source.text(),
+                source.lang.config,
+            );
+            assert_eq!(outline.tags, tags, "after editing in {new_text:?}");
+        }
+        let names: Vec<_> = outline
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.depth))
+            .collect();
+        assert_eq!(names, vec![("first", 0), ("method", 1), ("h", 1), ("g", 0)]);
+    }
+}
@@ -1,4 +1,5 @@
 use ropey::{Rope, RopeSlice};
+use unicode_width::UnicodeWidthChar;
 
 pub trait RopeExt {
     /// finds the characters surrounding an offset (prev, next).
@@ -88,6 +89,28 @@ pub trait RopeSliceExt {
     ///
     /// runs in O(log N) time
     fn excluding_linebreak(&self) -> Self;
+
+    /// the column a character index is displayed at, with wide characters taking up two columns
+    /// and tabs expanded to the next tab stop.
+    /// Columns past the end of the line are treated as if the line continued with spaces.
+    ///
+    /// runs in O(M + log N) time (M is the char column)
+    fn display_col(&self, char_col: usize, tab_width: usize) -> usize;
+
+    /// the character index closest to a display column (the inverse of `display_col`)
+    ///
+    /// runs in O(M + log N) time (M is the length of the line)
+    fn char_col_at_display(&self, display_col: usize, tab_width: usize) -> usize;
+
+    /// if a character index is between grapheme clusters rather than inside of one
+    ///
+    /// runs in O(M + log N) time (M is the char column)
+    fn is_cluster_boundary(&self, char_col: usize) -> bool;
+
+    /// the width of the whitespace at the start of the slice in display columns
+    ///
+    /// runs in O(M log N) time (N number chars, M length of first line)
+    fn indent_width(&self, tab_width: usize) -> usize;
 }
 
 impl<'a> RopeSliceExt for RopeSlice<'a> {
@@ -123,6 +146,133 @@ impl<'a> RopeSliceExt for RopeSlice<'a> {
         let new_end = self.len_chars() - linebreak_len;
         self.slice(..new_end)
     }
+
+    fn display_col(&self, char_col: usize, tab_width: usize) -> usize {
+        let line = self.excluding_linebreak();
+        let within_line = usize::min(char_col, line.len_chars());
+        let width: usize = display_widths(line.chars(), tab_width)
+            .take(within_line)
+            .map(|(_, width)| width)
+            .sum();
+        width + (char_col - within_line)
+    }
+
+    fn char_col_at_display(&self, display_col: usize, tab_width: usize) -> usize {
+        let mut col = 0;
+        let line = self.excluding_linebreak();
+        let mut widths = display_widths(line.chars(), tab_width)
+            .enumerate()
+            .peekable();
+        while let Some((idx, (_, width))) = widths.next() {
+            // the characters after the first in a cluster don't take up any columns
+            let mut cluster_len = 1;
+            while widths.next_if(|(_, (_, width))| *width == 0).is_some() {
+                cluster_len += 1;
+            }
+
+            if display_col < col + width {
+                // round to whichever side of the cluster is closer
+                return if (display_col - col) * 2 < width {
+                    idx
+                } else {
+                    idx + cluster_len
+                };
+            }
+            col += width;
+        }
+        line.len_chars() + (display_col - col)
+    }
+
+    fn is_cluster_boundary(&self, char_col: usize) -> bool {
+        // tabs never join a cluster, so their width doesn't matter
+        const ANY_TAB_WIDTH: usize = 1;
+        char_col == 0
+            || display_widths(self.excluding_linebreak().chars(), ANY_TAB_WIDTH)
+                .nth(char_col)
+                .is_none_or(|(_, width)| width > 0)
+    }
+
+    fn indent_width(&self, tab_width: usize) -> usize {
+        self.display_col(self.whitespace_at_start(), tab_width)
+    }
+}
+
+/// Pairs each character with the number of columns it takes up in the monospace grid,
+/// assuming the first character is displayed at column 0.
+///
+/// Wide characters (like CJK and most emoji) take up two columns and tabs extend to the next tab stop.
+/// A grapheme cluster is displayed as a single glyph, so its width is all given to its first character
+/// and the rest (combining marks, variation selectors, characters joined by a ZWJ, the second half of a flag)
+/// have a width of 0.
+pub fn display_widths(
+    chars: impl Iterator<Item = char>,
+    tab_width: usize,
+) -> impl Iterator<Item = (char, usize)> {
+    let mut chars = chars.peekable();
+    let mut col = 0;
+    let mut prev: Option<char> = None;
+    let mut unpaired_flag = false;
+
+    std::iter::from_fn(move || {
+        let ch = chars.next()?;
+        let width = if ch == '\t' {
+            tab_width - (col % tab_width)
+        } else if continues_cluster(prev, ch, unpaired_flag) {
+            0
+        } else if chars.peek() == Some(&EMOJI_PRESENTATION) || is_regional_indicator(ch) {
+            // drawn as emoji: text characters followed by the emoji variation selector and flags
+            2
+        } else {
+            // control characters are drawn as a placeholder glyph
+            ch.width().unwrap_or(1)
+        };
+
+        unpaired_flag = is_regional_indicator(ch) && !(unpaired_flag && width == 0);
+        prev = Some(ch);
+        col += width;
+        Some((ch, width))
+    })
+}
+
+/// If a character is part of a cluster without a glyph of its own (so shouldn't be drawn by itself)
+pub fn is_invisible_in_cluster(ch: char, width: usize) -> bool {
+    width == 0 && (ch.width() != Some(0) || is_format_char(ch))
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+const EMOJI_PRESENTATION: char = '\u{FE0F}';
+
+fn continues_cluster(prev: Option<char>, ch: char, unpaired_flag: bool) -> bool {
+    let Some(prev) = prev else {
+        return false;
+    };
+    if prev == '\t' || prev == '\n' || prev == '\r' {
+        return false;
+    }
+
+    ch.width() == Some(0)
+        || prev == ZERO_WIDTH_JOINER
+        || (unpaired_flag && is_regional_indicator(ch))
+        || is_skin_tone_modifier(ch)
+}
+
+/// zero width characters that change how others are displayed but aren't displayed themselves
+fn is_format_char(ch: char) -> bool {
+    matches!(ch,
+        '\u{200B}'..='\u{200F}' // zero width space, (non-)joiner, direction marks
+        | '\u{2060}'..='\u{2064}' // word joiner, invisible operators
+        | '\u{FE00}'..='\u{FE0F}' // variation selectors
+        | '\u{FEFF}' // byte order mark
+        | '\u{E0000}'..='\u{E01EF}' // tags, variation selectors supplement
+    )
+}
+
+fn is_regional_indicator(ch: char) -> bool {
+    matches!(ch, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+fn is_skin_tone_modifier(ch: char) -> bool {
+    matches!(ch, '\u{1F3FB}'..='\u{1F3FF}')
 }
 
 /// returns linebreak at the end of the slice (if any)
@@ -139,3 +289,69 @@ fn linebreak_of_line(line: &RopeSlice) -> Option<&'static str> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_col() {
+        // wide characters
+        display_col_test("漢字x", &[0, 2, 4, 5]);
+        display_col_test("a😀b", &[0, 1, 3, 4]);
+
+        // combining marks and emoji sequences take up the width of their first character
+        display_col_test("e\u{301}x", &[0, 1, 1, 2]);
+        display_col_test("👍🏽x", &[0, 2, 2, 3]);
+        display_col_test("👩\u{200D}💻x", &[0, 2, 2, 2, 3]);
+        display_col_test("🇯🇵🇺🇸", &[0, 2, 2, 4, 4]);
+        display_col_test("❤\u{FE0F}x", &[0, 2, 2, 3]);
+
+        // tabs go to the next tab stop after wide characters
+        display_col_test("漢\tx", &[0, 2, 4, 5]);
+        display_col_test("a\t漢\tx", &[0, 1, 4, 6, 8, 9]);
+
+        // past the end of the line
+        let line = Rope::from_str("漢\n");
+        assert_eq!(line.line(0).display_col(3, 4), 4);
+    }
+
+    #[test]
+    fn test_char_col_at_display() {
+        let line = Rope::from_str("a漢👍🏽\tb\n");
+        let line = line.line(0);
+        let char_cols: Vec<usize> = (0..11)
+            .map(|display_col| line.char_col_at_display(display_col, 4))
+            .collect();
+        // a=0, 漢=1..3, 👍🏽=3..5, tab=5..8, b=8, past the end
+        assert_eq!(char_cols, [0, 1, 2, 2, 4, 4, 4, 5, 5, 6, 7]);
+
+        // every char column in a cluster maps back to the start or end of the cluster
+        for col in 0..line.len_chars_no_linebreak() {
+            let display_col = line.display_col(col, 4);
+            let back = line.char_col_at_display(display_col, 4);
+            assert!(
+                line.is_cluster_boundary(back),
+                "{col} -> {display_col} -> {back}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_cluster_boundary() {
+        let text = Rope::from_str("x👩\u{200D}💻e\u{301}");
+        let line = text.line(0);
+        let boundaries: Vec<bool> = (0..=6).map(|col| line.is_cluster_boundary(col)).collect();
+        assert_eq!(boundaries, [true, true, false, false, true, false, true]);
+    }
+
+    /// check the display column at each char column of the line (including the end)
+    fn display_col_test(line: &str, expected: &[usize]) {
+        let text = Rope::from_str(line);
+        let line = text.line(0);
+        let display_cols: Vec<usize> = (0..expected.len())
+            .map(|col| line.display_col(col, 4))
+            .collect();
+        assert_eq!(display_cols, expected, "for {:?}", line);
+    }
+}
@@ -41,7 +41,8 @@ impl SearchPopup {
             let response = ui.interact(rect, id, egui::Sense::click_and_drag());
 
             // set background color
-            ui.painter().rect_filled(rect, 0.0, theme::POPUP_BACKGROUND);
+            ui.painter()
+                .rect_filled(rect, 0.0, theme::current().editor.popup_background);
 
             self.draw_text_box(ui, source, source_changed, rect);
             self.draw_buttons(ui, rect);
@@ -94,7 +95,7 @@ impl SearchPopup {
                 rect.right_top() - Vec2::new(60.0, 0.0) + MARGIN,
                 Vec2::new(20.0, rect.height()) - (MARGIN * 2.0),
             ),
-            egui::Button::new("^").fill(theme::POPUP_BACKGROUND),
+            egui::Button::new("^").fill(theme::current().editor.popup_background),
         );
         if up_response.clicked() {
             if let Some(r) = self.results.as_mut() {
@@ -108,7 +109,7 @@ impl SearchPopup {
                 rect.right_top() - Vec2::new(40.0, 0.0) + MARGIN,
                 Vec2::new(20.0, rect.height()) - (MARGIN * 2.0),
             ),
-            egui::Button::new("v").fill(theme::POPUP_BACKGROUND),
+            egui::Button::new("v").fill(theme::current().editor.popup_background),
         );
         if down_response.clicked() {
             if let Some(r) = self.results.as_mut() {
@@ -122,7 +123,7 @@ impl SearchPopup {
                 rect.right_top() - Vec2::new(20.0, 0.0) + MARGIN,
                 Vec2::new(20.0, rect.height()) - (MARGIN * 2.0),
             ),
-            egui::Button::new("\u{1F5D9}").fill(theme::POPUP_BACKGROUND),
+            egui::Button::new("\u{1F5D9}").fill(theme::current().editor.popup_background),
         );
         if close_response.clicked() {
             self.close();
@@ -172,6 +173,10 @@ impl SearchResults {
         temp
     }
 
+    pub fn results(&self) -> &[TextRange] {
+        &self.results
+    }
+
     pub fn current(&self) -> TextRange {
         self.results[self.current]
     }
@@ -201,12 +206,12 @@ impl SearchResults {
     ) {
         for (idx, range) in self.results.iter().enumerate() {
             let stroke = if idx == self.current {
-                Stroke::new(2.0, theme::SEARCH_RESULT_SELECTED)
+                Stroke::new(2.0, theme::current().editor.search_result_selected)
             } else {
                 Stroke::NONE
             };
             range.draw_selection_blocks(
-                theme::SEARCH_RESULT,
+                theme::current().editor.search_result,
                 stroke,
                 offset,
                 padding,
@@ -0,0 +1,303 @@
+use ropey::{Rope, RopeSlice};
+
+use super::{
+    rope_ext::{display_widths, RopeSliceExt},
+    text_range::TextPoint,
+};
+
+/// Where lines are broken into multiple visual rows so they fit in the width of the editor.
+/// When soft wrap is off, every line is a single row.
+#[derive(Debug, Default)]
+pub struct SoftWrap {
+    /// the number of columns that fit in a row, or None if soft wrap is off
+    max_cols: Option<usize>,
+
+    /// the rows of each line after the first (empty if the line fits on one row)
+    wrapped_rows: Vec<Vec<WrappedRow>>,
+
+    /// the visual row each line starts on
+    first_rows: Vec<usize>,
+}
+
+/// A row that a line continues on after wrapping
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WrappedRow {
+    /// the char column the row starts at
+    pub col: usize,
+
+    /// the display column of the start of the row within the unwrapped line
+    pub display_col: usize,
+
+    /// the column the row is drawn at (so it lines up with the indent of the line)
+    pub indent: usize,
+}
+
+impl WrappedRow {
+    const FIRST: WrappedRow = WrappedRow {
+        col: 0,
+        display_col: 0,
+        indent: 0,
+    };
+}
+
+impl SoftWrap {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Wrap each line of the text so its rows fit within `max_cols` columns.
+    /// Lines are broken after whitespace where possible and continue at their indent.
+    pub fn new(text: &Rope, max_cols: usize, tab_width: usize) -> Self {
+        let max_cols = max_cols.max(1);
+        let wrapped_rows: Vec<Vec<WrappedRow>> = text
+            .lines()
+            .map(|line| wrap_line(line, max_cols, tab_width))
+            .collect();
+
+        let mut first_rows = Vec::with_capacity(wrapped_rows.len());
+        let mut row = 0;
+        for rows in &wrapped_rows {
+            first_rows.push(row);
+            row += rows.len() + 1;
+        }
+
+        SoftWrap {
+            max_cols: Some(max_cols),
+            wrapped_rows,
+            first_rows,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_cols.is_some()
+    }
+
+    pub fn max_cols(&self) -> Option<usize> {
+        self.max_cols
+    }
+
+    /// The rows the line continues on after the first
+    pub fn wrapped_rows(&self, line: usize) -> &[WrappedRow] {
+        self.wrapped_rows.get(line).map_or(&[], |rows| rows)
+    }
+
+    /// The number of visual rows the line takes up
+    pub fn row_count(&self, line: usize) -> usize {
+        self.wrapped_rows(line).len() + 1
+    }
+
+    /// The visual row that the line starts on.
+    /// Lines past the end of the text are assumed to take up one row each.
+    pub fn first_row(&self, line: usize) -> usize {
+        if let Some(row) = self.first_rows.get(line) {
+            return *row;
+        }
+        match self.first_rows.len().checked_sub(1) {
+            Some(last) => self.first_rows[last] + self.row_count(last) + (line - last - 1),
+            None => line,
+        }
+    }
+
+    /// The row within its line that a point is displayed on.
+    /// A point at the boundary between two rows is at the start of the later one.
+    pub fn row_in_line(&self, point: TextPoint) -> usize {
+        self.wrapped_rows(point.line)
+            .iter()
+            .take_while(|row| row.col <= point.col)
+            .count()
+    }
+
+    /// The column that a point is displayed at within its row
+    pub fn visual_col(&self, point: TextPoint, source: &Rope, tab_width: usize) -> usize {
+        let display_col = match source.get_line(point.line) {
+            Some(line) => line.display_col(point.col, tab_width),
+            None => point.col,
+        };
+        let row = self.row_start(point.line, self.row_in_line(point));
+        row.indent + display_col - row.display_col
+    }
+
+    /// The point displayed closest to a column within a row of a line.
+    /// The point is kept within the row, so it may be past the end of the line on its last row.
+    pub fn point_at(
+        &self,
+        line: usize,
+        row: usize,
+        visual_col: usize,
+        source: &Rope,
+        tab_width: usize,
+    ) -> TextPoint {
+        let Some(text) = source.get_line(line) else {
+            return TextPoint::new(line, visual_col);
+        };
+
+        let rows = self.wrapped_rows(line);
+        let row = row.min(rows.len());
+        let start = self.row_start(line, row);
+
+        let display_col = start.display_col + visual_col.saturating_sub(start.indent);
+        let mut col = text
+            .char_col_at_display(display_col, tab_width)
+            .max(start.col);
+
+        // the end of a row is the start of the next one, so stop before the last cluster
+        if let Some(next) = rows.get(row) {
+            if col >= next.col {
+                col = next.col - 1;
+                while col > start.col && !text.is_cluster_boundary(col) {
+                    col -= 1;
+                }
+            }
+        }
+
+        TextPoint::new(line, col)
+    }
+
+    fn row_start(&self, line: usize, row: usize) -> WrappedRow {
+        match row.checked_sub(1) {
+            Some(idx) => self.wrapped_rows(line)[idx],
+            None => WrappedRow::FIRST,
+        }
+    }
+}
+
+/// Find where a line needs to be broken to fit within `max_cols` columns
+fn wrap_line(line: RopeSlice, max_cols: usize, tab_width: usize) -> Vec<WrappedRow> {
+    let line = line.excluding_linebreak();
+
+    // continue at the indent of the line so the rows stay within its block,
+    // as long as that leaves enough room for the text
+    let hanging_indent = line.indent_width(tab_width).min(max_cols / 2);
+    let content_start = line.whitespace_at_start();
+
+    let mut rows = vec![];
+    let mut row = WrappedRow::FIRST;
+
+    // the start of the last word, where the row could be broken
+    let mut break_point: Option<WrappedRow> = None;
+
+    let mut display_col = 0;
+    let mut prev_whitespace = false;
+    for (col, (ch, width)) in display_widths(line.chars(), tab_width).enumerate() {
+        // characters within a cluster stay with its first character
+        if width == 0 {
+            continue;
+        }
+
+        let whitespace = ch.is_whitespace();
+        if prev_whitespace && !whitespace && col > content_start {
+            break_point = Some(WrappedRow {
+                col,
+                display_col,
+                indent: hanging_indent,
+            });
+        }
+
+        // whitespace is allowed to hang past the edge so that rows start with words
+        while !whitespace
+            && col > row.col
+            && row.indent + display_col + width - row.display_col > max_cols
+        {
+            row = match break_point.take() {
+                Some(point) if point.col > row.col => point,
+                // the word is too long to fit in a row, so break it
+                _ => WrappedRow {
+                    col,
+                    display_col,
+                    indent: hanging_indent,
+                },
+            };
+            rows.push(row);
+        }
+
+        prev_whitespace = whitespace;
+        display_col += width;
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_line() {
+        // breaks after whitespace
+        wrap_test("aaa bbb ccc", 8, &["aaa bbb ", "ccc"]);
+        wrap_test("aaa bbb ccc", 6, &["aaa ", "bbb ", "ccc"]);
+
+        // words longer than a row are broken
+        wrap_test("abcdefghij", 4, &["abcd", "efgh", "ij"]);
+
+        // whitespace hangs past the edge instead of starting a row
+        wrap_test("aaaa    bbbb", 4, &["aaaa    ", "bbbb"]);
+
+        // continued rows line up with the indent
+        wrap_test("    aaa bbb", 8, &["    aaa ", "bbb"]);
+        let wrap = SoftWrap::new(&Rope::from_str("    aaa bbb"), 8, 4);
+        assert_eq!(wrap.wrapped_rows(0)[0].indent, 4);
+
+        // wide characters
+        wrap_test("漢字漢字", 5, &["漢字", "漢字"]);
+
+        // lines that fit aren't wrapped
+        wrap_test("aaa", 3, &["aaa"]);
+    }
+
+    #[test]
+    fn test_rows() {
+        let text = Rope::from_str("aaa bbb\nccc\nddd eee fff\n");
+        let wrap = SoftWrap::new(&text, 4, 4);
+
+        let row_counts: Vec<usize> = (0..4).map(|line| wrap.row_count(line)).collect();
+        assert_eq!(row_counts, [2, 1, 3, 1]);
+        let first_rows: Vec<usize> = (0..6).map(|line| wrap.first_row(line)).collect();
+        assert_eq!(first_rows, [0, 2, 3, 6, 7, 8]);
+
+        // the boundary between rows is on the later row
+        assert_eq!(wrap.row_in_line(TextPoint::new(0, 3)), 0);
+        assert_eq!(wrap.row_in_line(TextPoint::new(0, 4)), 1);
+        assert_eq!(wrap.visual_col(TextPoint::new(2, 9), &text, 4), 1);
+    }
+
+    #[test]
+    fn test_point_at() {
+        let text = Rope::from_str("  aaa bbb ccc\n");
+        let wrap = SoftWrap::new(&text, 7, 4);
+        assert_eq!(wrap.wrapped_rows(0).len(), 2);
+
+        // every point maps back to itself
+        for col in 0..=13 {
+            let point = TextPoint::new(0, col);
+            let row = wrap.row_in_line(point);
+            let visual_col = wrap.visual_col(point, &text, 4);
+            assert_eq!(wrap.point_at(0, row, visual_col, &text, 4), point);
+        }
+
+        // past the end of a row stays on the row
+        assert_eq!(wrap.point_at(0, 0, 20, &text, 4), TextPoint::new(0, 5));
+        // within the indent of a wrapped row goes to its start
+        assert_eq!(wrap.point_at(0, 1, 0, &text, 4), TextPoint::new(0, 6));
+    }
+
+    /// check the text of each row that a line is wrapped into
+    fn wrap_test(line: &str, max_cols: usize, expected: &[&str]) {
+        let text = Rope::from_str(line);
+        let wrap = SoftWrap::new(&text, max_cols, 4);
+
+        let mut starts: Vec<usize> = wrap.wrapped_rows(0).iter().map(|row| row.col).collect();
+        starts.insert(0, 0);
+        starts.push(line.chars().count());
+        let rows: Vec<String> = starts
+            .windows(2)
+            .map(|range| {
+                line.chars()
+                    .skip(range[0])
+                    .take(range[1] - range[0])
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows, expected, "for {:?} in {} columns", line, max_cols);
+    }
+}
@@ -1,7 +1,7 @@
 use ropey::Rope;
 use std::borrow::Cow;
 
-use super::TextEdit;
+use super::{IndentStyle, TextEdit};
 use crate::{
     block_editor::{
         rope_ext::{RopeExt, RopeSliceExt},
@@ -12,8 +12,6 @@ use crate::{
     lang::config::NewScopeChar,
 };
 
-const TAB_SIZE: usize = 4;
-
 /// Find the edit for inserting a single character. Returns the edit and the new selection.
 pub fn edit_for_insert_char<'a>(
     selection: TextRange,
@@ -91,18 +89,23 @@ pub fn edit_for_insert_char<'a>(
     (Some(edit), new_selection)
 }
 
+/// Find the edit for inserting a newline. Returns the edit and the new selection.
+/// `indent_level` is the level from the language's indent query, if it could find one.
+/// Otherwise the indent is copied from the current line (increasing after a scope char).
 pub fn edit_for_insert_newline<'a>(
     selection: TextRange,
     source: &Rope,
     new_scope_char: NewScopeChar,
+    indent_level: Option<usize>,
+    style: IndentStyle,
 ) -> (TextEdit<'a>, TextRange) {
     // find linebreak used in source
     let linebreak = source.detect_linebreak();
 
-    // find previous indent level and set new line to that many spaces
+    // find previous indent level and set new line to that many columns
     let old_selection = selection.ordered();
     let curr_line = source.line(old_selection.start.line);
-    let prev_indent = curr_line.whitespace_at_start();
+    let prev_indent = curr_line.indent_width(style.width);
 
     let char_before_cursor = if old_selection.start.col > 1 {
         curr_line.get_char(old_selection.start.col - 1)
@@ -118,14 +121,17 @@ pub fn edit_for_insert_newline<'a>(
     // find the indent level of the next line
     // (same as current line & increase if character before cursor is a scope char)
     let indent_inc = if char_before_cursor == Some(new_scope_char.char()) {
-        TAB_SIZE
+        style.width
     } else {
         0
     };
-    let next_indent = prev_indent + indent_inc;
+    let next_indent = match indent_level {
+        Some(level) => level * style.width,
+        None => prev_indent + indent_inc,
+    };
 
     // update source
-    let indent: &str = &" ".repeat(next_indent);
+    let indent = style.indent_str(next_indent);
     let to_insert = format!("{linebreak}{indent}");
 
     if !middle_of_bracket {
@@ -135,12 +141,17 @@ pub fn edit_for_insert_newline<'a>(
     } else {
         // if in the middle of a bracket, insert an extra linebreak and indent
         // but only move the cursor to the newline in the middle
-        let following_indent = " ".repeat(prev_indent);
+        let following_indent = match indent_level {
+            Some(level) => style.indent_str(level.saturating_sub(1) * style.width),
+            None => style.indent_str(prev_indent),
+        };
         let extra_to_insert = format!("{to_insert}{linebreak}{following_indent}");
 
         let edit = TextEdit::new(Cow::Owned(extra_to_insert), old_selection);
-        let new_selection =
-            TextRange::new_cursor(TextPoint::new(old_selection.start.line + 1, next_indent));
+        let new_selection = TextRange::new_cursor(TextPoint::new(
+            old_selection.start.line + 1,
+            indent.chars().count(),
+        ));
         (edit, new_selection)
     }
 }
@@ -150,6 +161,7 @@ pub fn edit_for_delete<'a>(
     source: &Rope,
     movement: TextMovement,
     pseudo_selection: Option<TextRange>,
+    style: IndentStyle,
     input_ignore_stack: &mut Vec<&'static str>,
     paired_delete_stack: &mut Vec<bool>,
 ) -> (Option<TextEdit<'a>>, TextRange) {
@@ -170,7 +182,7 @@ pub fn edit_for_delete<'a>(
             let line_indent = source.line(old_selection.start.line).whitespace_at_start();
             let at_indent = old_selection.start.col == line_indent;
             if at_indent {
-                let (edit, new_selection) = edit_for_unindent(old_selection, source);
+                let (edit, new_selection) = edit_for_unindent(old_selection, source, style);
                 return (Some(edit), new_selection);
             }
 
@@ -184,8 +196,10 @@ pub fn edit_for_delete<'a>(
                 0
             };
 
+            // delete the whole grapheme cluster before the cursor
+            let delete_start = old_selection.expanded_by(movement, source).ordered().start;
             TextRange::new(
-                TextPoint::new(old_selection.start.line, old_selection.start.col - 1),
+                delete_start,
                 TextPoint::new(
                     old_selection.start.line,
                     old_selection.start.col + after_delete_amount,
@@ -215,7 +229,11 @@ pub fn edit_for_delete<'a>(
     (Some(edit), new_selection)
 }
 
-pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>, TextRange) {
+pub fn edit_for_indent<'a>(
+    selection: TextRange,
+    source: &Rope,
+    style: IndentStyle,
+) -> (TextEdit<'a>, TextRange) {
     let ordered = selection.ordered();
 
     // expand selection to include entire lines
@@ -235,15 +253,15 @@ pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>
         // get current indent of line
         let line = new_text.line(line_num);
         let curr_indent = line.whitespace_at_start();
+        let curr_width = line.indent_width(style.width);
         let line_len = line.len_chars_no_linebreak();
 
-        // make what to add to start of line
-        let indent_amount = TAB_SIZE - (curr_indent % TAB_SIZE);
-        let indent = " ".repeat(indent_amount);
-
-        // add it
-        let start_of_line = TextPoint::new(line_num, 0);
-        new_text.insert(start_of_line.char_idx_in(&new_text), &indent);
+        // replace the indent with one indented to the next level
+        let indent = style.indent_str(curr_width - (curr_width % style.width) + style.width);
+        let new_indent = indent.chars().count();
+        let start_of_line = new_text.line_to_char(line_num);
+        new_text.remove(start_of_line..start_of_line + curr_indent);
+        new_text.insert(start_of_line, &indent);
 
         // Adjust selection if first or last line if the cursor for that line is in the text.
         // If the line is entirely whitespace, move the cursor anyway.
@@ -251,12 +269,14 @@ pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>
         if full_selection.start.line + line_num == new_selection.start.line
             && (new_selection.start.col > curr_indent || line_len == curr_indent)
         {
-            new_selection.start.col += indent_amount;
+            new_selection.start.col =
+                (new_selection.start.col + new_indent).saturating_sub(curr_indent);
         }
         if full_selection.start.line + line_num == new_selection.end.line
             && (new_selection.end.col > curr_indent || line_len == curr_indent)
         {
-            new_selection.end.col += indent_amount;
+            new_selection.end.col =
+                (new_selection.end.col + new_indent).saturating_sub(curr_indent);
         }
     }
 
@@ -266,7 +286,11 @@ pub fn edit_for_indent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>
     )
 }
 
-pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'a>, TextRange) {
+pub fn edit_for_unindent<'a>(
+    selection: TextRange,
+    source: &Rope,
+    style: IndentStyle,
+) -> (TextEdit<'a>, TextRange) {
     // apply to every line of selection
     let ordered = selection.ordered();
 
@@ -292,17 +316,18 @@ pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'
             continue;
         }
 
-        // remove start of line
-        let unindent_amount = if curr_indent.is_multiple_of(TAB_SIZE) {
-            TAB_SIZE
+        // replace the indent with one at the previous level
+        let curr_width = line.indent_width(style.width);
+        let new_width = if curr_width.is_multiple_of(style.width) {
+            curr_width.saturating_sub(style.width)
         } else {
-            curr_indent % TAB_SIZE
+            curr_width - (curr_width % style.width)
         };
-        let remove_range = TextRange::new(
-            TextPoint::new(line_num, 0),
-            TextPoint::new(line_num, unindent_amount),
-        );
-        new_text.remove(remove_range.char_range_in(&new_text));
+        let indent = style.indent_str(new_width);
+        let unindent_amount = curr_indent.saturating_sub(indent.chars().count());
+        let start_of_line = new_text.line_to_char(line_num);
+        new_text.remove(start_of_line..start_of_line + curr_indent);
+        new_text.insert(start_of_line, &indent);
 
         // adjust selection if first or last line
         if full_selection.start.line + line_num == new_selection.start.line {
@@ -319,6 +344,67 @@ pub fn edit_for_unindent<'a>(selection: TextRange, source: &Rope) -> (TextEdit<'
     )
 }
 
+/// Find the edit that sets the indent of each line starting at `first_line` to the given number of columns.
+/// Lines without an indent are left alone. Returns None if no lines would change.
+pub fn edit_for_reindent<'a>(
+    selection: TextRange,
+    first_line: usize,
+    indents: &[Option<usize>],
+    source: &Rope,
+    style: IndentStyle,
+) -> Option<(TextEdit<'a>, TextRange)> {
+    let last_line = first_line + indents.len().checked_sub(1)?;
+
+    // expand selection to include entire lines
+    let full_selection = TextRange::new(
+        TextPoint::new(first_line, 0),
+        TextPoint::new(last_line, source.line(last_line).len_chars_no_linebreak()),
+    );
+    let mut new_text: Rope = source.slice(full_selection.char_range_in(source)).into();
+
+    let mut new_selection = selection;
+    let mut changed = false;
+
+    for (line_num, new_indent) in indents.iter().enumerate() {
+        let Some(new_indent) = *new_indent else {
+            continue;
+        };
+        let line = new_text.line(line_num);
+        let curr_indent = line.whitespace_at_start();
+        let indent = style.indent_str(new_indent);
+        if line.slice(..curr_indent) == indent {
+            continue;
+        }
+        changed = true;
+
+        // replace the existing indent
+        let line_start = new_text.line_to_char(line_num);
+        new_text.remove(line_start..line_start + curr_indent);
+        new_text.insert(line_start, &indent);
+        let new_indent = indent.chars().count();
+
+        // move the selection with the text, or keep it within the indent
+        for point in [&mut new_selection.start, &mut new_selection.end] {
+            if point.line == first_line + line_num {
+                point.col = if point.col >= curr_indent {
+                    point.col - curr_indent + new_indent
+                } else {
+                    usize::min(point.col, new_indent)
+                };
+            }
+        }
+    }
+
+    if !changed {
+        return None;
+    }
+
+    Some((
+        TextEdit::new(Cow::Owned(new_text.to_string()), full_selection),
+        new_selection,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -518,6 +604,24 @@ mod tests {
             &mut vec![],
         );
 
+        // Delete a whole grapheme cluster
+        backspace_test(
+            "a👍🏽→←",
+            "a→←",
+            TextMovement::horizontal(HUnit::Grapheme, HDir::Left),
+            None,
+            &mut vec![],
+            &mut vec![],
+        );
+        backspace_test(
+            "e\u{301}→←x",
+            "→←x",
+            TextMovement::horizontal(HUnit::Grapheme, HDir::Left),
+            None,
+            &mut vec![],
+            &mut vec![],
+        );
+
         // Ctrl + Backspace
         backspace_test(
             "aaa→←",
@@ -649,7 +753,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_reindent() {
+        // set the indent of a single line
+        reindent_test("→←x = 1", &[Some(4)], "    →←x = 1");
+        reindent_test("        x = →1←", &[Some(4)], "    x = →1←");
+
+        // lines without an indent are left alone
+        reindent_test(
+            "→a\n      b\n  c←",
+            &[Some(0), None, Some(8)],
+            "→a\n      b\n        c←",
+        );
+
+        // a cursor in the indent stays within it
+        reindent_test("      →←  x", &[Some(4)], "    →←x");
+
+        // nothing to change
+        let (src, sel) = generate_state("    →←x");
+        assert!(edit_for_reindent(sel, 0, &[Some(4)], &src, IndentStyle::DEFAULT).is_none());
+
+        // indents are written in the file's style
+        reindent_test_with_style("    →←x", &[Some(8)], "\t\t→←x", TABS);
+        reindent_test_with_style("\t→←x", &[Some(6)], "\t  →←x", TABS);
+    }
+
+    #[test]
+    fn test_indent_with_tabs() {
+        indent_test_with_style("x→←", "\tx→←", TABS);
+        indent_test_with_style("\tx→←", "\t\tx→←", TABS);
+
+        // mixed indents are replaced with tabs
+        indent_test_with_style("  \tx→←", "\t\tx→←", TABS);
+        indent_test_with_style("  x→←", "\tx→←", TABS);
+
+        unindent_test_with_style("\t\tx→←", "\tx→←", TABS);
+        unindent_test_with_style("\t  x→←", "\tx→←", TABS);
+        unindent_test_with_style("    x→←", "x→←", TABS);
+    }
+
     /* --------------------------------- helpers -------------------------------- */
+    const TABS: IndentStyle = IndentStyle {
+        use_tabs: true,
+        width: 4,
+    };
+
     fn char_insert_test(start: &str, add: &str, target: &str) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
@@ -663,9 +811,13 @@ mod tests {
     }
 
     fn indent_test(start: &str, target: &str) {
+        indent_test_with_style(start, target, IndentStyle::DEFAULT);
+    }
+
+    fn indent_test_with_style(start: &str, target: &str, style: IndentStyle) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
-        let (edit, end_sel) = edit_for_indent(start_sel, &src);
+        let (edit, end_sel) = edit_for_indent(start_sel, &src, style);
 
         edit.apply_to_rope(&mut src);
 
@@ -674,9 +826,33 @@ mod tests {
     }
 
     fn unindent_test(start: &str, target: &str) {
+        unindent_test_with_style(start, target, IndentStyle::DEFAULT);
+    }
+
+    fn unindent_test_with_style(start: &str, target: &str, style: IndentStyle) {
+        let (mut src, start_sel) = generate_state(start);
+        let (target_src, target_sel) = generate_state(target);
+        let (edit, end_sel) = edit_for_unindent(start_sel, &src, style);
+
+        edit.apply_to_rope(&mut src);
+
+        assert_eq!(src, target_src);
+        assert_eq!(end_sel, target_sel);
+    }
+
+    fn reindent_test(start: &str, indents: &[Option<usize>], target: &str) {
+        reindent_test_with_style(start, indents, target, IndentStyle::DEFAULT);
+    }
+
+    fn reindent_test_with_style(
+        start: &str,
+        indents: &[Option<usize>],
+        target: &str,
+        style: IndentStyle,
+    ) {
         let (mut src, start_sel) = generate_state(start);
         let (target_src, target_sel) = generate_state(target);
-        let (edit, end_sel) = edit_for_unindent(start_sel, &src);
+        let (edit, end_sel) = edit_for_reindent(start_sel, 0, indents, &src, style).unwrap();
 
         edit.apply_to_rope(&mut src);
 
@@ -699,6 +875,7 @@ mod tests {
             &src,
             movement,
             pseudo_selection,
+            IndentStyle::DEFAULT,
             input_ignore_stack,
             paired_delete_stack,
         );
@@ -0,0 +1,147 @@
+use ropey::Rope;
+
+use crate::block_editor::rope_ext::RopeSliceExt;
+
+/// How the lines of a file are indented
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndentStyle {
+    /// whether to indent with tab characters instead of spaces
+    pub use_tabs: bool,
+
+    /// the number of columns in one level of indentation (and the width of a tab)
+    pub width: usize,
+}
+
+impl IndentStyle {
+    pub const DEFAULT: IndentStyle = IndentStyle {
+        use_tabs: false,
+        width: 4,
+    };
+
+    /// Guess the indent style from the existing indentation in the text.
+    /// Anything that can't be determined (like the width of a tab) is taken from `fallback`.
+    pub fn detect(text: &Rope, fallback: IndentStyle) -> IndentStyle {
+        // only look at the start of large files
+        const MAX_LINES: usize = 1000;
+
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+
+        // how often the indent changes by each amount of spaces between consecutive lines
+        let mut space_changes = [0; 9];
+        let mut prev_spaces = 0;
+
+        for line in text.lines().take(MAX_LINES) {
+            // blank lines don't tell us anything
+            let indent = line.whitespace_at_start();
+            if indent == line.len_chars_no_linebreak() {
+                continue;
+            }
+
+            let spaces = line.chars().take_while(|c| *c == ' ').count();
+            if line.char(0) == '\t' {
+                tab_lines += 1;
+            } else if indent > 0 && spaces == indent {
+                space_lines += 1;
+            }
+
+            // only lines indented purely with spaces count towards the width
+            if spaces == indent {
+                let change = spaces.abs_diff(prev_spaces);
+                if change > 1 && change < space_changes.len() {
+                    space_changes[change] += 1;
+                }
+                prev_spaces = spaces;
+            }
+        }
+
+        if tab_lines == 0 && space_lines == 0 {
+            return fallback;
+        }
+        if tab_lines > space_lines {
+            return IndentStyle {
+                use_tabs: true,
+                width: fallback.width,
+            };
+        }
+
+        // pick the most common change, preferring the fallback width in a tie
+        let mut width = fallback.width;
+        for (change, count) in space_changes.iter().enumerate() {
+            if *count > space_changes.get(width).copied().unwrap_or(0) {
+                width = change;
+            }
+        }
+        IndentStyle {
+            use_tabs: false,
+            width,
+        }
+    }
+
+    /// The whitespace for an indent of the given number of columns.
+    /// When using tabs, columns that don't fill a whole tab are made up with spaces.
+    pub fn indent_str(&self, cols: usize) -> String {
+        if self.use_tabs {
+            format!(
+                "{}{}",
+                "\t".repeat(cols / self.width),
+                " ".repeat(cols % self.width)
+            )
+        } else {
+            " ".repeat(cols)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        detect_test(
+            "def f():\n  if x:\n    pass\n",
+            IndentStyle::DEFAULT,
+            false,
+            2,
+        );
+        detect_test(
+            "def f():\n    if x:\n        pass\n",
+            IndentStyle::DEFAULT,
+            false,
+            4,
+        );
+        detect_test("int f() {\n\treturn 0;\n}\n", IndentStyle::DEFAULT, true, 4);
+
+        // no indentation uses the fallback
+        let fallback = IndentStyle {
+            use_tabs: true,
+            width: 8,
+        };
+        detect_test("x = 1\ny = 2\n", fallback, true, 8);
+
+        // a few odd lines don't change the guess
+        detect_test(
+            "a:\n    b:\n        c\n    d\n  e\n",
+            IndentStyle::DEFAULT,
+            false,
+            4,
+        );
+    }
+
+    #[test]
+    fn test_indent_str() {
+        let tabs = IndentStyle {
+            use_tabs: true,
+            width: 4,
+        };
+        assert_eq!(tabs.indent_str(8), "\t\t");
+        assert_eq!(tabs.indent_str(6), "\t  ");
+        assert_eq!(IndentStyle::DEFAULT.indent_str(6), "      ");
+    }
+
+    fn detect_test(text: &str, fallback: IndentStyle, use_tabs: bool, width: usize) {
+        let detected = IndentStyle::detect(&Rope::from_str(text), fallback);
+        assert_eq!(detected, IndentStyle { use_tabs, width }, "for {:?}", text);
+    }
+}
@@ -1,17 +1,23 @@
 use ropey::Rope;
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Range};
 
 use crate::{
-    block_editor::TextRange,
-    lang::{tree_manager::TreeManager, Language},
+    block_editor::{rope_ext::RopeSliceExt, TextRange},
+    lang::{
+        config::NewScopeChar,
+        tree_manager::{TreeChange, TreeManager},
+        Language,
+    },
 };
 use edit_generation::*;
 use undo_manager::{UndoItem, UndoStopCondition::*};
 
 mod edit_generation;
+mod indent_style;
 pub mod text_edit;
 mod undo_manager;
 
+pub use indent_style::IndentStyle;
 pub use text_edit::TextEdit;
 pub use undo_manager::UndoStopCondition;
 
@@ -42,12 +48,22 @@ pub struct Source {
 
     /// whether the text has changed since the last time it was checked
     text_changed: bool,
+
+    /// how the text is indented
+    indent_style: IndentStyle,
+
+    /// the indent style to use when it isn't detected from the text
+    configured_indent_style: IndentStyle,
+
+    /// whether to detect the indent style from the text
+    detect_indent_style: bool,
 }
 
 impl Source {
     pub fn new(text: Rope, mut lang: Language) -> Self {
         let mut tree_manager = TreeManager::new(&mut lang);
         tree_manager.replace(&text, &mut lang);
+        let indent_style = IndentStyle::detect(&text, IndentStyle::DEFAULT);
         Self {
             text,
             lang,
@@ -57,6 +73,9 @@ impl Source {
             input_ignore_stack: Vec::new(),
             paired_delete_stack: Vec::new(),
             text_changed: true,
+            indent_style,
+            configured_indent_style: IndentStyle::DEFAULT,
+            detect_indent_style: true,
         }
     }
 
@@ -69,6 +88,7 @@ impl Source {
         self.input_ignore_stack.clear();
         self.paired_delete_stack.clear();
         self.text_changed = true;
+        self.update_indent_style();
     }
 
     pub fn text(&self) -> &Rope {
@@ -79,6 +99,47 @@ impl Source {
         self.tree_manager.get_cursor()
     }
 
+    /// Counts up each time the syntax tree changes
+    pub fn tree_version(&self) -> usize {
+        self.tree_manager.version()
+    }
+
+    /// How the syntax tree changed from the version before, if it was edited (instead of replaced)
+    pub fn last_tree_change(&self) -> Option<&TreeChange> {
+        self.tree_manager.last_change()
+    }
+
+    pub fn indent_style(&self) -> IndentStyle {
+        self.indent_style
+    }
+
+    /// Set the indent style to use for this file (from the language or file settings).
+    /// If `detect` is set, the style is only used when it can't be detected from the text.
+    pub fn configure_indent_style(&mut self, style: IndentStyle, detect: bool) {
+        self.configured_indent_style = style;
+        self.detect_indent_style = detect;
+
+        // tabs need to be laid out again if their width changed
+        let old_width = self.indent_style.width;
+        self.update_indent_style();
+        if self.indent_style.width != old_width {
+            self.text_changed = true;
+        }
+    }
+
+    /// The configured indent style and whether it is detected, for carrying over to a new source
+    pub fn indent_style_config(&self) -> (IndentStyle, bool) {
+        (self.configured_indent_style, self.detect_indent_style)
+    }
+
+    fn update_indent_style(&mut self) {
+        self.indent_style = if self.detect_indent_style {
+            IndentStyle::detect(&self.text, self.configured_indent_style)
+        } else {
+            self.configured_indent_style
+        };
+    }
+
     /// Return if the text has changed since the last time this was called
     pub fn has_text_changed_since_last_check(&mut self) -> bool {
         let changed = self.text_changed;
@@ -139,18 +200,186 @@ impl Source {
             &mut self.paired_delete_stack,
         );
 
-        if let Some(edit) = edit {
+        let new_selection = if let Some(edit) = edit {
             self.apply_edit_helper(&edit, IfNotMerged, false);
+            self.reindent_if_closing_scope(new_selection)
+        } else {
+            new_selection
+        };
+
+        selections.set_selection(new_selection, self);
+    }
+
+    /// Handle pasting text. Pasted lines are reindented to fit where they were pasted.
+    pub fn paste(&mut self, add: &str, selections: &mut Selections) {
+        let old_selection = selections.selection().ordered();
+        let edit = TextEdit::new(Cow::Borrowed(add), old_selection);
+        self.apply_edit_helper(&edit, Always, false);
+        let mut new_selection = TextRange::new_cursor(edit.new_end());
+
+        // only reindent if the paste started a line, otherwise the first line's indent is unrelated
+        let start = old_selection.start;
+        let pasted_lines = start.line..new_selection.end.line + 1;
+        let started_line = self.text.line(start.line).whitespace_at_start() >= start.col;
+        if pasted_lines.len() > 1 && started_line {
+            let levels = self.indent_levels(pasted_lines.clone());
+            let indents = if self.lang.config.new_scope_char == NewScopeChar::Colon {
+                // the indent determines the structure in these languages,
+                // so shift the pasted lines together to keep their relative indents
+                let first_indent =
+                    ropey::RopeSlice::from(add).indent_width(self.indent_style.width);
+                self.shifted_indents(pasted_lines.clone(), levels[0], first_indent)
+            } else {
+                let width = self.indent_style.width;
+                levels.iter().map(|l| l.map(|l| l * width)).collect()
+            };
+
+            if let Some((reindent, reindented_selection)) = edit_for_reindent(
+                new_selection,
+                pasted_lines.start,
+                &indents,
+                &self.text,
+                self.indent_style,
+            ) {
+                self.apply_edit_helper(&reindent, Never, false);
+                new_selection = reindented_selection;
+            }
         }
 
+        self.add_undo_stop();
         selections.set_selection(new_selection, self);
     }
 
+    /// Reindent the selected lines, or the whole document if nothing is selected, as a single undo step
+    pub fn reindent(&mut self, selections: &mut Selections) {
+        let selection = selections.selection();
+        let lines = if selection.is_cursor() {
+            0..self.text.len_lines()
+        } else {
+            let ordered = selection.ordered();
+            ordered.start.line..ordered.end.line + 1
+        };
+
+        let width = self.indent_style.width;
+        let indents: Vec<Option<usize>> = self
+            .indent_levels(lines.clone())
+            .into_iter()
+            .map(|level| level.map(|level| level * width))
+            .collect();
+        if let Some((edit, new_selection)) = edit_for_reindent(
+            selection,
+            lines.start,
+            &indents,
+            &self.text,
+            self.indent_style,
+        ) {
+            self.apply_edit_helper(&edit, Always, true);
+            selections.set_selection(new_selection, self);
+        }
+    }
+
+    /// After typing, fix the indent of the line if it now starts with something
+    /// that closes a scope (like a `}` or `end`). Returns the adjusted selection.
+    fn reindent_if_closing_scope(&mut self, selection: TextRange) -> TextRange {
+        let Some(query) = &self.lang.indent_query else {
+            return selection;
+        };
+
+        // only check when typing the first word on the line
+        let cursor = selection.start;
+        let line = self.text.line(cursor.line);
+        let indent = line.whitespace_at_start();
+        let typed = line.slice(indent..usize::max(indent, cursor.col));
+        if typed.len_chars() == 0 || typed.chars().any(char::is_whitespace) {
+            return selection;
+        }
+
+        let root = self.tree_manager.get_cursor().node();
+        if !query.line_starts_with_outdent(root, &self.text, cursor.line) {
+            return selection;
+        }
+
+        let indents: Vec<Option<usize>> = query
+            .line_levels(root, &self.text, cursor.line..cursor.line + 1)
+            .into_iter()
+            .map(|level| level.map(|level| level * self.indent_style.width))
+            .collect();
+        match edit_for_reindent(
+            selection,
+            cursor.line,
+            &indents,
+            &self.text,
+            self.indent_style,
+        ) {
+            Some((edit, new_selection)) => {
+                self.apply_edit_helper(&edit, Never, false);
+                new_selection
+            }
+            None => selection,
+        }
+    }
+
+    /// The indent level of each line from the language's indent query
+    fn indent_levels(&self, lines: Range<usize>) -> Vec<Option<usize>> {
+        match &self.lang.indent_query {
+            Some(query) => {
+                query.line_levels(self.tree_manager.get_cursor().node(), &self.text, lines)
+            }
+            None => vec![None; lines.len()],
+        }
+    }
+
+    /// Indents for the pasted lines that keep them the same relative to the first line,
+    /// with the first line moved to the given level.
+    /// `first_indent` is the indent the first line had in the pasted text.
+    fn shifted_indents(
+        &self,
+        lines: Range<usize>,
+        first_level: Option<usize>,
+        first_indent: usize,
+    ) -> Vec<Option<usize>> {
+        let Some(first_level) = first_level else {
+            return vec![None; lines.len()];
+        };
+
+        let target = first_level * self.indent_style.width;
+        let first_line = lines.start;
+        lines
+            .map(|line_num| {
+                let line = self.text.line(line_num);
+                if line_num == first_line {
+                    return Some(target);
+                }
+                let indent = line.indent_width(self.indent_style.width);
+                if line.whitespace_at_start() == line.len_chars_no_linebreak() {
+                    None
+                } else {
+                    Some((indent + target).saturating_sub(first_indent))
+                }
+            })
+            .collect()
+    }
+
     pub fn insert_newline(&mut self, selections: &mut Selections) {
+        let insert_byte = selections
+            .selection()
+            .ordered()
+            .start
+            .byte_idx_in(&self.text);
+        let indent_level = self.lang.indent_query.as_ref().and_then(|query| {
+            query.new_line_level(
+                self.tree_manager.get_cursor().node(),
+                &self.text,
+                insert_byte,
+            )
+        });
+
         let (edit, new_selection) = edit_for_insert_newline(
             selections.selection(),
             &self.text,
             self.lang.config.new_scope_char,
+            indent_level,
+            self.indent_style,
         );
         self.apply_edit_helper(&edit, Always, false);
 
@@ -163,6 +392,7 @@ impl Source {
             &self.text,
             movement,
             selections.pseudo_selection(),
+            self.indent_style,
             &mut self.input_ignore_stack,
             &mut self.paired_delete_stack,
         );
@@ -173,17 +403,51 @@ impl Source {
     }
 
     pub fn indent(&mut self, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_indent(selections.selection(), &self.text);
+        let (edit, new_selection) =
+            edit_for_indent(selections.selection(), &self.text, self.indent_style);
         self.apply_edit_helper(&edit, Always, true);
         selections.set_selection(new_selection, self);
     }
 
     pub fn unindent(&mut self, selections: &mut Selections) {
-        let (edit, new_selection) = edit_for_unindent(selections.selection(), &self.text);
+        let (edit, new_selection) =
+            edit_for_unindent(selections.selection(), &self.text, self.indent_style);
         self.apply_edit_helper(&edit, Always, true);
         selections.set_selection(new_selection, self);
     }
 
+    /// Rewrite the indent of every line using tabs or spaces, as a single undo step
+    pub fn convert_indentation(&mut self, use_tabs: bool, selections: &mut Selections) {
+        let style = IndentStyle {
+            use_tabs,
+            ..self.indent_style
+        };
+
+        // keep the width of each indent, skipping blank lines
+        let indents: Vec<Option<usize>> = self
+            .text
+            .lines()
+            .map(|line| {
+                let indent = line.whitespace_at_start();
+                (indent != line.len_chars_no_linebreak()).then(|| line.indent_width(style.width))
+            })
+            .collect();
+
+        if let Some((edit, new_selection)) =
+            edit_for_reindent(selections.selection(), 0, &indents, &self.text, style)
+        {
+            self.apply_edit_helper(&edit, Always, true);
+            selections.set_selection(new_selection, self);
+        }
+
+        self.indent_style = style;
+    }
+
+    /// Keep the next edit from being undone together with the last one
+    pub fn end_undo_step(&mut self) {
+        self.add_undo_stop();
+    }
+
     pub fn undo(&mut self, selections: &mut Selections) {
         if let Some(new_selection) = self.apply_undo() {
             self.text_changed = true;
@@ -191,6 +455,15 @@ impl Source {
         }
     }
 
+    /// Take back the last undo step as if it never happened (like a block drag that was cancelled),
+    /// so it can't be redone
+    pub fn revert_undo_step(&mut self, selections: &mut Selections) {
+        if let Some(new_selection) = self.apply_undo_without_redo() {
+            self.text_changed = true;
+            selections.set_selection(new_selection, self);
+        }
+    }
+
     pub fn redo(&mut self, selections: &mut Selections) {
         if let Some(new_selection) = self.apply_redo() {
             self.text_changed = true;
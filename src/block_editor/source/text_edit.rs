@@ -112,6 +112,23 @@ impl<'a> TextEdit<'a> {
 }
 
 impl Source {
+    /// If applying the edit would add syntax errors (ERROR or missing nodes) to the tree.
+    /// The edit is parsed on a scratch copy of the text and tree, so the source isn't changed.
+    pub fn edit_adds_errors(&mut self, edit: &TextEdit) -> bool {
+        let char_range = edit.range.char_range_in(&self.text);
+        let byte_range = edit.range.byte_range_in(&self.text);
+
+        let mut scratch_text = self.text.clone();
+        scratch_text.remove(char_range.clone());
+        scratch_text.insert(char_range.start, &edit.text);
+
+        let tree_edit = edit.input_edit(byte_range);
+        let scratch_tree = self
+            .tree_manager
+            .parse_edited(&scratch_text, tree_edit, &mut self.lang);
+        count_errors(scratch_tree.root_node()) > count_errors(self.tree_manager.root_node())
+    }
+
     /// Apply the text edit on the rope and tree manager. Returns the inverse text edit.
     pub(super) fn apply(&mut self, edit: &TextEdit) -> TextEdit<'static> {
         let char_range = edit.range.char_range_in(&self.text);
@@ -126,14 +143,7 @@ impl Source {
         self.text.insert(char_range.start, &edit.text);
 
         // update tree
-        let tree_edit = InputEdit {
-            start_byte: byte_range.start,
-            old_end_byte: byte_range.end,
-            new_end_byte: byte_range.start + edit.text.len(),
-            start_position: edit.range.start.into(),
-            old_end_position: edit.range.end.into(),
-            new_end_position: edit.new_end().into(),
-        };
+        let tree_edit = edit.input_edit(byte_range);
         self.tree_manager
             .update(&self.text, tree_edit, &mut self.lang);
 
@@ -156,3 +166,58 @@ impl Source {
         }
     }
 }
+
+impl TextEdit<'_> {
+    /// The change to the tree from the edit, given the byte range it replaces
+    fn input_edit(&self, byte_range: std::ops::Range<usize>) -> InputEdit {
+        InputEdit {
+            start_byte: byte_range.start,
+            old_end_byte: byte_range.end,
+            new_end_byte: byte_range.start + self.text.len(),
+            start_position: self.range.start.into(),
+            old_end_position: self.range.end.into(),
+            new_end_position: self.new_end().into(),
+        }
+    }
+}
+
+/// The number of ERROR and missing nodes in the tree
+fn count_errors(node: tree_sitter::Node) -> usize {
+    if !node.has_error() {
+        return 0;
+    }
+
+    let own = usize::from(node.is_error() || node.is_missing());
+    let mut cursor = node.walk();
+    let children: Vec<_> = node.children(&mut cursor).collect();
+    own + children.into_iter().map(count_errors).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+    use crate::lang::Language;
+
+    #[test]
+    fn test_edit_adds_errors() {
+        let mut source = Source::new(
+            Rope::from_str("if x:\n    pass\n"),
+            Language::for_file("test.py"),
+        );
+        let insert = |line| {
+            TextEdit::new(
+                Cow::Borrowed("elif y:\n    pass\n"),
+                TextRange::new_cursor(TextPoint::new(line, 0)),
+            )
+        };
+
+        // an elif block only parses after an if
+        assert!(!source.edit_adds_errors(&insert(2)));
+        assert!(source.edit_adds_errors(&insert(0)));
+
+        // and the source isn't changed
+        assert_eq!(source.text().to_string(), "if x:\n    pass\n");
+    }
+}
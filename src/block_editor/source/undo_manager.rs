@@ -119,6 +119,26 @@ impl Source {
         selection
     }
 
+    // Apply all the undos on the stack until it hits a stop, without adding them to the redo stack
+    // (so the undone edits are forgotten instead of being redoable).
+    pub(super) fn apply_undo_without_redo(&mut self) -> Option<TextRange> {
+        if let Some(UndoItem::Stop) = self.undo_stack.last() {
+            self.undo_stack.pop();
+        }
+
+        let mut selection = None;
+        while let Some(item) = self.undo_stack.pop() {
+            match item {
+                UndoItem::Stop => break,
+                UndoItem::Edit(edit) => {
+                    self.apply(&edit);
+                    selection = Some(TextRange::new_cursor(edit.new_end()));
+                }
+            }
+        }
+        selection
+    }
+
     // Apply all the redos on the stack until it hits a stop. Adding their inverses to the undo stack.
     pub(super) fn apply_redo(&mut self) -> Option<TextRange> {
         // remove a stop from the top of the stack if it is there
@@ -1,4 +1,4 @@
-use egui::{Align2, Color32, Painter, Vec2};
+use egui::{Align2, Painter, Vec2};
 use ropey::Rope;
 use tree_sitter::Node;
 
@@ -9,14 +9,20 @@ use std::{
     ops::{Range, RangeInclusive},
 };
 
-use super::{blocks::Padding, source::Source, MonospaceFont};
+use super::{
+    blocks::Padding,
+    rope_ext::{display_widths, is_invisible_in_cluster},
+    soft_wrap::WrappedRow,
+    source::Source,
+    MonospaceFont,
+};
 use crate::{
     lang::{
         config::LanguageConfig,
         highlighter::{Highlight, HighlightEvent},
         Language,
     },
-    theme,
+    theme::{self, SyntaxColor},
 };
 
 // TODO: probably should have text drawers share highlight configurations
@@ -48,12 +54,29 @@ impl TextDrawer {
 
             let total_offset = Vec2 {
                 x: offset.x,
-                y: ((num as f32) * font.size.y) + padding.cumulative(num) + offset.y,
+                y: ((padding.first_row(num) as f32) * font.size.y)
+                    + padding.cumulative(num)
+                    + offset.y,
             };
-            layout.draw(total_offset, font, painter);
+            let wrapped_rows = padding.soft_wrap().wrapped_rows(num);
+            layout.draw(total_offset, wrapped_rows, font, painter);
+        }
+    }
+
+    /// Draw a single line in one row (ignoring wrapping), with its top left at the offset
+    pub fn draw_line(&self, line: usize, offset: Vec2, font: &MonospaceFont, painter: &Painter) {
+        if let Some(layout) = self.cache.get(line) {
+            layout.draw(offset, &[], font, painter);
         }
     }
 
+    /// The text of the line split into runs of the same color (empty if it hasn't been highlighted)
+    pub fn line_chunks(&self, line: usize) -> &[(String, SyntaxColor)] {
+        self.cache
+            .get(line)
+            .map_or(&[], |colored| colored.chunks.as_slice())
+    }
+
     pub fn highlight_source(&mut self, source: &mut Source) {
         let mut highlighter = source.lang.highlighter.borrow_mut();
         let highlight_config = source.lang.highlight_config.borrow_mut();
@@ -176,7 +199,7 @@ impl TextDrawer {
 }
 
 struct ColorRange {
-    color: Color32,
+    color: SyntaxColor,
     range: Range<usize>,
 }
 
@@ -186,7 +209,7 @@ struct ColoredTextBuilder<'a> {
 }
 
 struct ColoredText {
-    chunks: Vec<(String, Color32)>,
+    chunks: Vec<(String, SyntaxColor)>,
 }
 
 impl<'a> ColoredTextBuilder<'a> {
@@ -197,7 +220,7 @@ impl<'a> ColoredTextBuilder<'a> {
         }
     }
 
-    fn add_color(&mut self, color: Color32, range: Range<usize>) {
+    fn add_color(&mut self, color: SyntaxColor, range: Range<usize>) {
         self.color_ranges.push(ColorRange { color, range });
     }
 
@@ -210,7 +233,7 @@ impl<'a> ColoredTextBuilder<'a> {
             // add anything this might have skipped
             if handled_up_to < color_range.range.start {
                 let text = &self.text[handled_up_to..color_range.range.start];
-                chunks.push((text.to_string(), theme::syntax::DEFAULT));
+                chunks.push((text.to_string(), SyntaxColor::Default));
             }
 
             // add this
@@ -222,7 +245,7 @@ impl<'a> ColoredTextBuilder<'a> {
         // add the rest
         if handled_up_to != self.text.len() {
             let text = &self.text[handled_up_to..];
-            chunks.push((text.to_string(), theme::syntax::DEFAULT));
+            chunks.push((text.to_string(), SyntaxColor::Default));
         }
 
         ColoredText { chunks }
@@ -230,22 +253,45 @@ impl<'a> ColoredTextBuilder<'a> {
 }
 
 impl ColoredText {
-    fn draw(&self, mut offset: Vec2, font: &MonospaceFont, painter: &Painter) {
+    fn draw(
+        &self,
+        line_offset: Vec2,
+        wrapped_rows: &[WrappedRow],
+        font: &MonospaceFont,
+        painter: &Painter,
+    ) {
         // draw by character until egui fixes monospace layout by switching to cosmic-text:
         // https://github.com/emilk/egui/issues/3378
-        for (text, color) in &self.chunks {
-            for char in text.chars() {
-                if !char.is_whitespace() {
-                    painter.text(
-                        offset.to_pos2(),
-                        Align2::LEFT_TOP,
-                        char,
-                        font.id.clone(),
-                        *color,
-                    );
-                }
-                offset.x += font.size.x;
+        let syntax = &theme::current().syntax;
+        let chars = self.chunks.iter().flat_map(|(text, color)| {
+            let color = syntax.get(*color);
+            text.chars().map(move |char| (char, color))
+        });
+        let widths = display_widths(
+            self.chunks.iter().flat_map(|(text, _)| text.chars()),
+            font.tab_width,
+        );
+
+        let mut offset = line_offset;
+        let mut rows = wrapped_rows.iter().peekable();
+
+        // combining marks have no advance and are drawn back over the previous glyph
+        for (col, ((char, color), (_, width))) in chars.zip(widths).enumerate() {
+            // move down to the next row when the line wraps
+            if let Some(row) = rows.next_if(|row| row.col == col) {
+                offset.x = line_offset.x + row.indent as f32 * font.size.x;
+                offset.y += font.size.y;
+            }
+            if !char.is_whitespace() && !is_invisible_in_cluster(char, width) {
+                painter.text(
+                    offset.to_pos2(),
+                    Align2::LEFT_TOP,
+                    char,
+                    font.id.clone(),
+                    color,
+                );
             }
+            offset.x += width as f32 * font.size.x;
         }
     }
 }
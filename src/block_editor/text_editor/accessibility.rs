@@ -0,0 +1,195 @@
+use egui::{accesskit, output::OutputEvent, Response, Ui, Vec2, WidgetInfo, WidgetType};
+use ropey::Rope;
+
+use super::{breadcrumbs::block_label, coord_conversions::text_coord_to_pt, TextEditor};
+use crate::block_editor::{
+    blocks::{Block, BlockType},
+    rope_ext::RopeSliceExt,
+    source::Source,
+    text_range::TextPoint,
+    MonospaceFont,
+};
+
+impl TextEditor {
+    /// Describe the editor to screen readers.
+    /// With AccessKit (on native) the whole text and selection are exposed,
+    /// and with the web screen reader the cursor's line and surroundings are spoken when it moves to a new line.
+    pub(super) fn update_accessibility(
+        &mut self,
+        response: &Response,
+        offset: Vec2,
+        source: &Source,
+        font: &MonospaceFont,
+        ui: &Ui,
+    ) {
+        let ctx = ui.ctx();
+        let cursor = self.selections.selection().end;
+
+        ctx.accesskit_node_builder(response.id, |node| {
+            let selection = self.selections.selection();
+            node.set_role(accesskit::Role::MultilineTextInput);
+            node.set_label("Code editor");
+            node.set_description(self.cursor_context(source));
+            node.set_text_selection(accesskit::TextSelection {
+                anchor: text_position(response, selection.start),
+                focus: text_position(response, selection.end),
+            });
+        });
+
+        // each line is its own text run, so positions in the selection are (line, col)
+        ctx.with_accessibility_parent(response.id, || {
+            for (line_num, line) in source.text().lines().enumerate() {
+                ctx.accesskit_node_builder(response.id.with(line_num), |node| {
+                    let top_left = text_coord_to_pt(
+                        TextPoint::new(line_num, 0),
+                        self.blocks.padding(),
+                        source.text(),
+                        font,
+                    ) + offset;
+                    let height =
+                        self.blocks.padding().soft_wrap().row_count(line_num) as f32 * font.size.y;
+                    node.set_role(accesskit::Role::TextRun);
+                    node.set_bounds(accesskit::Rect {
+                        x0: top_left.x.into(),
+                        y0: top_left.y.into(),
+                        x1: response.rect.max.x.into(),
+                        y1: (top_left.y + height).into(),
+                    });
+                    node.set_text_direction(accesskit::TextDirection::LeftToRight);
+
+                    let value = line.to_string();
+                    let character_lengths: Vec<u8> =
+                        value.chars().map(|c| c.len_utf8() as u8).collect();
+                    node.set_word_lengths(word_lengths(&value));
+                    node.set_character_lengths(character_lengths);
+                    node.set_value(value);
+                });
+            }
+        });
+
+        // announce where the cursor is when it changes lines
+        let screen_reader = ctx.options(|o| o.screen_reader);
+        if screen_reader && response.has_focus() && self.announced_line != Some(cursor.line) {
+            self.announced_line = Some(cursor.line);
+
+            let line = source.text().line(cursor.line);
+            let text = line.slice(line.whitespace_at_start()..line.len_chars_no_linebreak());
+            let announcement = format!(
+                "Line {}: {}. {}",
+                cursor.line + 1,
+                text,
+                self.cursor_context(source)
+            );
+            ctx.output_mut(|o| {
+                o.events
+                    .push(OutputEvent::TextSelectionChanged(WidgetInfo::labeled(
+                        WidgetType::TextEdit,
+                        true,
+                        announcement,
+                    )))
+            });
+        } else if !response.has_focus() {
+            self.announced_line = None;
+        }
+    }
+
+    /// The blocks around the cursor (innermost first) and the diagnostics on its line,
+    /// like "inside for loop, inside function main. error: x is not defined"
+    fn cursor_context(&self, source: &Source) -> String {
+        let text = source.text();
+        let cursor = self.selections.selection().end;
+        let indent = text.line(cursor.line).whitespace_at_start();
+        let point = TextPoint::new(cursor.line, cursor.col.max(indent));
+
+        let blocks: Vec<String> = self
+            .blocks
+            .path_to(point, text)
+            .into_iter()
+            .rev()
+            .filter_map(|(level, idx)| describe_block(&level[idx], text))
+            .map(|description| format!("inside {description}"))
+            .collect();
+
+        let diagnostics = self
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                diagnostic.range.start.line <= cursor.line
+                    && cursor.line <= diagnostic.range.end.line
+            })
+            .map(|diagnostic| format!("{}: {}", diagnostic.severity.name(), diagnostic.message));
+
+        let mut sentences: Vec<String> = vec![];
+        if !blocks.is_empty() {
+            sentences.push(blocks.join(", "));
+        }
+        sentences.extend(diagnostics);
+        sentences.join(". ")
+    }
+}
+
+fn text_position(response: &Response, point: TextPoint) -> accesskit::TextPosition {
+    accesskit::TextPosition {
+        node: response.id.with(point.line).value().into(),
+        character_index: point.col,
+    }
+}
+
+/// The number of characters in each word of the line, with the whitespace after a word part of it
+fn word_lengths(line: &str) -> Vec<u8> {
+    let mut lengths = vec![];
+    let mut length: u8 = 0;
+    let mut prev_word_char = false;
+    for c in line.chars() {
+        let word_char = c.is_alphanumeric() || c == '_';
+        if word_char && !prev_word_char && length > 0 {
+            lengths.push(length);
+            length = 0;
+        }
+        length = length.saturating_add(1);
+        prev_word_char = word_char;
+    }
+    if length > 0 {
+        lengths.push(length);
+    }
+    lengths
+}
+
+/// How a block is read out, or None for blocks that aren't worth mentioning (like single statements)
+fn describe_block(block: &Block, source: &Rope) -> Option<String> {
+    use BlockType::*;
+
+    // definitions are described by their name, which is the last word before the parameters
+    let name = || {
+        let label = block_label(block, source);
+        label
+            .split_whitespace()
+            .last()
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    match block.syntax_type {
+        Object => Some(format!("class {}", name())),
+        FunctionDef => Some(format!("function {}", name())),
+        While => Some("while loop".to_string()),
+        If => Some("if statement".to_string()),
+        For => Some("for loop".to_string()),
+        Try => Some("try block".to_string()),
+        Always => Some("always block".to_string()),
+        Switch => Some("switch".to_string()),
+        Generic | Comment | Error | Divider => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_lengths() {
+        assert_eq!(word_lengths("for i in range(10):"), vec![4, 2, 3, 6, 4]);
+        assert_eq!(word_lengths("    x = 1"), vec![4, 4, 1]);
+        assert!(word_lengths("").is_empty());
+    }
+}
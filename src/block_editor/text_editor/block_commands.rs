@@ -0,0 +1,790 @@
+use std::{borrow::Cow, collections::HashMap, ops::Range};
+
+use egui::{Key, Modifiers, Pos2};
+use ropey::{Rope, RopeSlice};
+
+use super::{
+    block_dragging::{normalize_indent, set_indent},
+    coord_conversions::pt_to_unbounded_text_coord,
+    surround_popup::SurroundChoice,
+    TextEdit, TextEditor,
+};
+use crate::{
+    block_editor::{
+        blocks::Block,
+        rope_ext::{display_widths, RopeExt, RopeSliceExt},
+        source::{IndentStyle, Source, UndoStopCondition},
+        text_range::{TextPoint, TextRange},
+        BlockType, MonospaceFont, GUTTER_WIDTH, OUTER_PAD,
+    },
+    lang::config::{NewScopeChar, Snippet},
+    vscode,
+};
+
+/// Keyboard operations on the block around the cursor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockCommand {
+    Select,
+    MoveUp,
+    MoveDown,
+    Indent,
+    Outdent,
+    Duplicate,
+    Delete,
+    Unwrap,
+}
+
+impl BlockCommand {
+    pub const fn as_str(&self) -> &'static str {
+        use BlockCommand::*;
+        match self {
+            Select => "Select",
+            MoveUp => "MoveUp",
+            MoveDown => "MoveDown",
+            Indent => "Indent",
+            Outdent => "Outdent",
+            Duplicate => "Duplicate",
+            Delete => "Delete",
+            Unwrap => "Unwrap",
+        }
+    }
+}
+
+/// The lines a block covers, detached from the block tree so the source can be edited
+#[derive(Clone, Copy)]
+struct BlockLines {
+    /// first line of the block
+    start: usize,
+
+    /// line after the last line of the block
+    end: usize,
+
+    syntax_type: BlockType,
+}
+
+impl BlockLines {
+    fn new(block: &Block) -> Self {
+        Self {
+            start: block.line,
+            end: block.line + block.height,
+            syntax_type: block.syntax_type,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl TextEditor {
+    /// Returns if the keypress was handled as a block command
+    pub fn handle_block_command_keypress(
+        &mut self,
+        modifiers: &Modifiers,
+        key: Key,
+        source: &mut Source,
+    ) -> bool {
+        let command = match key {
+            Key::ArrowUp if modifiers.alt && !modifiers.shift && !modifiers.ctrl => {
+                BlockCommand::MoveUp
+            }
+            Key::ArrowDown if modifiers.alt && !modifiers.shift && !modifiers.ctrl => {
+                BlockCommand::MoveDown
+            }
+            Key::CloseBracket if modifiers.command => BlockCommand::Indent,
+            Key::OpenBracket if modifiers.command => BlockCommand::Outdent,
+            Key::B if modifiers.command && modifiers.shift => BlockCommand::Select,
+            Key::D if modifiers.command && modifiers.shift => BlockCommand::Duplicate,
+            Key::K if modifiers.command && modifiers.shift => BlockCommand::Delete,
+            Key::U if modifiers.command && modifiers.shift => BlockCommand::Unwrap,
+            Key::T if modifiers.command && modifiers.alt => {
+                self.surround_popup.open();
+                return true;
+            }
+            _ => return false,
+        };
+        self.run_block_command(command, source);
+        self.completion_popup.clear();
+        true
+    }
+
+    /// Apply a block command to the block at the cursor. Each command is a single undoable edit.
+    pub fn run_block_command(&mut self, command: BlockCommand, source: &mut Source) {
+        vscode::log_event(
+            "editor-block-command",
+            HashMap::from([
+                ("command", command.as_str()),
+                ("lang", source.lang.config.name),
+            ]),
+        );
+
+        match command {
+            BlockCommand::Select => self.select_block(source),
+            BlockCommand::MoveUp => self.move_block_up(source),
+            BlockCommand::MoveDown => self.move_block_down(source),
+            BlockCommand::Indent => self.indent_block(source),
+            BlockCommand::Outdent => self.outdent_block(source),
+            BlockCommand::Duplicate => self.duplicate_block(source),
+            BlockCommand::Delete => self.delete_block(source),
+            BlockCommand::Unwrap => self.unwrap_block(source),
+        }
+    }
+
+    /// Do what was picked in the "Surround with" popup
+    pub fn run_surround_choice(&mut self, choice: SurroundChoice, source: &mut Source) {
+        match choice {
+            SurroundChoice::Surround(snippet) => self.surround_with(snippet, source),
+            SurroundChoice::Unwrap => self.run_block_command(BlockCommand::Unwrap, source),
+        }
+        self.completion_popup.clear();
+        self.focus_requested = true;
+    }
+
+    /// Surround the selected lines (or the block at the cursor if nothing is selected)
+    /// with a palette snippet, which takes the place of the snippet's body line
+    pub fn surround_with(&mut self, snippet: &Snippet, source: &mut Source) {
+        let Some(body_line) = snippet.body_line else {
+            return;
+        };
+        vscode::log_event(
+            "editor-block-surround",
+            HashMap::from([("type", snippet.id), ("lang", source.lang.config.name)]),
+        );
+
+        let selection = self.selections.selection().ordered();
+        let (start, end) = if selection.is_cursor() {
+            let Some((block, _)) = self.cursor_block(source) else {
+                return;
+            };
+            (block.start, block.end)
+        } else {
+            // a selection ending at the start of a line doesn't include that line
+            let end = if selection.end.col == 0 && selection.end.line > selection.start.line {
+                selection.end.line
+            } else {
+                selection.end.line + 1
+            };
+            (selection.start.line, end)
+        };
+
+        let text = source.text();
+        let style = source.indent_style();
+        let indent = text.line(start).indent_width(style.width);
+        let body = lines_text(text, start, end);
+        let new_text = surround_text(snippet.source, body_line, &body, indent, style);
+
+        let new_cursor = TextPoint::new(start, style.indent_str(indent).chars().count());
+        self.replace_lines(start, end, new_text, new_cursor, source);
+    }
+
+    /// Remove the header (and closing line or other branches) of the if, loop, or try at the cursor,
+    /// keeping its body at the block's indent
+    fn unwrap_block(&mut self, source: &mut Source) {
+        let text = source.text();
+        let cursor = self.selections.selection().end;
+        let indent = text.line(cursor.line).whitespace_at_start();
+        let point = TextPoint::new(cursor.line, cursor.col.max(indent));
+        let Some(block) = self
+            .blocks
+            .path_to(point, text)
+            .into_iter()
+            .rev()
+            .map(|(level, idx)| &level[idx])
+            .find(|block| {
+                matches!(
+                    block.syntax_type,
+                    BlockType::If | BlockType::For | BlockType::While | BlockType::Try
+                )
+            })
+        else {
+            return;
+        };
+        let block = BlockLines::new(block);
+
+        let style = source.indent_style();
+        let Some(body) = body_lines(text, block, style.width) else {
+            return;
+        };
+        let block_indent = text.line(block.start).indent_width(style.width);
+        let new_text = shift_indent(&lines_text(text, body.start, body.end), block_indent, style);
+
+        let new_cursor =
+            TextPoint::new(block.start, style.indent_str(block_indent).chars().count());
+        self.replace_lines(block.start, block.end, new_text, new_cursor, source);
+    }
+
+    fn select_block(&mut self, source: &mut Source) {
+        if let Some((block, _)) = self.cursor_block(source) {
+            self.select_block_lines(block, source);
+        }
+    }
+
+    /// Select the block whose border was clicked on. Returns if there was one.
+    pub(super) fn select_block_with_border_at(
+        &mut self,
+        pos: Pos2,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) -> bool {
+        let line = pt_to_unbounded_text_coord(pos, self.blocks.padding(), source.text(), font).line;
+        let Some(block) =
+            self.blocks
+                .block_with_border_at(line, pos.x - (OUTER_PAD + GUTTER_WIDTH), font)
+        else {
+            return false;
+        };
+        let block = BlockLines::new(block);
+        self.select_block_lines(block, source);
+        source.external_cursor_move();
+        true
+    }
+
+    fn select_block_lines(&mut self, block: BlockLines, source: &Source) {
+        let text = source.text();
+        let last_line = block.end - 1;
+        let selection = TextRange::new(
+            TextPoint::new(block.start, text.line(block.start).whitespace_at_start()),
+            TextPoint::new(last_line, text.line(last_line).len_chars_no_linebreak()),
+        );
+        self.selections.set_selection(selection, source);
+    }
+
+    fn move_block_up(&mut self, source: &mut Source) {
+        let Some((block, path)) = self.cursor_block(source) else {
+            return;
+        };
+        let Some(prev) = path.prev_sibling else {
+            return;
+        };
+        if prev.end > block.start {
+            return;
+        }
+
+        // swap the block with the one above, keeping whatever is between them in place
+        let text = source.text();
+        let new_text = format!(
+            "{}{}{}",
+            lines_text(text, block.start, block.end),
+            lines_text(text, prev.end, block.start),
+            lines_text(text, prev.start, prev.end),
+        );
+        let cursor = self.selections.selection().end;
+        let new_cursor = TextPoint::new(cursor.line - (block.start - prev.start), cursor.col);
+        self.replace_lines(prev.start, block.end, new_text, new_cursor, source);
+    }
+
+    fn move_block_down(&mut self, source: &mut Source) {
+        let Some((block, path)) = self.cursor_block(source) else {
+            return;
+        };
+        let Some(next) = path.next_sibling else {
+            return;
+        };
+        if block.end > next.start {
+            return;
+        }
+
+        let text = source.text();
+        let new_text = format!(
+            "{}{}{}",
+            lines_text(text, next.start, next.end),
+            lines_text(text, block.end, next.start),
+            lines_text(text, block.start, block.end),
+        );
+        let cursor = self.selections.selection().end;
+        let new_cursor = TextPoint::new(cursor.line + (next.end - block.end), cursor.col);
+        self.replace_lines(block.start, next.end, new_text, new_cursor, source);
+    }
+
+    /// Move the block into the end of the body of the block above it
+    fn indent_block(&mut self, source: &mut Source) {
+        let Some((block, path)) = self.cursor_block(source) else {
+            return;
+        };
+        let (Some(prev), Some(body_indent)) = (path.prev_sibling, path.prev_sibling_body_indent)
+        else {
+            return;
+        };
+        if !has_body(prev.syntax_type) || prev.end > block.start {
+            return;
+        }
+
+        let text = source.text();
+        let style = source.indent_style();
+        let scope_char = source.lang.config.new_scope_char;
+        let old_indent = text.line(block.start).whitespace_at_start();
+        let indented = set_indent(
+            &normalize_indent(lines_text(text, block.start, block.end)),
+            body_indent,
+            style,
+        );
+
+        let cursor = self.selections.selection().end;
+        let new_indent = style.indent_str(body_indent).chars().count();
+        let cursor_col = (cursor.col + new_indent).saturating_sub(old_indent);
+
+        if scope_char == NewScopeChar::Colon {
+            // the body continues until the indent decreases, so just indent the block
+            let new_cursor = TextPoint::new(cursor.line, cursor_col);
+            self.replace_lines(block.start, block.end, indented, new_cursor, source);
+        } else {
+            // put the block before the line closing the scope above
+            let closing_line = prev.end - 1;
+            if closing_line == prev.start || !closes_scope(text.line(closing_line), scope_char) {
+                return;
+            }
+
+            let new_text = format!(
+                "{}{}{}",
+                indented,
+                lines_text(text, closing_line, prev.end),
+                lines_text(text, prev.end, block.start),
+            );
+            let new_cursor = TextPoint::new(closing_line + (cursor.line - block.start), cursor_col);
+            self.replace_lines(closing_line, block.end, new_text, new_cursor, source);
+        }
+    }
+
+    /// Move the block out of its parent, placing it after the parent
+    fn outdent_block(&mut self, source: &mut Source) {
+        let Some((block, path)) = self.cursor_block(source) else {
+            return;
+        };
+        let Some(parent) = path.parent else {
+            return;
+        };
+
+        // the block must be fully inside the parent's body
+        let scope_char = source.lang.config.new_scope_char;
+        let closes_after = scope_char != NewScopeChar::Colon;
+        if block.start <= parent.start || (closes_after && block.end >= parent.end) {
+            return;
+        }
+
+        let text = source.text();
+        let style = source.indent_style();
+        let old_indent = text.line(block.start).whitespace_at_start();
+        let new_indent = text.line(parent.start).indent_width(style.width);
+        let outdented = set_indent(
+            &normalize_indent(lines_text(text, block.start, block.end)),
+            new_indent,
+            style,
+        );
+        let new_indent = style.indent_str(new_indent).chars().count();
+        let new_text = format!("{}{}", lines_text(text, block.end, parent.end), outdented);
+
+        let cursor = self.selections.selection().end;
+        let new_cursor = TextPoint::new(
+            cursor.line + (parent.end - block.end),
+            (cursor.col + new_indent).saturating_sub(old_indent),
+        );
+        self.replace_lines(block.start, parent.end, new_text, new_cursor, source);
+    }
+
+    fn duplicate_block(&mut self, source: &mut Source) {
+        let Some((block, _)) = self.cursor_block(source) else {
+            return;
+        };
+
+        let block_text = lines_text(source.text(), block.start, block.end);
+        let new_text = format!("{block_text}{block_text}");
+        let cursor = self.selections.selection().end;
+        let new_cursor = TextPoint::new(cursor.line + block.len(), cursor.col);
+        self.replace_lines(block.start, block.end, new_text, new_cursor, source);
+    }
+
+    fn delete_block(&mut self, source: &mut Source) {
+        let Some((block, _)) = self.cursor_block(source) else {
+            return;
+        };
+
+        let new_cursor = TextPoint::new(block.start, 0);
+        self.replace_lines(block.start, block.end, String::new(), new_cursor, source);
+    }
+
+    /* --------------------------------- helpers -------------------------------- */
+    /// Find the innermost block at the cursor and its surroundings
+    fn cursor_block(&self, source: &Source) -> Option<(BlockLines, BlockSurroundings)> {
+        let text = source.text();
+
+        // treat the cursor being in the indent as being on the line's block
+        let cursor = self.selections.selection().end;
+        let indent = text.line(cursor.line).whitespace_at_start();
+        let point = TextPoint::new(cursor.line, cursor.col.max(indent));
+
+        let path = self.blocks.path_to(point, text);
+        let (level, idx) = *path.last()?;
+        let block = BlockLines::new(&level[idx]);
+
+        let prev_sibling = sibling(level[..idx].iter().rev());
+        let width = source.indent_style().width;
+        let prev_sibling_body_indent =
+            prev_sibling.map(|prev| match last_body_block(&prev.children) {
+                Some(child) => text.line(child.line).indent_width(width),
+                None => text.line(prev.line).indent_width(width) + width,
+            });
+
+        let surroundings = BlockSurroundings {
+            parent: path.len().checked_sub(2).map(|parent_idx| {
+                let (level, idx) = path[parent_idx];
+                BlockLines::new(&level[idx])
+            }),
+            prev_sibling: prev_sibling.map(BlockLines::new),
+            prev_sibling_body_indent,
+            next_sibling: sibling(level[idx + 1..].iter()).map(BlockLines::new),
+        };
+
+        Some((block, surroundings))
+    }
+
+    /// Replace the lines in [start, end) with the new text as a single undoable edit
+    fn replace_lines(
+        &mut self,
+        start: usize,
+        end: usize,
+        mut new_text: String,
+        new_cursor: TextPoint,
+        source: &mut Source,
+    ) {
+        let text = source.text();
+        let last_line = text.len_lines() - 1;
+
+        // if the lines run to the end of a file without a trailing linebreak, don't add one
+        let range_end = if end > last_line {
+            if new_text.ends_with('\n') {
+                new_text.pop();
+                if new_text.ends_with('\r') {
+                    new_text.pop();
+                }
+            }
+            TextPoint::new(last_line, text.line(last_line).len_chars())
+        } else {
+            TextPoint::new(end, 0)
+        };
+
+        let edit = TextEdit::new(
+            Cow::Owned(new_text),
+            TextRange::new(TextPoint::new(start, 0), range_end),
+        );
+        source.apply_edit(&edit, UndoStopCondition::Always, true, &mut self.selections);
+
+        let line = new_cursor.line.min(source.text().len_lines() - 1);
+        let col = source.text().clamp_col(line, new_cursor.col);
+        self.selections
+            .set_selection(TextRange::new_cursor(TextPoint::new(line, col)), source);
+    }
+}
+
+struct BlockSurroundings {
+    /// closest enclosing block that is not a divider
+    parent: Option<BlockLines>,
+
+    prev_sibling: Option<BlockLines>,
+
+    /// the indent (in columns) that a block moved into the previous sibling's body should have
+    prev_sibling_body_indent: Option<usize>,
+
+    next_sibling: Option<BlockLines>,
+}
+
+/// The first block in the iterator that a block can be moved past.
+/// Skips whitespace dividers but stops at dividers that are part of the syntax (like an else)
+fn sibling<'a>(mut blocks: impl Iterator<Item = &'a Block>) -> Option<&'a Block> {
+    blocks
+        .find(|b| b.syntax_type != BlockType::Divider || b.height > 0)
+        .filter(|b| b.syntax_type != BlockType::Divider)
+}
+
+/// The last block inside a body, looking inside dividers (so the else of an if is used)
+fn last_body_block(children: &[Block]) -> Option<&Block> {
+    for child in children.iter().rev() {
+        if child.syntax_type == BlockType::Divider {
+            if let Some(inner) = last_body_block(&child.children) {
+                return Some(inner);
+            }
+        } else {
+            return Some(child);
+        }
+    }
+    None
+}
+
+fn has_body(block_type: BlockType) -> bool {
+    use BlockType::*;
+    matches!(
+        block_type,
+        Object | FunctionDef | While | If | For | Try | Always | Switch
+    )
+}
+
+/// If the line closes a scope (a closing brace or an end keyword)
+fn closes_scope(line: RopeSlice, scope_char: NewScopeChar) -> bool {
+    let trimmed = line.to_string();
+    let trimmed = trimmed.trim_start();
+    match scope_char {
+        NewScopeChar::Colon => false,
+        NewScopeChar::Brace => trimmed.starts_with('}'),
+        NewScopeChar::Begin => trimmed.starts_with("end"),
+    }
+}
+
+/// The lines of the block's body: from the line after its header to the first line
+/// at the block's indent or less (like an else or a closing brace), if there are any
+fn body_lines(text: &Rope, block: BlockLines, tab_width: usize) -> Option<Range<usize>> {
+    let block_indent = text.line(block.start).indent_width(tab_width);
+    let body_start = block.start + 1;
+    let body_end = (body_start..block.end.min(text.len_lines()))
+        .find(|&line| {
+            let line = text.line(line);
+            !line.chars().all(char::is_whitespace) && line.indent_width(tab_width) <= block_indent
+        })
+        .unwrap_or(block.end);
+    (body_start < body_end).then_some(body_start..body_end)
+}
+
+/// The snippet with its body line replaced by the body, all indented to start at the indent
+fn surround_text(
+    snippet: &str,
+    body_line: usize,
+    body: &str,
+    indent: usize,
+    style: IndentStyle,
+) -> String {
+    // snippets are written with 4 space indents, so convert them to the file's style
+    const SNIPPET_INDENT: usize = 4;
+    let restyled: Vec<String> = snippet
+        .split_inclusive('\n')
+        .map(|line| {
+            let content = line.trim_start_matches(' ');
+            let levels = (line.len() - content.len()) / SNIPPET_INDENT;
+            format!("{}{}", style.indent_str(levels * style.width), content)
+        })
+        .collect();
+    let body_levels = snippet.lines().nth(body_line).map_or(1, |line| {
+        (line.len() - line.trim_start_matches(' ').len()) / SNIPPET_INDENT
+    });
+
+    let header = restyled[..body_line.min(restyled.len())].concat();
+    let footer = restyled
+        .get(body_line + 1..)
+        .map(|lines| lines.concat())
+        .unwrap_or_default();
+    format!(
+        "{}{}{}",
+        set_indent(&header, indent, style),
+        shift_indent(body, indent + body_levels * style.width, style),
+        set_indent(&footer, indent, style),
+    )
+}
+
+/// Move the lines so the least indented starts at the indent, keeping the lines' relative indents.
+/// Blank lines are left empty.
+fn shift_indent(text: &str, indent: usize, style: IndentStyle) -> String {
+    let indent_of = |line: &str| -> usize {
+        let content = line.trim_start_matches([' ', '\t']);
+        display_widths(line[..line.len() - content.len()].chars(), style.width)
+            .map(|(_, width)| width)
+            .sum()
+    };
+    let is_blank = |line: &str| line.trim().is_empty();
+
+    let min_indent = text
+        .split_inclusive('\n')
+        .filter(|line| !is_blank(line))
+        .map(indent_of)
+        .min()
+        .unwrap_or(0);
+    text.split_inclusive('\n')
+        .map(|line| {
+            if is_blank(line) {
+                line.trim_start_matches([' ', '\t']).to_string()
+            } else {
+                let content = line.trim_start_matches([' ', '\t']);
+                let new_indent = indent + indent_of(line) - min_indent;
+                format!("{}{}", style.indent_str(new_indent), content)
+            }
+        })
+        .collect()
+}
+
+/// The text of the lines in [start, end), always ending with a linebreak
+fn lines_text(source: &Rope, start: usize, end: usize) -> String {
+    let end = end.min(source.len_lines());
+    let mut text = source
+        .slice(source.line_to_char(start)..source.line_to_char(end))
+        .to_string();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push_str(source.detect_linebreak());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_editor::blocks::BlockTrees, lang::Language};
+
+    const SPACES: IndentStyle = IndentStyle::DEFAULT;
+    const TABS: IndentStyle = IndentStyle {
+        use_tabs: true,
+        width: 4,
+    };
+
+    #[test]
+    fn test_surround_text() {
+        // python: the body takes the place of the `pass`
+        let surrounded = surround_text(
+            "if True:\n    pass\n",
+            1,
+            "    x = 1\n\n    y = 2\n",
+            4,
+            SPACES,
+        );
+        assert_eq!(surrounded, "    if True:\n        x = 1\n\n        y = 2\n");
+
+        // braces: the closing line is kept after the body, and indents follow the file's style
+        let surrounded = surround_text("while (true) {\n    \n}\n", 1, "x++;\n", 0, TABS);
+        assert_eq!(surrounded, "while (true) {\n\tx++;\n}\n");
+    }
+
+    #[test]
+    fn test_shift_indent() {
+        // relative indents are kept and blank lines are left empty
+        let shifted = shift_indent("        a\n            b\n    \n        c\n", 0, SPACES);
+        assert_eq!(shifted, "a\n    b\n\nc\n");
+    }
+
+    #[test]
+    fn test_body_lines() {
+        let text = Rope::from_str("if x {\n    y();\n    z();\n} else {\n    w();\n}\n");
+        let block = BlockLines {
+            start: 0,
+            end: 6,
+            syntax_type: BlockType::If,
+        };
+        assert_eq!(body_lines(&text, block, 4), Some(1..3));
+
+        let text = Rope::from_str("if x:\n    y()\n\n    z()\n");
+        let block = BlockLines {
+            start: 0,
+            end: 4,
+            syntax_type: BlockType::If,
+        };
+        assert_eq!(body_lines(&text, block, 4), Some(1..4));
+    }
+
+    /// Run the command with the cursor at the line and column, returning the new text and cursor.
+    /// Checks that undoing once puts the text back.
+    fn run(
+        file: &str,
+        text: &str,
+        cursor: (usize, usize),
+        command: BlockCommand,
+    ) -> (String, TextPoint) {
+        let mut source = Source::new(Rope::from_str(text), Language::for_file(file));
+        let mut text_editor = TextEditor::new();
+        text_editor.blocks = BlockTrees::for_ts_tree(
+            &mut source.get_tree_cursor(),
+            source.text(),
+            source.lang.config,
+            4,
+        );
+        let cursor = TextRange::new_cursor(TextPoint::new(cursor.0, cursor.1));
+        text_editor.selections.set_selection(cursor, &source);
+
+        text_editor.run_block_command(command, &mut source);
+        let result = (source.text().to_string(), text_editor.cursor());
+
+        source.undo(&mut text_editor.selections);
+        assert_eq!(
+            source.text().to_string(),
+            text,
+            "{command:?} is not one undo step"
+        );
+        result
+    }
+
+    #[test]
+    fn test_move_block() {
+        let text = "a = 1\n\nif a:\n    b = 2\nc = 3\n";
+
+        // the whole if moves past the line above, keeping the blank line between them
+        assert_eq!(
+            run("test.py", text, (2, 1), BlockCommand::MoveUp),
+            (
+                "if a:\n    b = 2\n\na = 1\nc = 3\n".into(),
+                TextPoint::new(0, 1)
+            )
+        );
+        assert_eq!(
+            run("test.py", text, (2, 1), BlockCommand::MoveDown),
+            (
+                "a = 1\n\nc = 3\nif a:\n    b = 2\n".into(),
+                TextPoint::new(3, 1)
+            )
+        );
+
+        // blocks don't leave their parent
+        let text = "if a:\n    b = 2\nc = 3\n";
+        assert_eq!(
+            run("test.py", text, (1, 4), BlockCommand::MoveUp),
+            (text.into(), TextPoint::new(1, 4))
+        );
+    }
+
+    #[test]
+    fn test_indent_block() {
+        // python: the block joins the body above
+        let text = "if a:\n    b = 2\nif c:\n    d = 4\n";
+        let indented = "if a:\n    b = 2\n    if c:\n        d = 4\n";
+        assert_eq!(
+            run("test.py", text, (2, 1), BlockCommand::Indent),
+            (indented.into(), TextPoint::new(2, 5))
+        );
+        assert_eq!(
+            run("test.py", indented, (2, 5), BlockCommand::Outdent),
+            (text.into(), TextPoint::new(2, 1))
+        );
+
+        // braces: the block goes before the closing brace
+        let text =
+            "fn f() {\n    if a {\n        b();\n    }\n    if c {\n        d();\n    }\n}\n";
+        let indented =
+            "fn f() {\n    if a {\n        b();\n        if c {\n            d();\n        }\n    }\n}\n";
+        assert_eq!(
+            run("test.rs", text, (4, 5), BlockCommand::Indent),
+            (indented.into(), TextPoint::new(3, 9))
+        );
+        assert_eq!(
+            run("test.rs", indented, (3, 9), BlockCommand::Outdent),
+            (text.into(), TextPoint::new(4, 5))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_and_delete_block() {
+        let text = "if a:\n    b = 2\nc = 3\n";
+        assert_eq!(
+            run("test.py", text, (0, 3), BlockCommand::Duplicate),
+            (
+                "if a:\n    b = 2\nif a:\n    b = 2\nc = 3\n".into(),
+                TextPoint::new(2, 3)
+            )
+        );
+        assert_eq!(
+            run("test.py", text, (0, 3), BlockCommand::Delete),
+            ("c = 3\n".into(), TextPoint::new(0, 0))
+        );
+
+        // the last block of a file without a trailing linebreak
+        assert_eq!(
+            run(
+                "test.py",
+                "if a:\n    b = 2\nc = 3",
+                (2, 1),
+                BlockCommand::Delete
+            ),
+            ("if a:\n    b = 2\n".into(), TextPoint::new(2, 0))
+        );
+    }
+}
@@ -4,76 +4,98 @@ use egui::{Painter, Pos2, Rect, Vec2};
 use ropey::Rope;
 
 use super::{
-    coord_conversions::{pt_to_text_coord, pt_to_unbounded_text_coord, text_coord_to_pt},
+    coord_conversions::{
+        display_col, pt_to_text_coord, pt_to_unbounded_text_coord, text_coord_to_pt,
+    },
     TextEdit, TextEditor,
 };
 use crate::{
     block_editor::{
         blocks::Block,
-        rope_ext::{RopeExt, RopeSliceExt},
-        source::{Source, UndoStopCondition},
+        rope_ext::{display_widths, RopeExt, RopeSliceExt},
+        source::{IndentStyle, Source, UndoStopCondition},
         text_range::{
             movement::{HDir, HUnit, TextMovement},
             TextPoint, TextRange,
         },
-        BlockType, DragSession, MonospaceFont, GUTTER_WIDTH, OUTER_PAD,
+        DragSession, MonospaceFont, GUTTER_WIDTH, OUTER_PAD,
     },
     lang::config::NewScopeChar,
     theme, vscode,
 };
 
 impl TextEditor {
+    /// Start dragging the block under the mouse, or if the selection covers a run of sibling
+    /// blocks that includes it, all of them. Copies the blocks if `copy` is set instead of moving them.
     pub fn start_block_drag(
         &mut self,
         mouse_pos: Pos2,
+        copy: bool,
         drag_block: &mut Option<DragSession>,
         source: &mut Source,
         font: &MonospaceFont,
     ) {
         let cursor_pos = pt_to_text_coord(mouse_pos, self.blocks.padding(), source.text(), font);
 
-        if let Some(block) = block_for_point(self.blocks.trees(), cursor_pos, source.text()) {
-            let mut text_range = block.text_range();
-
-            vscode::log_event(
-                "editor-block-drag",
-                HashMap::from([
-                    ("type", block.syntax_type.as_str()),
-                    ("lang", source.lang.config.name),
-                ]),
-            );
-
-            // select the whole first line (to get all the indent)
-            text_range.start.col = 0;
-
-            // normalize the text
-            let char_range = text_range.char_range_in(source.text());
-            let mut block_text = source.text().slice(char_range.clone()).to_string();
-            block_text = normalize_indent(block_text);
-            if !block_text.ends_with('\n') {
-                // add a newline to the end if it doesn't have one
-                block_text.push('\n');
-            }
-
-            // offset the dragging popup so it matches where the mouse picked up the block
-            let block_corner = text_coord_to_pt(
-                TextPoint {
-                    col: block.col,
-                    line: block.line,
-                },
-                self.blocks.padding(),
-                font,
-            );
-            let relative_pos =
-                Pos2::new(mouse_pos.x - block_corner.x, mouse_pos.y - block_corner.y);
-
-            // set dragging popup
-            *drag_block = Some(DragSession {
-                text: block_text.clone(),
-                offset: relative_pos,
-            });
+        let path = self.blocks.path_to(cursor_pos, source.text());
+        let Some(&(level, idx)) = path.last() else {
+            return;
+        };
+        let (first, last) = selected_siblings(&path, self.selections.selection(), source.text())
+            .unwrap_or((&level[idx], &level[idx]));
+        let mut text_range = TextRange::new(first.text_range().start, last.text_range().end);
+
+        vscode::log_event(
+            "editor-block-drag",
+            HashMap::from([
+                ("type", first.syntax_type.as_str()),
+                ("lang", source.lang.config.name),
+                ("copy", if copy { "true" } else { "false" }),
+                (
+                    "group",
+                    if std::ptr::eq(first, last) {
+                        "false"
+                    } else {
+                        "true"
+                    },
+                ),
+            ]),
+        );
+
+        // select the whole first line (to get all the indent)
+        text_range.start.col = 0;
+
+        // normalize the text
+        let char_range = text_range.char_range_in(source.text());
+        let mut block_text = source.text().slice(char_range.clone()).to_string();
+        block_text = normalize_indent(block_text);
+        if !block_text.ends_with('\n') {
+            // add a newline to the end if it doesn't have one
+            block_text.push('\n');
+        }
 
-            // remove dragged block from source
+        // offset the dragging popup so it matches where the mouse picked up the block
+        let block_corner = text_coord_to_pt(
+            TextPoint {
+                col: first.col,
+                line: first.line,
+            },
+            self.blocks.padding(),
+            source.text(),
+            font,
+        );
+        let relative_pos = Pos2::new(mouse_pos.x - block_corner.x, mouse_pos.y - block_corner.y);
+
+        // set dragging popup
+        *drag_block = Some(DragSession {
+            text: block_text,
+            offset: relative_pos,
+            from_palette: false,
+            copy,
+        });
+
+        // remove dragged blocks from source (unless copying them)
+        if !copy {
             source.apply_edit(
                 &TextEdit::delete(text_range),
                 UndoStopCondition::Always,
@@ -91,19 +113,7 @@ impl TextEditor {
     ) -> bool {
         // note: using take() also sets to None
         if let Some(drag_block) = drag_block.take() {
-            let mut indented_text = set_indent(&drag_block.text, drop_point.col);
-
-            // if at the end of the file, and the last line doesn't have a newline, add one
-            if drop_point.line == source.text().len_lines() {
-                indented_text.insert_str(0, source.text().detect_linebreak());
-            }
-
-            // apply edit
-            let insert_point = TextPoint::new(drop_point.line, 0);
-            let edit = TextEdit::new(
-                Cow::Owned(indented_text),
-                TextRange::new_cursor(insert_point),
-            );
+            let edit = drop_edit(&drag_block.text, drop_point, source);
             source.apply_edit(&edit, UndoStopCondition::Never, true, &mut self.selections);
 
             // move the cursor from the line after the block to the end of the text
@@ -118,9 +128,60 @@ impl TextEditor {
         }
     }
 
+    /// Find where the block would be dropped, and if it would parse there
+    pub fn find_drop_target(
+        &mut self,
+        mouse_pos: Pos2,
+        drag_block: &DragSession,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) -> DropTarget {
+        let point = self.find_drop_point(mouse_pos, source, font);
+
+        // parsing is only needed when the drop point moves
+        let checked = self
+            .drop_check
+            .as_ref()
+            .filter(|check| check.point == point && check.text == drag_block.text);
+        let valid = match checked {
+            Some(check) => check.valid,
+            None => {
+                let edit = drop_edit(&drag_block.text, point, source);
+                let valid = !source.edit_adds_errors(&edit);
+                self.drop_check = Some(DropCheck {
+                    point,
+                    text: drag_block.text.clone(),
+                    valid,
+                });
+                valid
+            }
+        };
+        DropTarget { point, valid }
+    }
+
+    /// Drop the block if it parses where it would be dropped (or the drop is forced).
+    /// Otherwise the block is put back where it was dragged from.
+    pub fn drop_block_if_valid(
+        &mut self,
+        drag_block: &mut Option<DragSession>,
+        target: DropTarget,
+        force: bool,
+        source: &mut Source,
+    ) {
+        if target.valid || force {
+            self.drop_block(drag_block, target.point, source);
+        } else if let Some(drag) = drag_block.take() {
+            vscode::log_event(
+                "editor-block-drop-invalid",
+                HashMap::from([("lang", source.lang.config.name)]),
+            );
+            self.restore_dragged_block(&drag, source);
+        }
+    }
+
     pub fn draw_dropping_line(
         &self,
-        drop_point: TextPoint,
+        target: DropTarget,
         viewport_width: f32,
         offset: Vec2,
         font: &MonospaceFont,
@@ -128,16 +189,24 @@ impl TextEditor {
     ) {
         const THICKNESS: f32 = 4.0;
 
+        let drop_point = target.point;
         let line_padding_above = self.blocks.padding().cumulative(drop_point.line);
-        let y = (drop_point.line as f32) * font.size.y + OUTER_PAD + line_padding_above;
+        let row = self.blocks.padding().first_row(drop_point.line);
+        let y = (row as f32) * font.size.y + OUTER_PAD + line_padding_above;
         let x = (drop_point.col as f32) * font.size.x + OUTER_PAD + GUTTER_WIDTH;
 
         let origin = Pos2::new(x, y);
         let size = Vec2::new(viewport_width - origin.x - 10.0, THICKNESS);
         let rect = Rect::from_min_size(origin + offset, size);
-        painter.rect_filled(rect, 0.0, theme::CURSOR);
+        let color = if target.valid {
+            theme::current().editor.cursor
+        } else {
+            theme::current().diagnostic.error
+        };
+        painter.rect_filled(rect, 0.0, color);
     }
 
+    /// Find where a block would be dropped. The column of the point is the display column of the indent.
     pub fn find_drop_point(
         &mut self,
         mouse_pos: Pos2,
@@ -146,89 +215,171 @@ impl TextEditor {
     ) -> TextPoint {
         // find the point adjusted so that it is based around between lines
         let adj_pos = Pos2::new(mouse_pos.x, mouse_pos.y + (font.size.y / 2.0));
-        let coord = pt_to_unbounded_text_coord(adj_pos, self.blocks.padding(), font);
+        let coord = pt_to_unbounded_text_coord(adj_pos, self.blocks.padding(), source.text(), font);
+        let col = display_col(coord, self.blocks.padding(), source.text(), font);
 
         // clamp line to end of source
         let line = coord.line.min(source.text().len_lines());
 
-        // limit to the first non-empty above line's level
-        // (or 1 more if it ends in a new scope character)
-        let mut relative_whitespace_line = line;
-        let allowed_indent = loop {
-            if relative_whitespace_line == 0 {
-                break 0;
-            }
+        TextPoint::new(line, drop_indent(line, col, source))
+    }
 
-            let line_above = source.text().line(relative_whitespace_line - 1);
-            let above_indent = line_above.whitespace_at_start();
+    /// Insert a block (like one from the palette) on the line after the cursor,
+    /// at the same indent as the cursor's line (or 1 more if it starts a new scope)
+    pub fn insert_block(&mut self, text: String, source: &mut Source) {
+        let style = source.indent_style();
+        let cursor_line = self.selections.selection().end.line;
+        let indent = source.text().line(cursor_line).indent_width(style.width);
+        let line = (cursor_line + 1).min(source.text().len_lines());
+
+        let mut drag_block = Some(DragSession {
+            text,
+            offset: Pos2::ZERO,
+            from_palette: false,
+            copy: false,
+        });
+        let drop_point = TextPoint::new(line, drop_indent(line, indent + style.width, source));
+        self.drop_block(&mut drag_block, drop_point, source);
+        self.focus_requested = true;
+    }
 
-            // if the line above is entirely whitespace, move to the line above
-            if above_indent == line_above.len_chars_no_linebreak() {
-                relative_whitespace_line -= 1;
-                continue;
-            }
+    /// Throw away a block moved out of the editor, so deleting it is its own undo step
+    pub fn discard_dragged_block(&mut self, drag: &DragSession, source: &mut Source) {
+        vscode::log_event(
+            "editor-block-delete",
+            HashMap::from([("lang", source.lang.config.name)]),
+        );
+        if drag.removed_from_source() {
+            source.end_undo_step();
+        }
+    }
 
-            // if the line above ends in a new scope character, allow one more indent
-            if line_above
-                .excluding_linebreak()
-                .ends_with(source.lang.config.new_scope_char.char())
-            {
-                break above_indent + 4;
-            }
+    /// Put a block moved out of the editor back where it was
+    /// (like when it was saved to the palette instead of dropped)
+    pub fn restore_dragged_block(&mut self, drag: &DragSession, source: &mut Source) {
+        if drag.removed_from_source() {
+            source.revert_undo_step(&mut self.selections);
+        }
+    }
+}
 
-            // otherwise, allow up to the same indent as the line above
-            break above_indent;
-        };
-        let indent = match source.lang.config.new_scope_char {
-            // when scope is indent based, allow reducing scope when dragging
-            NewScopeChar::Colon => ((coord.col / 4) * 4).min(allowed_indent),
-            // when scope is brace based, only allow the maximum indent
-            NewScopeChar::Brace => allowed_indent,
-            // when scope is begin/end based, only allow the maximum indent
-            NewScopeChar::Begin => allowed_indent,
-        };
+/// Where a dragged block would be dropped
+#[derive(Clone, Copy)]
+pub struct DropTarget {
+    pub point: TextPoint,
 
-        TextPoint::new(line, indent)
-    }
+    /// if the code would still parse (without new syntax errors) after the drop
+    pub valid: bool,
+}
+
+/// The last drop point that was parsed, so it isn't parsed again every frame
+pub struct DropCheck {
+    point: TextPoint,
+    text: String,
+    valid: bool,
 }
 
 /* ---------------------------- Helper Functions ---------------------------- */
-fn block_for_point<'a>(blocks: &'a [Block], point: TextPoint, source: &Rope) -> Option<&'a Block> {
-    let mut curr_block: Option<&Block> = None;
-    let mut curr_level = blocks;
-    'outer: while !curr_level.is_empty() {
-        for block in curr_level {
-            if block.text_range().contains(point, source) {
-                // walk divider blocks but do not return them
-                // dividers can also have nonsense columns walk them even if
-                // the column is less than the point's
-                if block.syntax_type != BlockType::Divider {
-                    // check that the column of the block is less than the point's.
-                    // this because the block's text range includes the indents but we want
-                    // clicking on indents to select the scope above
-                    if point.col < block.col {
-                        continue;
-                    }
-
-                    curr_block = Some(block);
-                }
-
-                curr_level = &block.children;
-                continue 'outer;
-            }
+/// The edit that drops the block's text at the point
+fn drop_edit(text: &str, drop_point: TextPoint, source: &Source) -> TextEdit<'static> {
+    let mut indented_text = set_indent(text, drop_point.col, source.indent_style());
+
+    // if at the end of the file, and the last line doesn't have a newline, add one
+    if drop_point.line == source.text().len_lines() {
+        indented_text.insert_str(0, source.text().detect_linebreak());
+    }
+
+    let insert_point = TextPoint::new(drop_point.line, 0);
+    TextEdit::new(
+        Cow::Owned(indented_text),
+        TextRange::new_cursor(insert_point),
+    )
+}
+
+/// The indent (in display columns) a block dropped at the line would get when dropped at the column
+fn drop_indent(line: usize, col: usize, source: &Source) -> usize {
+    let style = source.indent_style();
+
+    // limit to the first non-empty above line's level
+    // (or 1 more if it ends in a new scope character)
+    let mut relative_whitespace_line = line;
+    let allowed_indent = loop {
+        if relative_whitespace_line == 0 {
+            break 0;
+        }
+
+        let line_above = source.text().line(relative_whitespace_line - 1);
+        let above_indent = line_above.indent_width(style.width);
+
+        // if the line above is entirely whitespace, move to the line above
+        if line_above.whitespace_at_start() == line_above.len_chars_no_linebreak() {
+            relative_whitespace_line -= 1;
+            continue;
+        }
+
+        // if the line above ends in a new scope character, allow one more indent
+        if line_above
+            .excluding_linebreak()
+            .ends_with(source.lang.config.new_scope_char.char())
+        {
+            break above_indent + style.width;
         }
-        break;
+
+        // otherwise, allow up to the same indent as the line above
+        break above_indent;
+    };
+    match source.lang.config.new_scope_char {
+        // when scope is indent based, allow reducing scope when dragging
+        NewScopeChar::Colon => ((col / style.width) * style.width).min(allowed_indent),
+        // when scope is brace based, only allow the maximum indent
+        NewScopeChar::Brace => allowed_indent,
+        // when scope is begin/end based, only allow the maximum indent
+        NewScopeChar::Begin => allowed_indent,
+    }
+}
+
+/// The first and last of the sibling blocks that the selection covers the lines of,
+/// if they include the innermost block of the path
+fn selected_siblings<'a>(
+    path: &[(&'a [Block], usize)],
+    selection: TextRange,
+    source: &Rope,
+) -> Option<(&'a Block, &'a Block)> {
+    let selection = selection.ordered();
+    if selection.is_cursor() {
+        return None;
     }
-    curr_block
+
+    // a selection ending at the start of a line doesn't include that line
+    let first_line = selection.start.line;
+    let last_line = if selection.end.col == 0 && selection.end.line > first_line {
+        selection.end.line - 1
+    } else {
+        selection.end.line
+    };
+    if last_line >= source.len_lines() {
+        return None;
+    }
+
+    // from the innermost level out, so the smallest blocks covering the selection are used
+    path.iter().rev().find_map(|&(level, idx)| {
+        let first = level.iter().position(|b| b.line == first_line)?;
+        let last = level
+            .iter()
+            .position(|b| b.line + b.height.max(1) - 1 == last_line)?;
+        (first <= idx && idx <= last).then(|| (&level[first], &level[last]))
+    })
 }
 
 /// Reduces the indent of the block such that the first line has no indent.
 /// Assumes the indents of all lines are aligned.
-fn normalize_indent(mut block: String) -> String {
+pub(super) fn normalize_indent(mut block: String) -> String {
     // set indent of string to the insertion point row
     // all lines after the first are indented relative to the first
-    let existing_indent_count = block.chars().take_while(|c| c.is_whitespace()).count();
-    let existing_indent = " ".repeat(existing_indent_count);
+    let existing_indent: String = block
+        .chars()
+        .take_while(|c| c.is_whitespace() && *c != '\n' && *c != '\r')
+        .collect();
 
     // replace the indent at the start (which is not after a newline)
     block = block.replacen(&existing_indent, "", 1);
@@ -240,23 +391,85 @@ fn normalize_indent(mut block: String) -> String {
     block
 }
 
-/// Increases the indent of a *normalized* block so the first line is indented to new_indent_count
+/// Increases the indent of a *normalized* block so the first line is indented to `new_indent` columns.
+/// The indent of every line is rewritten in the given style.
 /// Assumes the indents of all lines are aligned.
-fn set_indent(block: &str, new_indent_count: usize) -> String {
-    // get the new indent
-    let new_indent = " ".repeat(new_indent_count);
-    let new_linebreak_indent = format!("\n{new_indent}");
-
-    // add indent to start of string
-    let mut indented = format!("{new_indent}{block}");
+pub(super) fn set_indent(block: &str, new_indent: usize, style: IndentStyle) -> String {
+    block
+        .split_inclusive('\n')
+        .map(|line| {
+            let content = line.trim_start_matches([' ', '\t']);
+            let existing_indent: usize =
+                display_widths(line[..line.len() - content.len()].chars(), style.width)
+                    .map(|(_, width)| width)
+                    .sum();
+            format!(
+                "{}{}",
+                style.indent_str(new_indent + existing_indent),
+                content
+            )
+        })
+        .collect()
+}
 
-    // replace all linebreaks with linebreak indents
-    indented = indented.replace('\n', &new_linebreak_indent);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_editor::blocks::BlockTrees, lang::Language};
+
+    #[test]
+    fn test_restore_dragged_block() {
+        let text = "x = 1\nif x:\n    y = 2\nw = 4\n";
+        let mut source = Source::new(Rope::from_str(text), Language::for_file("test.py"));
+        let mut text_editor = TextEditor::new();
+
+        // drag the if out of the source, then put it back
+        source.apply_edit(
+            &TextEdit::delete(TextRange::new(TextPoint::new(1, 0), TextPoint::new(3, 0))),
+            UndoStopCondition::Always,
+            false,
+            &mut text_editor.selections,
+        );
+        let drag = DragSession {
+            text: "if x:\n    y = 2\n".to_string(),
+            offset: Pos2::ZERO,
+            from_palette: false,
+            copy: false,
+        };
+        text_editor.restore_dragged_block(&drag, &mut source);
+        assert_eq!(source.text().to_string(), text);
 
-    // remove trailing indent
-    if indented.ends_with(&new_linebreak_indent) {
-        indented.replace_range((indented.len() - new_linebreak_indent.len()).., "\n");
+        // redoing doesn't delete it again
+        source.redo(&mut text_editor.selections);
+        assert_eq!(source.text().to_string(), text);
     }
 
-    indented
+    #[test]
+    fn test_selected_siblings() {
+        let text = Rope::from_str("x = 1\nif x:\n    y = 2\n    z = 3\nw = 4\n");
+        let source = Source::new(text.clone(), Language::for_file("test.py"));
+        let blocks =
+            BlockTrees::for_ts_tree(&mut source.get_tree_cursor(), &text, source.lang.config, 4);
+        let lines = |selection: TextRange, point: TextPoint| {
+            let path = blocks.path_to(point, &text);
+            selected_siblings(&path, selection, &text)
+                .map(|(first, last)| (first.line, last.line + last.height))
+        };
+
+        // the lines of the if and the block after it
+        let selection = TextRange::new(TextPoint::new(1, 0), TextPoint::new(5, 0));
+        assert_eq!(lines(selection, TextPoint::new(1, 1)), Some((1, 5)));
+
+        // the two lines inside the if, dragged from the second
+        let selection = TextRange::new(TextPoint::new(3, 9), TextPoint::new(2, 4));
+        assert_eq!(lines(selection, TextPoint::new(3, 5)), Some((2, 4)));
+
+        // a selection that doesn't end at the end of a block
+        let selection = TextRange::new(TextPoint::new(0, 0), TextPoint::new(2, 6));
+        assert_eq!(lines(selection, TextPoint::new(0, 1)), None);
+
+        // or that doesn't include the block under the mouse
+        let selection = TextRange::new(TextPoint::new(0, 0), TextPoint::new(0, 5));
+        assert_eq!(lines(selection, TextPoint::new(4, 1)), None);
+    }
 }
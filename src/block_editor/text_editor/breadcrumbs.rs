@@ -0,0 +1,131 @@
+use egui::{Align2, Pos2, Widget};
+use ropey::Rope;
+
+use super::TextEditor;
+use crate::{
+    block_editor::{
+        blocks::Block, rope_ext::RopeSliceExt, source::Source, text_range::TextPoint,
+        MonospaceFont, GUTTER_WIDTH, OUTER_PAD,
+    },
+    theme,
+};
+
+/// drawn between the label of each block
+const SEPARATOR: &str = " › ";
+
+/// the most characters of a block's header to show before truncating it
+const MAX_LABEL_CHARS: usize = 30;
+
+/// The chain of blocks enclosing the cursor, drawn as a single line of text
+pub struct Breadcrumbs<'a> {
+    labels: Vec<String>,
+    font: &'a MonospaceFont,
+}
+
+impl TextEditor {
+    pub fn breadcrumbs<'a>(&self, source: &Source, font: &'a MonospaceFont) -> Breadcrumbs<'a> {
+        let text = source.text();
+
+        // treat the cursor being in the indent as being on the line's block
+        let cursor = self.selections.selection().end;
+        let indent = text.line(cursor.line).whitespace_at_start();
+        let point = TextPoint::new(cursor.line, cursor.col.max(indent));
+
+        let labels = self
+            .blocks
+            .path_to(point, text)
+            .into_iter()
+            .map(|(level, idx)| block_label(&level[idx], text))
+            .collect();
+
+        Breadcrumbs { labels, font }
+    }
+}
+
+/// The start of the block's first line, up until its parameters or scope begin
+pub(super) fn block_label(block: &Block, source: &Rope) -> String {
+    let header = source.line(block.line).to_string();
+    let header = header.trim();
+    let end = header.find(['(', '{', ':']).unwrap_or(header.len());
+    let label = header[..end].trim_end();
+
+    if label.chars().count() > MAX_LABEL_CHARS {
+        let truncated: String = label.chars().take(MAX_LABEL_CHARS).collect();
+        format!("{}…", truncated.trim_end())
+    } else {
+        label.to_string()
+    }
+}
+
+impl Widget for Breadcrumbs<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (id, rect) = ui.allocate_space(ui.available_size());
+        let response = ui.interact(rect, id, egui::Sense::hover());
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, theme::current().editor.background);
+        painter.text(
+            Pos2::new(rect.min.x + OUTER_PAD + GUTTER_WIDTH, rect.center().y),
+            Align2::LEFT_CENTER,
+            self.labels.join(SEPARATOR),
+            self.font.id.clone(),
+            theme::current().editor.interface_text,
+        );
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_editor::{blocks::BlockTrees, text_range::TextRange},
+        lang::Language,
+    };
+
+    #[test]
+    fn test_breadcrumbs() {
+        let source = Source::new(
+            Rope::from_str("class Shape:\n    def area(self, scale):\n        if scale > 1:\n            return 0\n"),
+            Language::for_file("test.py"),
+        );
+        let font = MonospaceFont::new("test", 14.0);
+        let mut editor = TextEditor::new();
+        editor.blocks = BlockTrees::for_ts_tree(
+            &mut source.get_tree_cursor(),
+            source.text(),
+            source.lang.config,
+            4,
+        );
+        let mut labels_at = |line, col| {
+            let cursor = TextRange::new_cursor(TextPoint::new(line, col));
+            editor.selections.set_selection(cursor, &source);
+            editor.breadcrumbs(&source, &font).labels
+        };
+
+        // each header is cut off before its parameters or scope
+        assert_eq!(
+            labels_at(3, 14),
+            vec!["class Shape", "def area", "if scale > 1", "return 0"]
+        );
+        assert_eq!(labels_at(1, 6), vec!["class Shape", "def area"]);
+
+        // the cursor in the indent is on the line's block
+        assert_eq!(
+            labels_at(2, 0),
+            vec!["class Shape", "def area", "if scale > 1"]
+        );
+    }
+
+    #[test]
+    fn test_block_label_truncated() {
+        let text = Rope::from_str("if some_very_long_condition_name and another_one:\n    pass\n");
+        let source = Source::new(text.clone(), Language::for_file("test.py"));
+        let blocks =
+            BlockTrees::for_ts_tree(&mut source.get_tree_cursor(), &text, source.lang.config, 4);
+        let block = blocks.children_of(None)[0];
+
+        assert_eq!(block_label(block, &text), "if some_very_long_condition_na…");
+    }
+}
@@ -2,8 +2,9 @@ use std::borrow::Cow;
 
 use egui::{Pos2, Rect, Response, Sense, Ui, Vec2, Widget};
 use ropey::Rope;
+use unicode_width::UnicodeWidthStr;
 
-use super::TextEdit;
+use super::{coord_conversions::display_col, TextEdit};
 use crate::{
     block_editor::{
         blocks::Padding,
@@ -54,7 +55,7 @@ impl CompletionPopup {
             let painter = ui.painter();
 
             // draw background
-            painter.rect_filled(rect, 0.0, theme::POPUP_BACKGROUND);
+            painter.rect_filled(rect, 0.0, theme::current().editor.popup_background);
 
             // draw completions
             for (line, completion) in self.completions.iter().enumerate() {
@@ -143,7 +144,7 @@ impl CompletionPopup {
         let max_label_len: usize = self
             .completions
             .iter()
-            .map(|fix| fix.name().chars().count())
+            .map(|fix| fix.name().width())
             .max()
             .unwrap_or(0);
         let width = max_label_len as f32 * font.size.x;
@@ -151,11 +152,18 @@ impl CompletionPopup {
         Vec2::new(width, height)
     }
 
-    pub fn calc_origin(&self, cursor: TextPoint, padding: &Padding, font: &MonospaceFont) -> Pos2 {
+    pub fn calc_origin(
+        &self,
+        cursor: TextPoint,
+        padding: &Padding,
+        source: &Rope,
+        font: &MonospaceFont,
+    ) -> Pos2 {
         // find the bottom of the current selection
         let total_padding: f32 = padding.cumulative(cursor.line + 1);
-        let y = (cursor.line as f32 + 2.0) * font.size.y + total_padding;
-        let x = (cursor.col as f32) * font.size.x + TOTAL_TEXT_X_OFFSET;
+        let y = (padding.row_of(cursor) as f32 + 2.0) * font.size.y + total_padding;
+        let x =
+            (display_col(cursor, padding, source, font) as f32) * font.size.x + TOTAL_TEXT_X_OFFSET;
         Pos2::new(x, y)
     }
 
@@ -1,10 +1,11 @@
 use egui::Pos2;
 use ropey::Rope;
 
-use super::TextPoint;
+use super::{TextPoint, TextRange};
 use crate::block_editor::{
-    blocks::Padding, rope_ext::RopeExt, MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD,
-    TOTAL_TEXT_X_OFFSET,
+    blocks::Padding,
+    rope_ext::{RopeExt, RopeSliceExt},
+    MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD, TOTAL_TEXT_X_OFFSET,
 };
 
 pub fn pt_to_text_coord(
@@ -17,7 +18,7 @@ pub fn pt_to_text_coord(
     let raw_y = point.y - OUTER_PAD;
     let mut line: usize = 0;
     for line_pad in padding.cumulative_iter() {
-        let curr_line_start = line_pad + (line as f32 * font.size.y);
+        let curr_line_start = line_pad + (padding.first_row(line) as f32 * font.size.y);
         if raw_y <= curr_line_start {
             break;
         }
@@ -34,8 +35,13 @@ pub fn pt_to_text_coord(
 
     // TODO: if past last line, move to end of last line
 
-    let col_raw =
+    let row = row_in_line(raw_y, line, padding, font);
+    let visual_col =
         ((point.x - OUTER_PAD - GUTTER_WIDTH - TEXT_L_PAD) / font.size.x).round() as usize;
+    let col_raw = padding
+        .soft_wrap()
+        .point_at(line, row, visual_col, source, font.tab_width)
+        .col;
     let col_bound = source.clamp_col(line, col_raw);
 
     TextPoint::new(line, col_bound)
@@ -45,12 +51,13 @@ pub fn pt_to_text_coord(
 pub fn pt_to_unbounded_text_coord(
     point: Pos2,
     padding: &Padding,
+    source: &Rope,
     font: &MonospaceFont,
 ) -> TextPoint {
     // find the line clicked on by finding the next one and then going back one
     let mut line: usize = 0;
     for line_pad in padding.cumulative_iter() {
-        let curr_line_start = line_pad + (line as f32 * font.size.y);
+        let curr_line_start = line_pad + (padding.first_row(line) as f32 * font.size.y);
         let raw_y = point.y - OUTER_PAD;
         if raw_y <= curr_line_start {
             break;
@@ -59,18 +66,81 @@ pub fn pt_to_unbounded_text_coord(
     }
 
     // add any remaining lines past the last line
-    line += ((point.y - (padding.total() + (line as f32 * font.size.y))) / font.size.y) as usize;
+    line += ((point.y - (padding.total() + (padding.first_row(line) as f32 * font.size.y)))
+        / font.size.y) as usize;
 
     line = line.saturating_sub(1);
 
-    let col = ((point.x - OUTER_PAD - GUTTER_WIDTH - TEXT_L_PAD) / font.size.x).round() as usize;
-
-    TextPoint::new(line, col)
+    let row = row_in_line(point.y - OUTER_PAD, line, padding, font);
+    let visual_col =
+        ((point.x - OUTER_PAD - GUTTER_WIDTH - TEXT_L_PAD) / font.size.x).round() as usize;
+    padding
+        .soft_wrap()
+        .point_at(line, row, visual_col, source, font.tab_width)
 }
 
-pub fn text_coord_to_pt(coord: TextPoint, padding: &Padding, font: &MonospaceFont) -> Pos2 {
-    let y = OUTER_PAD + (coord.line as f32 * font.size.y) + padding.cumulative(coord.line);
-    let x = TOTAL_TEXT_X_OFFSET + (coord.col as f32 * font.size.x);
+pub fn text_coord_to_pt(
+    coord: TextPoint,
+    padding: &Padding,
+    source: &Rope,
+    font: &MonospaceFont,
+) -> Pos2 {
+    let y =
+        OUTER_PAD + (padding.row_of(coord) as f32 * font.size.y) + padding.cumulative(coord.line);
+    let x = TOTAL_TEXT_X_OFFSET + (display_col(coord, padding, source, font) as f32 * font.size.x);
 
     Pos2::new(x, y)
 }
+
+/// The column a text coordinate is displayed at within its row,
+/// accounting for the width of characters and where the line is wrapped
+pub fn display_col(
+    coord: TextPoint,
+    padding: &Padding,
+    source: &Rope,
+    font: &MonospaceFont,
+) -> usize {
+    padding
+        .soft_wrap()
+        .visual_col(coord, source, font.tab_width)
+}
+
+/// Split a range within a single line into the parts on each of the rows the line is wrapped onto
+pub fn split_into_rows(range: TextRange, padding: &Padding) -> Vec<TextRange> {
+    let line = range.start.line;
+    let mut row_starts = vec![range.start];
+    row_starts.extend(
+        padding
+            .soft_wrap()
+            .wrapped_rows(line)
+            .iter()
+            .filter(|row| range.start.col < row.col && row.col < range.end.col)
+            .map(|row| TextPoint::new(line, row.col)),
+    );
+
+    let mut row_ends: Vec<TextPoint> = row_starts[1..].to_vec();
+    row_ends.push(range.end);
+    row_starts
+        .into_iter()
+        .zip(row_ends)
+        .map(|(start, end)| TextRange::new(start, end))
+        .collect()
+}
+
+/// The width of a range within a single row in display columns
+pub fn display_width(range: TextRange, source: &Rope, font: &MonospaceFont) -> usize {
+    match source.get_line(range.start.line) {
+        Some(line) => {
+            line.display_col(range.end.col, font.tab_width)
+                - line.display_col(range.start.col, font.tab_width)
+        }
+        None => range.end.col - range.start.col,
+    }
+}
+
+/// The row within a line that a y position (below the outer padding) is on
+fn row_in_line(y: f32, line: usize, padding: &Padding, font: &MonospaceFont) -> usize {
+    let line_start = padding.cumulative(line.min(padding.count() - 1))
+        + (padding.first_row(line) as f32 * font.size.y);
+    ((y - line_start) / font.size.y).max(0.0) as usize
+}
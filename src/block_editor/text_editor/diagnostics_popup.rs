@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use egui::{Align2, Painter, Pos2, Rect, Response, Stroke, Ui, Vec2, Widget};
 use ropey::Rope;
+use unicode_width::UnicodeWidthStr;
 
+use super::coord_conversions::{display_col, display_width, split_into_rows};
 use crate::{
     block_editor::{blocks::Padding, MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET},
     lsp::diagnostics::{Diagnostic, VSCodeCodeAction},
@@ -31,7 +33,8 @@ impl DiagnosticPopup {
             let response = ui.interact(rect, id, egui::Sense::click_and_drag());
 
             // set background color
-            ui.painter().rect_filled(rect, 0.0, theme::POPUP_BACKGROUND);
+            ui.painter()
+                .rect_filled(rect, 0.0, theme::current().editor.popup_background);
 
             // draw message
             ui.painter().text(
@@ -39,7 +42,7 @@ impl DiagnosticPopup {
                 Align2::LEFT_TOP,
                 &diagnostic.message,
                 font.id.clone(),
-                theme::syntax::DEFAULT,
+                theme::current().syntax.default,
             );
 
             // draw fixes
@@ -52,7 +55,7 @@ impl DiagnosticPopup {
                         ),
                         SelectableRow::new(
                             &fix.title,
-                            theme::syntax::FUNCTION,
+                            theme::current().syntax.function,
                             false,
                             font.id.clone(),
                         ),
@@ -83,6 +86,7 @@ impl DiagnosticPopup {
         diagnostic: &Diagnostic,
         offset: Vec2,
         padding: &Padding,
+        source: &Rope,
         font: &MonospaceFont,
     ) -> Pos2 {
         // find height
@@ -95,7 +99,7 @@ impl DiagnosticPopup {
         let total_padding: f32 = padding.cumulative(diagnostic.range.start.line + 1);
         let diagnostic_start = OUTER_PAD
             + total_padding
-            + (diagnostic.range.start.line as f32 * font.size.y)
+            + (padding.row_of(diagnostic.range.start) as f32 * font.size.y)
             + offset.y;
         let y = if height > diagnostic_start {
             // put it below the line if there isn't enough room above
@@ -105,7 +109,8 @@ impl DiagnosticPopup {
         };
 
         // find the horizontal start
-        let x = TOTAL_TEXT_X_OFFSET + (diagnostic.range.start.col as f32 * font.size.x) + offset.x;
+        let start_col = display_col(diagnostic.range.start, padding, source, font);
+        let x = TOTAL_TEXT_X_OFFSET + (start_col as f32 * font.size.x) + offset.x;
 
         Pos2::new(x, y)
     }
@@ -117,13 +122,9 @@ impl DiagnosticPopup {
             height += fixes.len() as f32 * font.size.y;
         }
 
-        let text_len = diagnostic.message.chars().count();
+        let text_len = diagnostic.message.width();
         let max_fix_len: usize = if let Some(fixes) = &self.fixes.get(&diagnostic.id) {
-            fixes
-                .iter()
-                .map(|fix| fix.title.chars().count())
-                .max()
-                .unwrap_or(0)
+            fixes.iter().map(|fix| fix.title.width()).max().unwrap_or(0)
         } else {
             0
         };
@@ -146,21 +147,23 @@ impl Diagnostic {
         let line_ranges = range.individual_lines(source);
 
         for line_range in line_ranges {
-            let line_num = line_range.start.line;
-
-            // find bottom of current line
-            let y =
-                padding.cumulative(line_num) + ((line_num + 1) as f32 * font.size.y) + OUTER_PAD;
-
-            // find the start and end of the line
-            let x = TOTAL_TEXT_X_OFFSET + (line_range.start.col as f32 * font.size.x);
-            let width = (line_range.end.col - line_range.start.col) as f32 * font.size.x;
-
-            // draw line
-            painter.line_segment(
-                [Pos2::new(x, y) + offset, Pos2::new(x + width, y) + offset],
-                Stroke::new(2.0, self.severity.color()),
-            );
+            for row_range in split_into_rows(line_range, padding) {
+                // find bottom of current row
+                let y = padding.cumulative(row_range.start.line)
+                    + ((padding.row_of(row_range.start) + 1) as f32 * font.size.y)
+                    + OUTER_PAD;
+
+                // find the start and end of the row
+                let start_col = display_col(row_range.start, padding, source, font);
+                let x = TOTAL_TEXT_X_OFFSET + (start_col as f32 * font.size.x);
+                let width = display_width(row_range, source, font) as f32 * font.size.x;
+
+                // draw line
+                painter.line_segment(
+                    [Pos2::new(x, y) + offset, Pos2::new(x + width, y) + offset],
+                    Stroke::new(2.0, self.severity.color()),
+                );
+            }
         }
     }
 }
@@ -1,9 +1,11 @@
+use super::coord_conversions::display_col;
 use crate::{
     block_editor::{blocks::Padding, MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET},
     lsp::documentation::Documentation,
 };
 use egui::{Pos2, Vec2};
 use egui_commonmark::CommonMarkCache;
+use ropey::Rope;
 pub struct DocumentationPopup {
     pub markdown_cache: CommonMarkCache,
 }
@@ -31,15 +33,16 @@ impl DocumentationPopup {
         documentation: &Documentation,
         offset: Vec2,
         padding: &Padding,
+        source: &Rope,
         font: &MonospaceFont,
     ) -> Pos2 {
         //Largely based obn diagnostic_popup.rs version
         let total_padding = padding.cumulative(documentation.range.start.line + 1);
-        let x =
-            TOTAL_TEXT_X_OFFSET + (documentation.range.start.col as f32 * font.size.x) + offset.x;
+        let start_col = display_col(documentation.range.start, padding, source, font);
+        let x = TOTAL_TEXT_X_OFFSET + (start_col as f32 * font.size.x) + offset.x;
         let y = OUTER_PAD
             + total_padding
-            + (documentation.range.start.line as f32 * font.size.y)
+            + (padding.row_of(documentation.range.start) as f32 * font.size.y)
             + offset.y
             + 8.8; //This puts it at about midpoint of the line, 10 looks better but it goes away before the mouse moves on to it, maybe 9.5 is better.
                    //FIX: LITTLE GAP BETWEEN TEXT AND POPUP GOES AWAY, COULDN'T FIGURE OUT.
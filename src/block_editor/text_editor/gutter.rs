@@ -69,8 +69,10 @@ impl Widget for Gutter<'_> {
 impl Gutter<'_> {
     fn draw(&self, preview_line: Option<usize>, offset: Vec2, painter: &Painter) {
         for (num, line_cumulative_padding) in self.padding.cumulative_iter().enumerate() {
+            // only label the first row of wrapped lines
+            let row = self.padding.first_row(num);
             let y_pos =
-                offset.y + line_cumulative_padding + (self.font.size.y * num as f32) + OUTER_PAD;
+                offset.y + line_cumulative_padding + (self.font.size.y * row as f32) + OUTER_PAD;
 
             // draw a background color for the stack trace lines
             // TODO: look better (maybe highlight the code instead of the gutter?)
@@ -81,7 +83,7 @@ impl Gutter<'_> {
                         Pos2::new(offset.x, y_pos),
                         Vec2::new(GUTTER_WIDTH, self.font.size.y),
                     );
-                    painter.rect_filled(rect, 0.0, theme::STACK_FRAME_SELECTED);
+                    painter.rect_filled(rect, 0.0, theme::current().editor.stack_frame_selected);
                 }
             }
 
@@ -91,15 +93,15 @@ impl Gutter<'_> {
                         Pos2::new(offset.x, y_pos),
                         Vec2::new(GUTTER_WIDTH, self.font.size.y),
                     );
-                    painter.rect_filled(rect, 0.0, theme::STACK_FRAME_DEEPEST);
+                    painter.rect_filled(rect, 0.0, theme::current().editor.stack_frame_deepest);
                 }
             }
 
             // draw a red dot before line numbers that have breakpoints
             let color: Option<Color32> = if self.breakpoints.contains(&num) {
-                Some(theme::BREAKPOINT)
+                Some(theme::current().editor.breakpoint)
             } else if preview_line == Some(num) {
-                Some(theme::PREVIEW_BREAKPOINT)
+                Some(theme::current().editor.preview_breakpoint)
             } else {
                 None
             };
@@ -110,9 +112,9 @@ impl Gutter<'_> {
 
             // draw the line number
             let color = if self.curr_line == num {
-                theme::INTERFACE_TEXT
+                theme::current().editor.interface_text
             } else {
-                theme::LINE_NUMBERS
+                theme::current().editor.line_numbers
             };
             let display_num = (num + 1).to_string();
             let pos = Pos2::new(offset.x + GUTTER_WIDTH, y_pos);
@@ -0,0 +1,263 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use egui::{Key, Modifiers, Painter, Stroke, Vec2};
+
+use super::{TextEdit, TextEditor};
+use crate::{
+    block_editor::{
+        source::{Source, UndoStopCondition},
+        text_range::{TextPoint, TextRange},
+        MonospaceFont,
+    },
+    theme, vscode,
+};
+
+/// The local variable at the cursor, found again when the cursor moves or the text changes
+#[derive(Default)]
+pub struct LocalHighlights {
+    /// the cursor the variable was found for, or None if it needs to be found again
+    cursor: Option<TextPoint>,
+
+    variable: Option<LocalRanges>,
+}
+
+/// Where a local variable is defined and used in the text
+struct LocalRanges {
+    definition: TextRange,
+
+    /// every use of the name, including the definitions, in order
+    references: Vec<TextRange>,
+}
+
+impl LocalHighlights {
+    /// Find the variable again next frame (like after the text changes)
+    pub fn clear(&mut self) {
+        self.cursor = None;
+        self.variable = None;
+    }
+}
+
+impl TextEditor {
+    /// Returns if the keypress was handled as a local variable command
+    pub(super) fn handle_local_variable_keypress(
+        &mut self,
+        modifiers: &Modifiers,
+        key: Key,
+        source: &mut Source,
+    ) -> bool {
+        match key {
+            Key::F12 if modifiers.is_none() => self.go_to_local_definition(source),
+            Key::F2 if modifiers.is_none() => self.open_rename_popup(source),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Find the variable at the cursor if the cursor moved since it was last found
+    pub(super) fn update_local_highlights(&mut self, source: &Source) {
+        let selection = self.selections.selection();
+        if !selection.is_cursor() {
+            self.local_highlights.clear();
+            return;
+        }
+        if self.local_highlights.cursor == Some(selection.end) {
+            return;
+        }
+        self.local_highlights = LocalHighlights {
+            cursor: Some(selection.end),
+            variable: find_local(selection.end, source),
+        };
+    }
+
+    /// Highlight every reference to the variable at the cursor, outlining where it is defined
+    pub(super) fn draw_local_highlights(
+        &self,
+        offset: Vec2,
+        source: &Source,
+        font: &MonospaceFont,
+        painter: &Painter,
+    ) {
+        let Some(variable) = &self.local_highlights.variable else {
+            return;
+        };
+        for range in &variable.references {
+            let stroke = if *range == variable.definition {
+                Stroke::new(1.0_f32, theme::current().editor.search_result_selected)
+            } else {
+                Stroke::NONE
+            };
+            range.draw_selection_blocks(
+                theme::current().editor.search_result,
+                stroke,
+                offset,
+                self.blocks.padding(),
+                source.text(),
+                font,
+                painter,
+            );
+        }
+    }
+
+    /// Move the cursor to where the local variable at the cursor is defined
+    pub fn go_to_local_definition(&mut self, source: &mut Source) {
+        let Some(variable) = find_local(self.selections.selection().end, source) else {
+            return;
+        };
+        vscode::log_event(
+            "editor-local-definition",
+            HashMap::from([("lang", source.lang.config.name)]),
+        );
+        self.selections
+            .set_selection(TextRange::new_cursor(variable.definition.start), source);
+        source.external_cursor_move();
+        self.scroll_to_cursor_requested = true;
+    }
+
+    /// Start renaming the local variable at the cursor
+    pub fn open_rename_popup(&mut self, source: &Source) {
+        let cursor = self.selections.selection().end;
+        if let Some(variable) = find_local(cursor, source) {
+            let name = source
+                .text()
+                .slice(variable.definition.char_range_in(source.text()))
+                .to_string();
+            self.rename_popup.open(name);
+            self.completion_popup.clear();
+        }
+    }
+
+    /// Rename every reference to the local variable at the cursor as a single undo step
+    pub fn rename_local(&mut self, new_name: &str, source: &mut Source) {
+        let cursor = self.selections.selection().end;
+        let Some(variable) = find_local(cursor, source) else {
+            return;
+        };
+        vscode::log_event(
+            "editor-local-rename",
+            HashMap::from([("lang", source.lang.config.name)]),
+        );
+
+        // edit from the end so the earlier ranges stay the same
+        for (idx, range) in variable.references.iter().rev().enumerate() {
+            let stop_before = if idx == 0 {
+                UndoStopCondition::Always
+            } else {
+                UndoStopCondition::Never
+            };
+            let edit = TextEdit::new(Cow::Borrowed(new_name), *range);
+            source.apply_edit(&edit, stop_before, false, &mut self.selections);
+        }
+        source.end_undo_step();
+
+        // keep the cursor in the same place in the renamed name
+        let new_len = new_name.chars().count();
+        let mut col = cursor.col;
+        for range in variable
+            .references
+            .iter()
+            .filter(|range| range.start.line == cursor.line && range.start.col < cursor.col)
+        {
+            let old_len = range.end.col - range.start.col;
+            let before = (cursor.col - range.start.col).min(old_len);
+            let new_before = if cursor.col > range.end.col {
+                new_len
+            } else {
+                before.min(new_len)
+            };
+            col = col - before + new_before;
+        }
+        let new_cursor = TextPoint::new(cursor.line, col);
+        self.selections
+            .set_selection(TextRange::new_cursor(new_cursor), source);
+        self.focus_requested = true;
+    }
+}
+
+/// Find the local variable with a definition or reference at the point
+fn find_local(point: TextPoint, source: &Source) -> Option<LocalRanges> {
+    let query = source.lang.locals_query.as_ref()?;
+    let text = source.text();
+    let variable = query.variable_at(
+        source.get_tree_cursor().node(),
+        text,
+        point.byte_idx_in(text),
+    )?;
+
+    let to_range = |bytes: &std::ops::Range<usize>| {
+        TextRange::from_char_range_in(
+            text,
+            text.byte_to_char(bytes.start)..text.byte_to_char(bytes.end),
+        )
+    };
+    Some(LocalRanges {
+        definition: to_range(&variable.definition),
+        references: variable.references.iter().map(to_range).collect(),
+    })
+}
+
+/// Why the local variable at the point can't be renamed to the name, or None if it can be
+pub fn rename_problem(name: &str, point: TextPoint, source: &Source) -> Option<String> {
+    if !is_valid_name(name) {
+        return Some("Not a valid name".to_string());
+    }
+    if source.lang.is_keyword(name) {
+        return Some(format!("\"{name}\" is a keyword"));
+    }
+
+    let query = source.lang.locals_query.as_ref()?;
+    let text = source.text();
+    let conflicts = query.name_conflicts(
+        source.get_tree_cursor().node(),
+        text,
+        point.byte_idx_in(text),
+        name,
+    );
+    conflicts.then(|| format!("\"{name}\" is already used here"))
+}
+
+/// If the name can be used as a variable name (letters, numbers, and underscores, not starting with a number)
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("count"));
+        assert!(is_valid_name("_total2"));
+        assert!(is_valid_name("größe"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2nd"));
+        assert!(!is_valid_name("my name"));
+        assert!(!is_valid_name("a-b"));
+    }
+
+    #[test]
+    fn test_rename_problem() {
+        let source = Source::new(
+            ropey::Rope::from_str("def f(a):\n    b = a\n    return b\n"),
+            crate::lang::Language::for_file("test.py"),
+        );
+        let point = TextPoint::new(0, 6);
+        assert_eq!(rename_problem("c", point, &source), None);
+        assert_eq!(
+            rename_problem("2c", point, &source),
+            Some("Not a valid name".into())
+        );
+        assert_eq!(
+            rename_problem("return", point, &source),
+            Some("\"return\" is a keyword".into())
+        );
+        assert_eq!(
+            rename_problem("b", point, &source),
+            Some("\"b\" is already used here".into())
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use egui::{Color32, CursorIcon, Rect, Sense, Stroke, Ui, Vec2, Widget};
+
+use super::TextEditor;
+use crate::{
+    block_editor::{
+        rope_ext::display_widths, search::SearchResults, soft_wrap::WrappedRow, BlockType,
+        MonospaceFont, OUTER_PAD, TEXT_L_PAD,
+    },
+    theme::{self, blocks_theme::BlocksTheme, SyntaxColor},
+};
+
+/// the width of the minimap strip beside the editor
+pub const MINIMAP_WIDTH: f32 = 90.0;
+
+/// how much smaller the minimap is than the editor
+const SCALE: f32 = 0.125;
+
+/// the width of the marks for diagnostics and search results on the right edge
+const MARK_WIDTH: f32 = 4.0;
+
+/// The whole document scaled down, drawn beside the editor.
+/// The shapes are found once each time the text changes (not each time the view scrolls),
+/// and the colors are looked up when drawing so they follow the theme.
+pub struct Minimap {
+    /// the character size the shapes were found for, or None if they need to be found again
+    char_size: Option<Vec2>,
+
+    blocks: Vec<MinimapBlock>,
+    text: Vec<MinimapText>,
+
+    /// the scaled height of the document
+    height: f32,
+
+    /// the part of the editor scrolled into view last frame
+    viewport: Rect,
+
+    /// the height that the editor can scroll through
+    content_height: f32,
+
+    /// where to scroll the editor to next frame (after the viewport was dragged)
+    scroll_request: Option<f32>,
+}
+
+/// A block's outline, in scaled coordinates from the top left of the minimap
+struct MinimapBlock {
+    rect: Rect,
+    block_type: BlockType,
+    level: usize,
+}
+
+/// A run of characters of the same color drawn as a line
+struct MinimapText {
+    rect: Rect,
+    color: SyntaxColor,
+}
+
+/// A run of non-whitespace characters of the same color in a line
+#[derive(Debug, PartialEq)]
+struct TextRun {
+    /// which of the line's wrapped rows it is on
+    row: usize,
+
+    /// the display columns it covers
+    start: usize,
+    end: usize,
+
+    color: SyntaxColor,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Minimap {
+            char_size: None,
+            blocks: vec![],
+            text: vec![],
+            height: 0.0,
+            viewport: Rect::NOTHING,
+            content_height: 0.0,
+            scroll_request: None,
+        }
+    }
+
+    /// Find the shapes again next frame (like after the text changes)
+    pub fn clear(&mut self) {
+        self.char_size = None;
+    }
+
+    /// Remember the part of the editor in view, to draw the viewport rectangle from
+    pub fn set_view(&mut self, viewport: Rect, content_height: f32) {
+        self.viewport = viewport;
+        self.content_height = content_height;
+    }
+
+    /// Where the editor should scroll to, if the viewport was dragged
+    pub fn take_scroll_request(&mut self) -> Option<f32> {
+        self.scroll_request.take()
+    }
+
+    /// How far the minimap is scrolled, when the document is taller than the space for it.
+    /// It scrolls along with the editor, so both reach their ends together.
+    fn scroll_offset(&self, map_height: f32) -> f32 {
+        let overflow = self.height - map_height;
+        let scrollable = self.content_height - self.viewport.height();
+        if overflow <= 0.0 || scrollable <= 0.0 {
+            return 0.0;
+        }
+        (self.viewport.min.y / scrollable).clamp(0.0, 1.0) * overflow
+    }
+}
+
+impl TextEditor {
+    pub fn minimap<'a>(
+        &'a mut self,
+        search_results: &'a Option<SearchResults>,
+        blocks_theme: BlocksTheme,
+        font: &'a MonospaceFont,
+    ) -> impl Widget + 'a {
+        move |ui: &mut Ui| -> egui::Response {
+            let (id, rect) = ui.allocate_space(ui.available_size());
+            let response = ui
+                .interact(rect, id, Sense::click_and_drag())
+                .on_hover_cursor(CursorIcon::Default);
+
+            if self.minimap.char_size != Some(font.size) {
+                self.update_minimap(font);
+            }
+
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0.0, theme::current().editor.background);
+
+            let minimap = &self.minimap;
+            let scroll_offset = minimap.scroll_offset(rect.height());
+            let offset = rect.min.to_vec2() - Vec2::new(0.0, scroll_offset);
+            let visible = |shape: &Rect| {
+                shape.bottom() + offset.y >= rect.top() && shape.top() + offset.y <= rect.bottom()
+            };
+
+            // blocks, then the text on top
+            for block in minimap.blocks.iter().filter(|block| visible(&block.rect)) {
+                if let Some(color) = (blocks_theme.color_for)(block.block_type, block.level) {
+                    let block_rect = block.rect.translate(offset);
+                    painter.rect_filled(block_rect, 1.0, color.gamma_multiply(0.2));
+                    painter.rect_stroke(
+                        block_rect,
+                        1.0,
+                        Stroke::new(1.0_f32, color),
+                        egui::StrokeKind::Inside,
+                    );
+                }
+            }
+            let syntax = &theme::current().syntax;
+            for text in minimap.text.iter().filter(|text| visible(&text.rect)) {
+                painter.rect_filled(
+                    text.rect.translate(offset),
+                    0.0,
+                    syntax.get(text.color).gamma_multiply(0.8),
+                );
+            }
+
+            // the lines with diagnostics and search results, on the right edge
+            let padding = self.blocks.padding();
+            let line_y = |line: usize| {
+                (OUTER_PAD
+                    + padding.cumulative(line)
+                    + padding.first_row(line) as f32 * font.size.y)
+                    * SCALE
+                    + offset.y
+            };
+            let mark = |line: usize, color: Color32| {
+                let mark_rect = Rect::from_min_size(
+                    egui::pos2(rect.right() - MARK_WIDTH, line_y(line)),
+                    Vec2::new(MARK_WIDTH, (font.size.y * SCALE).max(2.0)),
+                );
+                painter.rect_filled(mark_rect, 0.0, color);
+            };
+            if let Some(results) = search_results {
+                for result in results.results() {
+                    mark(
+                        result.start.line,
+                        theme::current().editor.search_result_selected,
+                    );
+                }
+            }
+            for diagnostic in &self.diagnostics {
+                mark(diagnostic.range.start.line, diagnostic.severity.color());
+            }
+
+            // the part of the document in view
+            let viewport_rect = Rect::from_min_size(
+                egui::pos2(rect.left(), minimap.viewport.min.y * SCALE + offset.y),
+                Vec2::new(rect.width(), minimap.viewport.height() * SCALE),
+            );
+            painter.rect_filled(
+                viewport_rect,
+                0.0,
+                theme::current().editor.interface_text.gamma_multiply(0.1),
+            );
+            painter.rect_stroke(
+                viewport_rect,
+                0.0,
+                Stroke::new(1.0_f32, theme::current().editor.line_numbers),
+                egui::StrokeKind::Inside,
+            );
+
+            // clicking centers the view on that point, and dragging moves the viewport along
+            let max_scroll = (minimap.content_height - minimap.viewport.height()).max(0.0);
+            let new_scroll = if response.dragged() && !response.drag_started() {
+                // the viewport rectangle moves this far for each point scrolled
+                let map_height = minimap.height.min(rect.height());
+                let track = map_height - minimap.viewport.height() * SCALE;
+                (track > 0.0)
+                    .then(|| minimap.viewport.min.y + response.drag_delta().y * max_scroll / track)
+            } else if response.clicked() || response.drag_started() {
+                response
+                    .interact_pointer_pos()
+                    .map(|pos| (pos.y - offset.y) / SCALE - minimap.viewport.height() / 2.0)
+            } else {
+                None
+            };
+            if let Some(scroll) = new_scroll {
+                self.minimap.scroll_request = Some(scroll.clamp(0.0, max_scroll));
+                ui.ctx().request_repaint();
+            }
+
+            response
+        }
+    }
+
+    /// Find the scaled shapes of the blocks and text
+    fn update_minimap(&mut self, font: &MonospaceFont) {
+        let padding = self.blocks.padding();
+        let width = MINIMAP_WIDTH / SCALE - OUTER_PAD;
+        self.minimap.blocks = self
+            .blocks
+            .outlines(width, font.size)
+            .into_iter()
+            .map(|outline| MinimapBlock {
+                rect: scale(outline.rect.translate(Vec2::splat(OUTER_PAD))),
+                block_type: outline.block.syntax_type,
+                level: outline.level,
+            })
+            .collect();
+
+        self.minimap.text.clear();
+        for line in 0..padding.count() {
+            let wrapped_rows = padding.soft_wrap().wrapped_rows(line);
+            let top =
+                OUTER_PAD + padding.cumulative(line) + padding.first_row(line) as f32 * font.size.y;
+            for run in text_runs(
+                self.text_drawer.line_chunks(line),
+                wrapped_rows,
+                font.tab_width,
+            ) {
+                // a line through the middle of the row, thinner than the text
+                let row_top = top + (run.row as f32 + 0.3) * font.size.y;
+                let rect = Rect::from_min_max(
+                    egui::pos2(
+                        OUTER_PAD + TEXT_L_PAD + run.start as f32 * font.size.x,
+                        row_top,
+                    ),
+                    egui::pos2(
+                        OUTER_PAD + TEXT_L_PAD + run.end as f32 * font.size.x,
+                        row_top + font.size.y * 0.4,
+                    ),
+                );
+                self.minimap.text.push(MinimapText {
+                    rect: scale(rect),
+                    color: run.color,
+                });
+            }
+        }
+
+        let rows = padding.first_row(padding.count());
+        self.minimap.height =
+            (rows as f32 * font.size.y + OUTER_PAD * 2.0 + padding.total()) * SCALE;
+        self.minimap.char_size = Some(font.size);
+    }
+}
+
+fn scale(rect: Rect) -> Rect {
+    Rect::from_min_max(
+        (rect.min.to_vec2() * SCALE).to_pos2(),
+        (rect.max.to_vec2() * SCALE).to_pos2(),
+    )
+}
+
+/// Split a line's colored text into the runs to draw, following where it wraps
+fn text_runs(
+    chunks: &[(String, SyntaxColor)],
+    wrapped_rows: &[WrappedRow],
+    tab_width: usize,
+) -> Vec<TextRun> {
+    let colors = chunks
+        .iter()
+        .flat_map(|(text, color)| text.chars().map(move |_| *color));
+    let widths = display_widths(chunks.iter().flat_map(|(text, _)| text.chars()), tab_width);
+
+    let mut runs: Vec<TextRun> = vec![];
+    let mut rows = wrapped_rows.iter().peekable();
+    let mut row = 0;
+    let mut display_col = 0;
+    let mut continues_run = false;
+    for (col, (color, (char, width))) in colors.zip(widths).enumerate() {
+        if let Some(wrapped) = rows.next_if(|wrapped| wrapped.col == col) {
+            row += 1;
+            display_col = wrapped.indent;
+            continues_run = false;
+        }
+
+        if char.is_whitespace() {
+            continues_run = false;
+        } else if let Some(run) = runs
+            .last_mut()
+            .filter(|run| continues_run && run.color == color)
+        {
+            run.end = display_col + width;
+        } else {
+            runs.push(TextRun {
+                row,
+                start: display_col,
+                end: display_col + width,
+                color,
+            });
+            continues_run = true;
+        }
+        display_col += width;
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_runs() {
+        let chunks = vec![
+            ("\tif".to_string(), SyntaxColor::Keyword),
+            (" x == 1:\n".to_string(), SyntaxColor::Default),
+        ];
+        let run = |row, start, end, color| TextRun {
+            row,
+            start,
+            end,
+            color,
+        };
+
+        // whitespace and color changes end runs, and tabs take up their width
+        assert_eq!(
+            text_runs(&chunks, &[], 4),
+            vec![
+                run(0, 4, 6, SyntaxColor::Keyword),
+                run(0, 7, 8, SyntaxColor::Default),
+                run(0, 9, 11, SyntaxColor::Default),
+                run(0, 12, 14, SyntaxColor::Default),
+            ]
+        );
+
+        // wrapped rows start at their indent
+        let wrapped = [WrappedRow {
+            col: 6,
+            display_col: 9,
+            indent: 4,
+        }];
+        assert_eq!(
+            text_runs(&chunks, &wrapped, 4),
+            vec![
+                run(0, 4, 6, SyntaxColor::Keyword),
+                run(0, 7, 8, SyntaxColor::Default),
+                run(1, 4, 6, SyntaxColor::Default),
+                run(1, 7, 9, SyntaxColor::Default),
+            ]
+        );
+    }
+}
@@ -1,24 +1,42 @@
 use std::collections::HashSet;
 
+mod accessibility;
+mod block_commands;
 mod block_dragging;
+mod breadcrumbs;
 mod completion_popup;
 mod coord_conversions;
 mod diagnostics_popup;
 mod documentation_popup;
 mod gutter;
+mod local_variables;
+pub mod minimap;
+mod rename_popup;
+mod rich_copy;
 pub mod selections;
+mod sticky_headers;
+mod surround_popup;
 mod widget;
 
 use super::blocks::BlockTrees;
+use super::export::{clipboard::Clipboard, CopyFormat};
 use super::text_drawer::*;
 use super::text_range::*;
-use crate::block_editor::{source::TextEdit, text_range::TextRange};
+use crate::block_editor::{
+    source::{Source, TextEdit},
+    text_range::TextRange,
+};
 use crate::lsp::diagnostics::Diagnostic;
 use crate::lsp::documentation::Documentation;
+use block_dragging::DropCheck;
 use completion_popup::CompletionPopup;
 use diagnostics_popup::DiagnosticPopup;
 use documentation_popup::DocumentationPopup;
+use local_variables::LocalHighlights;
+use minimap::Minimap;
+use rename_popup::RenamePopup;
 use selections::Selections;
+use surround_popup::SurroundPopup;
 
 pub struct TextEditor {
     /// the actual and pseudo selection
@@ -59,6 +77,42 @@ pub struct TextEditor {
 
     /// overlay view for hover
     documentation_popup: DocumentationPopup,
+
+    /// menu of blocks to surround the selection with
+    surround_popup: SurroundPopup,
+
+    /// box for renaming the local variable at the cursor
+    rename_popup: RenamePopup,
+
+    /// references to the local variable at the cursor
+    local_highlights: LocalHighlights,
+
+    /// if the view should scroll to the cursor on the next frame (like after going to a definition)
+    scroll_to_cursor_requested: bool,
+
+    /// if long lines are wrapped onto multiple rows to fit the width of the editor
+    soft_wrap: bool,
+
+    /// the whole document scaled down, with the part in view
+    minimap: Minimap,
+
+    /// if the headers of the blocks enclosing the top of the view are pinned there
+    sticky_scroll: bool,
+
+    /// the cursor line last announced to screen readers
+    announced_line: Option<usize>,
+
+    /// if the editor should take focus on the next frame (like after inserting a block from the keyboard)
+    focus_requested: bool,
+
+    /// a copy with colors requested by a keyboard shortcut, done once the frame's input is handled
+    rich_copy_requested: Option<CopyFormat>,
+
+    /// where copies with colors go, kept for as long as the editor so what was copied stays available
+    clipboard: Clipboard,
+
+    /// if the block being dragged would parse at the drop point last checked
+    drop_check: Option<DropCheck>,
 }
 
 #[derive(Clone, Copy)]
@@ -92,6 +146,37 @@ impl TextEditor {
             diagnostic_popup: DiagnosticPopup::new(),
             completion_popup: CompletionPopup::new(),
             documentation_popup: DocumentationPopup::new(),
+            surround_popup: SurroundPopup::new(),
+            rename_popup: RenamePopup::new(),
+            local_highlights: LocalHighlights::default(),
+            scroll_to_cursor_requested: false,
+            soft_wrap: false,
+            minimap: Minimap::new(),
+            sticky_scroll: true,
+            announced_line: None,
+            focus_requested: false,
+            rich_copy_requested: None,
+            clipboard: Clipboard::new(),
+            drop_check: None,
         }
     }
+
+    /// Undo the last edit (like from a button outside of the editor), focusing the editor again
+    pub fn undo(&mut self, source: &mut Source) {
+        source.undo(&mut self.selections);
+        self.focus_requested = true;
+    }
+
+    /// The end of the selection, where the cursor is drawn
+    pub fn cursor(&self) -> TextPoint {
+        self.selections.selection().end
+    }
+
+    /// Select the range (like from the outline), scrolling to it and focusing the editor
+    pub fn select_and_reveal(&mut self, range: TextRange, source: &mut Source) {
+        self.selections.set_selection(range, source);
+        source.external_cursor_move();
+        self.scroll_to_cursor_requested = true;
+        self.focus_requested = true;
+    }
 }
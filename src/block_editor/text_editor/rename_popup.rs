@@ -0,0 +1,93 @@
+use egui::{Area, Frame, Id, Key, Order, Pos2, TextEdit, Ui};
+
+use crate::theme;
+
+/// Box for typing a new name for the local variable at the cursor
+pub struct RenamePopup {
+    /// the name being typed, or None if the popup is closed
+    name: Option<String>,
+
+    /// if the text box should take focus (when it is first shown)
+    focus_requested: bool,
+
+    /// the last name checked and why it can't be used, so it is only checked again once it changes
+    problem: Option<(String, Option<String>)>,
+}
+
+impl RenamePopup {
+    pub fn new() -> Self {
+        RenamePopup {
+            name: None,
+            focus_requested: false,
+            problem: None,
+        }
+    }
+
+    /// Open the popup starting with the current name
+    pub fn open(&mut self, name: String) {
+        self.name = Some(name);
+        self.focus_requested = true;
+    }
+
+    pub fn close(&mut self) {
+        self.name = None;
+        self.problem = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Show the popup at the position (in screen coordinates),
+    /// returning the new name once it is entered.
+    /// `find_problem` gives why a name can't be used, or None if it can.
+    pub fn show(
+        &mut self,
+        ui: &Ui,
+        pos: Pos2,
+        find_problem: impl FnOnce(&str) -> Option<String>,
+    ) -> Option<String> {
+        let name = self.name.as_mut()?;
+        let problem = &mut self.problem;
+        let mut entered = None;
+        let mut cancelled = false;
+        Area::new(Id::new("rename_popup"))
+            .order(Order::Foreground)
+            .fixed_pos(pos)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label("Rename variable");
+                    let response = ui.add(TextEdit::singleline(name).desired_width(160.0));
+                    if std::mem::take(&mut self.focus_requested) {
+                        response.request_focus();
+                    }
+
+                    if problem.as_ref().is_none_or(|(checked, _)| checked != name) {
+                        *problem = Some((name.clone(), find_problem(name)));
+                    }
+                    let problem = problem.as_ref().and_then(|(_, problem)| problem.as_ref());
+                    if let Some(problem) = problem {
+                        ui.colored_label(theme::current().diagnostic.error, problem);
+                    }
+
+                    if response.lost_focus() {
+                        if ui.input(|i| i.key_pressed(Key::Enter)) {
+                            if problem.is_none() {
+                                entered = Some(name.clone());
+                            } else {
+                                self.focus_requested = true;
+                            }
+                        } else {
+                            // escape or clicking elsewhere
+                            cancelled = true;
+                        }
+                    }
+                });
+            });
+
+        if entered.is_some() || cancelled {
+            self.close();
+        }
+        entered
+    }
+}
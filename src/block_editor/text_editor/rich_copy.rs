@@ -0,0 +1,69 @@
+use log::info;
+
+use super::TextEditor;
+use crate::{
+    block_editor::{
+        export::{to_html, to_image, CodeView, CopyFormat, ExportFont},
+        source::Source,
+        ExternalCommand, MonospaceFont,
+    },
+    theme::blocks_theme::BlocksTheme,
+};
+
+/// Pixels per point of copied images, so they stay sharp on high resolution screens and when scaled up
+const IMAGE_SCALE: f32 = 2.0;
+
+impl TextEditor {
+    /// Copy the selection with its syntax colors for pasting into documents and slides,
+    /// when requested by a command or the keyboard shortcut (which always includes the blocks)
+    pub(super) fn handle_rich_copy(
+        &mut self,
+        commands: &[ExternalCommand],
+        source: &Source,
+        blocks_theme: BlocksTheme,
+        font: &MonospaceFont,
+    ) {
+        let shortcut = self.rich_copy_requested.take().map(|format| (format, true));
+        let requested = commands.iter().filter_map(|command| match command {
+            ExternalCommand::CopyRich { format, blocks } => Some((*format, *blocks)),
+            _ => None,
+        });
+
+        for (format, blocks) in shortcut.into_iter().chain(requested) {
+            self.copy_rich(format, blocks, source, blocks_theme, font);
+        }
+    }
+
+    fn copy_rich(
+        &mut self,
+        format: CopyFormat,
+        blocks: bool,
+        source: &Source,
+        blocks_theme: BlocksTheme,
+        font: &MonospaceFont,
+    ) {
+        let selection = self.selections.selection();
+        if selection.is_cursor() {
+            info!("Nothing selected to copy");
+            return;
+        }
+
+        let view = CodeView::for_selection(
+            selection,
+            source.text(),
+            &self.text_drawer,
+            &self.blocks,
+            blocks.then_some(blocks_theme),
+            font.tab_width,
+        );
+        match format {
+            CopyFormat::Html => self
+                .clipboard
+                .copy_html(&to_html(&view, &font.family), &view.text()),
+            CopyFormat::Image => {
+                let image = to_image(&view, &ExportFont::monospace(font.id.size), IMAGE_SCALE);
+                self.clipboard.copy_image(&image);
+            }
+        }
+    }
+}
@@ -3,8 +3,15 @@ use tree_sitter::TreeCursor;
 
 use super::Selections;
 use crate::block_editor::{
-    blocks::Padding, rope_ext::RopeExt, source::Source,
-    text_editor::coord_conversions::pt_to_text_coord, text_range::movement::TextMovement,
+    blocks::{BlockTrees, Padding},
+    rope_ext::{RopeExt, RopeSliceExt},
+    soft_wrap::SoftWrap,
+    source::Source,
+    text_editor::coord_conversions::pt_to_text_coord,
+    text_range::{
+        movement::{BlockMovement, TextMovement, VDir},
+        TextPoint,
+    },
     MonospaceFont, TextRange,
 };
 
@@ -12,6 +19,9 @@ impl Selections {
     pub fn set_selection(&mut self, new_selection: TextRange, source: &Source) {
         self.selection = new_selection;
 
+        // any other change to the selection ends a chain of structural expansions
+        self.expansion_stack.clear();
+
         // find pseudo selection for new position
         self.find_pseudo_selection(source);
 
@@ -91,6 +101,97 @@ impl Selections {
         self.set_selection_user(new_sel, source);
     }
 
+    pub fn move_cursor_by_block(
+        &mut self,
+        movement: BlockMovement,
+        blocks: &BlockTrees,
+        source: &mut Source,
+    ) {
+        let new_cursor = self
+            .selection
+            .find_block_movement_result(movement, blocks, source.text());
+        self.set_selection_user(TextRange::new_cursor(new_cursor), source);
+    }
+
+    pub fn move_selecting_by_block(
+        &mut self,
+        movement: BlockMovement,
+        blocks: &BlockTrees,
+        source: &mut Source,
+    ) {
+        let new_sel = self
+            .selection
+            .expanded_by_block(movement, blocks, source.text());
+        self.set_selection_user(new_sel, source);
+    }
+
+    pub fn move_cursor_by_row(
+        &mut self,
+        direction: VDir,
+        soft_wrap: &SoftWrap,
+        source: &mut Source,
+        tab_width: usize,
+    ) {
+        let new_cursor =
+            self.selection
+                .find_row_movement_result(direction, soft_wrap, source.text(), tab_width);
+        self.set_selection_user(TextRange::new_cursor(new_cursor), source);
+    }
+
+    pub fn move_selecting_by_row(
+        &mut self,
+        direction: VDir,
+        soft_wrap: &SoftWrap,
+        source: &mut Source,
+        tab_width: usize,
+    ) {
+        let new_sel =
+            self.selection
+                .expanded_by_row(direction, soft_wrap, source.text(), tab_width);
+        self.set_selection_user(new_sel, source);
+    }
+
+    /* -------------------------- Structural Selection -------------------------- */
+    /// Expand the selection to the smallest named syntax node that is larger than it
+    pub fn expand_structurally(&mut self, source: &mut Source) {
+        let Some(new_sel) = Self::enclosing_node_range(self.selection.ordered(), source) else {
+            return;
+        };
+
+        // setting the selection clears the stack, so hold onto it
+        let mut stack = std::mem::take(&mut self.expansion_stack);
+        stack.push(self.selection);
+        self.set_selection_user(new_sel, source);
+        self.expansion_stack = stack;
+    }
+
+    /// Go back to the selection from before the last structural expansion
+    pub fn shrink_structurally(&mut self, source: &mut Source) {
+        let Some(prev_sel) = self.expansion_stack.pop() else {
+            return;
+        };
+
+        let stack = std::mem::take(&mut self.expansion_stack);
+        self.set_selection_user(prev_sel, source);
+        self.expansion_stack = stack;
+    }
+
+    fn enclosing_node_range(selection: TextRange, source: &Source) -> Option<TextRange> {
+        let text = source.text();
+        let sel_bytes = selection.byte_range_in(text);
+
+        // start at the smallest node that covers the selection,
+        // then go up until it is named and actually grows the selection
+        let root = source.get_tree_cursor().node();
+        let mut node = root.descendant_for_byte_range(sel_bytes.start, sel_bytes.end)?;
+        while !node.is_named() || node.byte_range() == sel_bytes {
+            node = node.parent()?;
+        }
+
+        let char_range = text.byte_to_char(node.start_byte())..text.byte_to_char(node.end_byte());
+        Some(TextRange::from_char_range_in(text, char_range))
+    }
+
     /* ------------------------------ Mouse Clicks ------------------------------ */
     pub fn mouse_clicked(
         &mut self,
@@ -103,6 +204,66 @@ impl Selections {
         self.set_selection_user(TextRange::new_cursor(text_pos), source);
     }
 
+    /// Select the word (or run of whitespace or symbols) under the click
+    pub fn mouse_double_clicked(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let text_pos = pt_to_text_coord(pos, padding, source.text(), font);
+        let line = source.text().line(text_pos.line);
+        let line_len = line.len_chars_no_linebreak();
+        if line_len == 0 {
+            self.set_selection_user(TextRange::new_cursor(text_pos), source);
+            return;
+        }
+
+        // the click position is between characters, so prefer a word on either side of it
+        let mut col = usize::min(text_pos.col, line_len - 1);
+        if col > 0
+            && CharClass::of(line.char(col)) != CharClass::Word
+            && CharClass::of(line.char(col - 1)) == CharClass::Word
+        {
+            col -= 1;
+        }
+
+        let class = CharClass::of(line.char(col));
+        let mut start = col;
+        while start > 0 && CharClass::of(line.char(start - 1)) == class {
+            start -= 1;
+        }
+        let mut end = col + 1;
+        while end < line_len && CharClass::of(line.char(end)) == class {
+            end += 1;
+        }
+
+        let selection = TextRange::new(
+            TextPoint::new(text_pos.line, start),
+            TextPoint::new(text_pos.line, end),
+        );
+        self.set_selection_user(selection, source);
+    }
+
+    /// Select the whole line under the click, including its linebreak
+    pub fn mouse_triple_clicked(
+        &mut self,
+        pos: Pos2,
+        padding: &Padding,
+        source: &mut Source,
+        font: &MonospaceFont,
+    ) {
+        let line = pt_to_text_coord(pos, padding, source.text(), font).line;
+        let text = source.text();
+        let end = if line + 1 < text.len_lines() {
+            TextPoint::new(line + 1, 0)
+        } else {
+            TextPoint::new(line, text.line(line).len_chars_no_linebreak())
+        };
+        self.set_selection_user(TextRange::new(TextPoint::new(line, 0), end), source);
+    }
+
     pub fn expand_selection(
         &mut self,
         pos: Pos2,
@@ -112,6 +273,7 @@ impl Selections {
     ) {
         // set selection end to dragged position
         self.selection.end = pt_to_text_coord(pos, padding, source.text(), font);
+        self.expansion_stack.clear();
 
         // clear pseudo selection if making a selection
         if !self.selection.is_cursor() {
@@ -122,3 +284,149 @@ impl Selections {
         self.last_selection_time = self.frame_start_time;
     }
 }
+
+/// The kinds of characters that a double click groups together
+#[derive(PartialEq)]
+enum CharClass {
+    Word,
+    Whitespace,
+    Symbol,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else if c.is_whitespace() {
+            CharClass::Whitespace
+        } else {
+            CharClass::Symbol
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Vec2;
+    use ropey::Rope;
+
+    use super::*;
+    use crate::{
+        block_editor::{GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD},
+        lang::Language,
+    };
+
+    fn python(code: &str) -> Source {
+        Source::new(Rope::from_str(code), Language::for_file("test.py"))
+    }
+
+    fn range(start: (usize, usize), end: (usize, usize)) -> TextRange {
+        TextRange::new(
+            TextPoint::new(start.0, start.1),
+            TextPoint::new(end.0, end.1),
+        )
+    }
+
+    /// A font with a known character size, and the position of the middle of a character drawn in it
+    fn font_and_pos() -> (MonospaceFont, impl Fn(usize, usize) -> Pos2) {
+        let font = MonospaceFont {
+            size: Vec2::new(10.0, 20.0),
+            ..MonospaceFont::new("test", 14.0)
+        };
+        let pos = |line: usize, col: usize| {
+            Pos2::new(
+                OUTER_PAD + GUTTER_WIDTH + TEXT_L_PAD + col as f32 * 10.0 + 2.0,
+                OUTER_PAD + line as f32 * 20.0 + 10.0,
+            )
+        };
+        (font, pos)
+    }
+
+    #[test]
+    fn test_expand_and_shrink_structurally() {
+        let mut source = python("def f(x):\n    return foo(x, 1)\n");
+        let mut selections = Selections::new();
+        let cursor = range((1, 12), (1, 12));
+        selections.set_selection(cursor, &source);
+
+        // the identifier, then the call, then the return statement
+        selections.expand_structurally(&mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 14)));
+        selections.expand_structurally(&mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 20)));
+        selections.expand_structurally(&mut source);
+        assert_eq!(selections.selection(), range((1, 4), (1, 20)));
+
+        // shrinking retraces the expansions
+        selections.shrink_structurally(&mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 20)));
+        selections.shrink_structurally(&mut source);
+        assert_eq!(selections.selection(), range((1, 11), (1, 14)));
+        selections.shrink_structurally(&mut source);
+        assert_eq!(selections.selection(), cursor);
+
+        // with nothing left to retrace, shrinking does nothing
+        selections.shrink_structurally(&mut source);
+        assert_eq!(selections.selection(), cursor);
+
+        // moving the selection starts over
+        selections.expand_structurally(&mut source);
+        selections.set_selection(range((0, 0), (0, 0)), &source);
+        selections.shrink_structurally(&mut source);
+        assert_eq!(selections.selection(), range((0, 0), (0, 0)));
+    }
+
+    #[test]
+    fn test_enclosing_node_range_skips_same_range() {
+        let source = python("foo(x)\nbar\n");
+
+        // the expression statement around the call covers the same text, so the module is next
+        let call = range((0, 0), (0, 6));
+        assert_eq!(
+            Selections::enclosing_node_range(call, &source),
+            Some(range((0, 0), (2, 0)))
+        );
+
+        // and nothing is larger than the module
+        assert_eq!(
+            Selections::enclosing_node_range(range((0, 0), (2, 0)), &source),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mouse_double_clicked() {
+        let mut source = python("foo_bar  = baz(1)\n");
+        let padding = Padding::for_blocks(&vec![], source.text().len_lines());
+        let (font, pos) = font_and_pos();
+        let mut selections = Selections::new();
+        let mut double_click = |col| {
+            selections.mouse_double_clicked(pos(0, col), &padding, &mut source, &font);
+            selections.selection()
+        };
+
+        // a word, even when clicking just after it
+        assert_eq!(double_click(3), range((0, 0), (0, 7)));
+        assert_eq!(double_click(7), range((0, 0), (0, 7)));
+
+        // a run of whitespace or symbols
+        assert_eq!(double_click(8), range((0, 7), (0, 9)));
+        assert_eq!(double_click(9), range((0, 9), (0, 10)));
+    }
+
+    #[test]
+    fn test_mouse_triple_clicked() {
+        let mut source = python("a = 1\nb = 2");
+        let padding = Padding::for_blocks(&vec![], source.text().len_lines());
+        let (font, pos) = font_and_pos();
+        let mut selections = Selections::new();
+
+        // the line with its linebreak
+        selections.mouse_triple_clicked(pos(0, 2), &padding, &mut source, &font);
+        assert_eq!(selections.selection(), range((0, 0), (1, 0)));
+
+        // the last line has no linebreak
+        selections.mouse_triple_clicked(pos(1, 2), &padding, &mut source, &font);
+        assert_eq!(selections.selection(), range((1, 0), (1, 5)));
+    }
+}
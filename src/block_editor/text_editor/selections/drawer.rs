@@ -6,8 +6,12 @@ use ropey::Rope;
 use super::{Selections, CURSOR_OFF_DURATION, CURSOR_ON_DURATION};
 use crate::{
     block_editor::{
-        blocks::Padding, text_editor::TextPoint, text_range::TextRange, MonospaceFont, OUTER_PAD,
-        TOTAL_TEXT_X_OFFSET,
+        blocks::Padding,
+        text_editor::coord_conversions::{
+            display_col, display_width, split_into_rows, text_coord_to_pt,
+        },
+        text_range::TextRange,
+        MonospaceFont, OUTER_PAD, TOTAL_TEXT_X_OFFSET,
     },
     theme,
 };
@@ -18,16 +22,13 @@ impl Selections {
         &self,
         offset: Vec2,
         padding: &Padding,
+        source: &Rope,
         font: &MonospaceFont,
         ui: &Ui,
     ) -> Rect {
         // we want to draw the cursor where the mouse has last been (selection end)
-        let total_pad: f32 = padding.cumulative(self.selection.end.line);
         let block = Rect::from_min_size(
-            Pos2::new(
-                TOTAL_TEXT_X_OFFSET + (self.selection.end.col as f32) * font.size.x,
-                OUTER_PAD + (self.selection.end.line as f32) * font.size.y + total_pad,
-            ) + offset,
+            text_coord_to_pt(self.selection.end, padding, source, font) + offset,
             Vec2::new(2.0, font.size.y),
         );
 
@@ -36,7 +37,8 @@ impl Selections {
         let time_in_cycle = time_since_last_selection % total_duration;
         let wake_in = if time_in_cycle < CURSOR_ON_DURATION {
             // cursor is visible
-            ui.painter().rect_filled(block, 0.0, theme::CURSOR);
+            ui.painter()
+                .rect_filled(block, 0.0, theme::current().editor.cursor);
             CURSOR_ON_DURATION - time_in_cycle
         } else {
             // cursor is not visible
@@ -57,7 +59,7 @@ impl Selections {
     ) {
         if !self.selection.is_cursor() {
             self.selection.draw_selection_blocks(
-                theme::SELECTION,
+                theme::current().editor.selection,
                 Stroke::NONE,
                 offset,
                 padding,
@@ -78,7 +80,7 @@ impl Selections {
     ) {
         if let Some(selection) = self.pseudo_selection {
             selection.draw_selection_blocks(
-                theme::PSEUDO_SELECTION,
+                theme::current().editor.pseudo_selection,
                 Stroke::NONE,
                 offset,
                 padding,
@@ -108,53 +110,71 @@ impl TextRange {
             // one line per range so the line number is the start of the range
             let line_num = line_range.start.line;
 
-            // find width of selection block in chars
-            let width = line_range.end.col - line_range.start.col
-                + if line_num != selection.end.line { 1 } else { 0 }; // 1 is added to the width to include the newline
-
-            // start the padding through the first line so the selection
-            // block is placed on the text of the first line (instead of the padding above it)
-            let padding_above: f32 =
-                padding.cumulative(max(selection.start.line, line_num.saturating_sub(1)));
-
-            self.draw_selection_block(
-                TextPoint::new(line_num, line_range.start.col),
-                width,
-                padding_above,
-                line_num != selection.start.line,
-                fill,
-                stroke,
-                offset,
-                padding,
-                font,
-                painter,
-            );
+            // draw a block on each of the rows the line is wrapped onto
+            let row_ranges = split_into_rows(line_range, padding);
+            let row_count = row_ranges.len();
+            for (idx, row_range) in row_ranges.into_iter().enumerate() {
+                let is_first_row = idx == 0;
+                let is_last_row = idx == row_count - 1;
+
+                // find where the selection block is in display columns
+                let start_col = display_col(row_range.start, padding, source, font);
+                // 1 is added to the width to include the newline
+                let newline_width = if is_last_row && line_num != selection.end.line {
+                    1
+                } else {
+                    0
+                };
+                let width = display_width(row_range, source, font) + newline_width;
+
+                // start the padding through the first line so the selection
+                // block is placed on the text of the first line (instead of the padding above it)
+                let padding_above: f32 = if is_first_row {
+                    padding.cumulative(max(selection.start.line, line_num.saturating_sub(1)))
+                } else {
+                    padding.cumulative(line_num)
+                };
+
+                // cover the padding between this line and the one above if it is also selected
+                let line_padding = if is_first_row && line_num != selection.start.line {
+                    padding.individual(line_num)
+                } else {
+                    0.0
+                };
+
+                self.draw_selection_block(
+                    padding.row_of(row_range.start),
+                    start_col,
+                    width,
+                    padding_above,
+                    line_padding,
+                    fill,
+                    stroke,
+                    offset,
+                    font,
+                    painter,
+                );
+            }
         }
     }
 
     fn draw_selection_block(
         &self,
-        start: TextPoint,
+        row: usize,
+        col: usize,
         width: usize,
         padding_above: f32,
-        has_block_above: bool,
+        line_padding: f32,
         fill: Color32,
         stroke: Stroke,
         offset: Vec2,
-        padding: &Padding,
         font: &MonospaceFont,
         painter: &Painter,
     ) {
-        let line_padding = if has_block_above {
-            padding.individual(start.line)
-        } else {
-            0.0
-        };
-
         let block = Rect::from_min_size(
             Pos2::new(
-                (start.col as f32 * font.size.x) + TOTAL_TEXT_X_OFFSET,
-                (start.line as f32 * font.size.y) + OUTER_PAD + padding_above,
+                (col as f32 * font.size.x) + TOTAL_TEXT_X_OFFSET,
+                (row as f32 * font.size.y) + OUTER_PAD + padding_above,
             ) + offset,
             Vec2::new(width as f32 * font.size.x, font.size.y + line_padding),
         );
@@ -22,6 +22,9 @@ pub struct Selections {
 
     /// the time of the last selection change (used for cursor blinking)
     last_selection_time: f64,
+
+    /// selections from before each structural expansion, so shrinking retraces them
+    expansion_stack: Vec<TextRange>,
 }
 
 impl Selections {
@@ -31,6 +34,7 @@ impl Selections {
             pseudo_selection: None,
             frame_start_time: 0.0,
             last_selection_time: 0.0,
+            expansion_stack: vec![],
         }
     }
 
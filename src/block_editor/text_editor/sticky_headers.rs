@@ -0,0 +1,192 @@
+use egui::{Align2, CursorIcon, Id, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+
+use super::TextEditor;
+use crate::{
+    block_editor::{
+        blocks::{Block, BlockTrees},
+        text_range::TextPoint,
+        MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TOTAL_TEXT_X_OFFSET,
+    },
+    theme::{self, blocks_theme::BlocksTheme},
+};
+
+/// the most headers pinned at once, so they don't cover the code they are for
+const MAX_STICKY_HEADERS: usize = 5;
+
+/// the width of the border drawn on the left of each pinned header
+const BORDER_WIDTH: f32 = 1.5;
+
+impl TextEditor {
+    /// Pin the header lines of the blocks enclosing the top of the viewport over the text.
+    /// Returns the start of the block whose header was clicked, to move the cursor to.
+    pub(super) fn show_sticky_headers(
+        &self,
+        top_line: usize,
+        offset: Vec2,
+        viewport: Rect,
+        blocks_theme: BlocksTheme,
+        font: &MonospaceFont,
+        ui: &Ui,
+    ) -> Option<TextPoint> {
+        // the row of the top line at the top of the viewport, since wrapped lines take up several
+        let padding = self.blocks.padding();
+        let line_top = OUTER_PAD
+            + padding.cumulative(top_line)
+            + padding.first_row(top_line) as f32 * font.size.y;
+        let row_in_line = ((viewport.min.y - line_top) / font.size.y).max(0.0) as usize;
+        let top_row = padding.first_row(top_line)
+            + row_in_line.min(padding.soft_wrap().row_count(top_line) - 1);
+
+        let headers = sticky_blocks(&self.blocks, top_line, top_row);
+        if headers.is_empty() {
+            return None;
+        }
+
+        let painter = ui.painter();
+        let top_left = viewport.min + offset;
+        let block_x = |block: &Block| {
+            offset.x + OUTER_PAD + GUTTER_WIDTH + block.display_col as f32 * font.size.x
+                - BORDER_WIDTH / 2.0
+        };
+
+        let mut clicked = None;
+        for (row, &(block, level)) in headers.iter().enumerate() {
+            let rect = Rect::from_min_size(
+                top_left + Vec2::new(0.0, row as f32 * font.size.y),
+                Vec2::new(viewport.width(), font.size.y),
+            );
+            painter.rect_filled(rect, 0.0, theme::current().editor.background);
+
+            // tint the header like its block, with the borders of the blocks around it
+            if let Some(color) = (blocks_theme.color_for)(block.syntax_type, level) {
+                let tinted = Rect::from_min_max(Pos2::new(block_x(block), rect.top()), rect.max);
+                painter.rect_filled(tinted, 0.0, color.gamma_multiply(0.15));
+            }
+            for &(outer, outer_level) in &headers[..=row] {
+                if let Some(color) = (blocks_theme.color_for)(outer.syntax_type, outer_level) {
+                    painter.vline(
+                        block_x(outer),
+                        rect.y_range(),
+                        Stroke::new(BORDER_WIDTH, color),
+                    );
+                }
+            }
+
+            painter.text(
+                Pos2::new(offset.x + GUTTER_WIDTH, rect.top()),
+                Align2::RIGHT_TOP,
+                (block.line + 1).to_string(),
+                font.id.clone(),
+                theme::current().editor.line_numbers,
+            );
+            self.text_drawer.draw_line(
+                block.line,
+                Vec2::new(offset.x + TOTAL_TEXT_X_OFFSET, rect.top()),
+                font,
+                painter,
+            );
+
+            let response = ui
+                .interact(rect, Id::new(("sticky_header", row)), Sense::click())
+                .on_hover_cursor(CursorIcon::PointingHand);
+            if response.clicked() {
+                clicked = Some(TextPoint::new(block.line, block.col));
+            }
+        }
+
+        // separate the pinned headers from the scrolling text
+        let bottom = top_left.y + headers.len() as f32 * font.size.y;
+        painter.hline(
+            top_left.x..=top_left.x + viewport.width(),
+            bottom,
+            Stroke::new(1.0_f32, theme::current().editor.line_numbers),
+        );
+
+        clicked
+    }
+}
+
+/// The blocks to pin the headers of when the line is at the top of the viewport (with their levels),
+/// outermost first. A block is pinned once its header has scrolled out of view,
+/// until its last row would be hidden behind the pinned headers.
+/// Rows are counted instead of lines, since soft wrapped lines take up more than one.
+fn sticky_blocks(blocks: &BlockTrees, top_line: usize, top_row: usize) -> Vec<(&Block, usize)> {
+    let padding = blocks.padding();
+    let mut sticky = vec![];
+    for (block, level) in blocks.enclosing(top_line) {
+        if sticky.len() == MAX_STICKY_HEADERS {
+            break;
+        }
+        let rows_below_headers = top_row + sticky.len() + 1;
+        let end_row = padding.first_row(block.line + block.height);
+        if padding.first_row(block.line) < top_row && end_row > rows_below_headers {
+            sticky.push((block, level));
+        }
+    }
+    sticky
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+    use crate::{
+        block_editor::{soft_wrap::SoftWrap, source::Source},
+        lang::Language,
+    };
+
+    #[test]
+    fn test_sticky_blocks() {
+        let text = Rope::from_str(
+            "def f():\n    for x in y:\n        if x:\n            a()\n            b()\n            c()\n            e()\n    d()\n",
+        );
+        let source = Source::new(text.clone(), Language::for_file("test.py"));
+        let blocks =
+            BlockTrees::for_ts_tree(&mut source.get_tree_cursor(), &text, source.lang.config, 4);
+        let lines = |top_line| {
+            sticky_blocks(&blocks, top_line, top_line)
+                .into_iter()
+                .map(|(block, level)| (block.line, level))
+                .collect::<Vec<_>>()
+        };
+
+        // nothing is pinned while the headers are in view
+        assert_eq!(lines(0), vec![]);
+
+        // the function is pinned once its header scrolls off, then the loop
+        assert_eq!(lines(1), vec![(0, 0)]);
+        assert_eq!(lines(2), vec![(0, 0), (1, 1)]);
+        assert_eq!(lines(3), vec![(0, 0), (1, 1), (2, 2)]);
+
+        // a block stops being pinned when it would cover its own last line
+        assert_eq!(lines(4), vec![(0, 0), (1, 1)]);
+        assert_eq!(lines(7), vec![]);
+    }
+
+    #[test]
+    fn test_sticky_blocks_soft_wrapped() {
+        let text = Rope::from_str(
+            "def f():\n    for x in y:\n        a(1, 2, 3, 4, 5, 6, 7, 8)\n        b()\n    d()\n",
+        );
+        let source = Source::new(text.clone(), Language::for_file("test.py"));
+        let mut blocks =
+            BlockTrees::for_ts_tree(&mut source.get_tree_cursor(), &text, source.lang.config, 4);
+        blocks.set_soft_wrap(SoftWrap::new(&text, 20, 4));
+        let padding = blocks.padding();
+        assert_eq!(padding.soft_wrap().row_count(2), 3);
+        let lines = |top_line, top_row| {
+            sticky_blocks(&blocks, top_line, top_row)
+                .into_iter()
+                .map(|(block, level)| (block.line, level))
+                .collect::<Vec<_>>()
+        };
+
+        // the loop's last row is further down than its last line would be, so it stays pinned
+        assert_eq!(lines(2, 2), vec![(0, 0), (1, 1)]);
+        assert_eq!(lines(2, 3), vec![(0, 0), (1, 1)]);
+
+        // until its last row would be behind the headers
+        assert_eq!(lines(2, 4), vec![(0, 0)]);
+    }
+}
@@ -0,0 +1,102 @@
+use egui::{Area, Frame, Id, Key, Modifiers, Order, Pos2, Ui};
+
+use crate::lang::config::{LanguageConfig, Snippet};
+
+/// Menu for surrounding the selection with a block (like an if or loop) or unwrapping the block at the cursor
+pub struct SurroundPopup {
+    open: bool,
+
+    /// index of the highlighted choice, moved with the arrow keys
+    selected: usize,
+}
+
+/// What was picked in the popup
+#[derive(Clone, Copy)]
+pub enum SurroundChoice {
+    Surround(&'static Snippet),
+    Unwrap,
+}
+
+impl SurroundPopup {
+    pub fn new() -> Self {
+        SurroundPopup {
+            open: false,
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The choices for a language: the palette snippets that can surround code, then unwrapping
+    fn choices(lang: &LanguageConfig) -> Vec<SurroundChoice> {
+        lang.palettes
+            .iter()
+            .flat_map(|palette| palette.snippets)
+            .filter(|snippet| snippet.body_line.is_some())
+            .map(SurroundChoice::Surround)
+            .chain(std::iter::once(SurroundChoice::Unwrap))
+            .collect()
+    }
+
+    /// Take the keys for moving through the popup before the editor sees them,
+    /// returning the choice if one was picked with enter
+    pub fn handle_keys(&mut self, ui: &Ui, lang: &LanguageConfig) -> Option<SurroundChoice> {
+        let choices = Self::choices(lang);
+        ui.input_mut(|i| {
+            if i.consume_key(Modifiers::NONE, Key::Escape) {
+                self.close();
+            } else if i.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                self.selected = (self.selected + 1) % choices.len();
+            } else if i.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                self.selected = (self.selected + choices.len() - 1) % choices.len();
+            } else if i.consume_key(Modifiers::NONE, Key::Enter) {
+                self.close();
+                return choices.get(self.selected).copied();
+            }
+            None
+        })
+    }
+
+    /// Show the popup at the position (in screen coordinates), returning the choice if one was clicked
+    pub fn show(&mut self, ui: &Ui, pos: Pos2, lang: &LanguageConfig) -> Option<SurroundChoice> {
+        let choices = Self::choices(lang);
+        let mut picked = None;
+        let response = Area::new(Id::new("surround_popup"))
+            .order(Order::Foreground)
+            .fixed_pos(pos)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label("Surround with");
+                    for (idx, choice) in choices.iter().enumerate() {
+                        if matches!(choice, SurroundChoice::Unwrap) {
+                            ui.separator();
+                        }
+                        let label = match choice {
+                            SurroundChoice::Surround(snippet) => snippet.id,
+                            SurroundChoice::Unwrap => "Unwrap",
+                        };
+                        if ui.selectable_label(idx == self.selected, label).clicked() {
+                            picked = Some(*choice);
+                        }
+                    }
+                });
+            })
+            .response;
+
+        if picked.is_some() || response.clicked_elsewhere() {
+            self.close();
+        }
+        picked
+    }
+}
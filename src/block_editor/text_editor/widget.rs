@@ -5,17 +5,23 @@ use egui::{
 use std::{collections::HashSet, ops::RangeInclusive};
 
 use super::{
+    block_commands::BlockCommand,
+    block_dragging::DropTarget,
     coord_conversions::{pt_to_unbounded_text_coord, text_coord_to_pt},
     gutter::Gutter,
-    TextEdit, TextEditor, TextPoint,
+    local_variables::rename_problem,
+    TextEdit, TextEditor,
 };
 use crate::{
     block_editor::{
         blocks::BlockTrees,
+        export::CopyFormat,
+        rope_ext::RopeSliceExt,
         search::SearchResults,
+        soft_wrap::SoftWrap,
         source::{Source, UndoStopCondition},
         text_range::{
-            movement::{HDir, HUnit, TextMovement, VDir, VUnit},
+            movement::{BlockMovement, HDir, HUnit, TextMovement, VDir, VUnit},
             TextRange,
         },
         DragSession, ExternalCommand, MonospaceFont, GUTTER_WIDTH, OUTER_PAD, TEXT_L_PAD,
@@ -24,6 +30,9 @@ use crate::{
     theme::{self, blocks_theme::BlocksTheme},
 };
 
+/// extra space to the right of the text for the edges of nested blocks
+const WRAP_R_PAD: f32 = 40.0;
+
 const EVENT_FILTER: EventFilter = EventFilter {
     horizontal_arrows: true,
     vertical_arrows: true,
@@ -42,15 +51,21 @@ impl TextEditor {
         font: &'a MonospaceFont,
     ) -> impl Widget + 'a {
         move |ui: &mut Ui| -> egui::Response {
-            ScrollArea::both()
+            // wrapped lines always fit, so only scroll vertically
+            let mut scroll_area = ScrollArea::new([!self.soft_wrap, true])
                 .auto_shrink([false; 2])
                 .scroll_bar_visibility(ScrollBarVisibility::VisibleWhenNeeded)
                 .id_salt("text_editor_scroll")
-                .drag_to_scroll(false)
+                .drag_to_scroll(false);
+            if let Some(scroll) = self.minimap.take_scroll_request() {
+                scroll_area = scroll_area.vertical_scroll_offset(scroll);
+            }
+            scroll_area
                 .show_viewport(ui, |ui, viewport| {
                     // allocate space
                     let content_size = self.content_size(source, viewport, font);
                     let expanded_size = content_size.max(ui.available_size() - Vec2::new(0.0, 5.0));
+                    self.minimap.set_view(viewport, expanded_size.y);
                     let (auto_id, rect) = ui.allocate_space(expanded_size);
 
                     // setup interactivity
@@ -68,11 +83,38 @@ impl TextEditor {
                     let drop_point =
                         self.handle_pointer(ui, offset, &mut response, drag_block, source, font);
                     self.handle_external_commands(external_commands, source);
+                    if std::mem::take(&mut self.focus_requested) {
+                        response.request_focus();
+                    }
                     if response.has_focus() {
-                        self.handle_input_events(source, ui);
+                        // escape without a popup open gives up focus, so tab can reach the palette
+                        let release_focus = !self.completion_popup.has_completions()
+                            && ui.input(|i| i.key_pressed(Key::Escape));
+
+                        if self.surround_popup.is_open() {
+                            if let Some(choice) =
+                                self.surround_popup.handle_keys(ui, source.lang.config)
+                            {
+                                self.run_surround_choice(choice, source);
+                            }
+                        }
+                        self.handle_input_events(source, ui, font);
+
+                        if release_focus {
+                            response.surrender_focus();
+                        }
                     }
-                    self.update_text_if_needed(source, &mut response);
+                    self.handle_rich_copy(external_commands, source, blocks_theme, font);
+                    self.update_text_if_needed(source, &mut response, viewport, font);
+                    self.update_local_highlights(source);
                     // TODO: if the selection moved out of view, scroll to it
+                    if std::mem::take(&mut self.scroll_to_cursor_requested) {
+                        let cursor = self.selections.selection().end;
+                        let pos =
+                            text_coord_to_pt(cursor, self.blocks.padding(), source.text(), font);
+                        let rect = Rect::from_min_size(pos + offset, Vec2::new(1.0, font.size.y));
+                        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                    }
 
                     // set the selection to the current find result if it closed
                     if let Some(results) = search_results {
@@ -86,7 +128,12 @@ impl TextEditor {
                     if let Some(results) = search_results {
                         if results.check_and_clear_scroll_to_current() {
                             let current = results.current();
-                            let pos = text_coord_to_pt(current.start, self.blocks.padding(), font);
+                            let pos = text_coord_to_pt(
+                                current.start,
+                                self.blocks.padding(),
+                                source.text(),
+                                font,
+                            );
                             let rect = Rect::from_min_size(
                                 pos + offset,
                                 Vec2::new((current.end.col - current.start.col) as f32, 1.0)
@@ -97,10 +144,11 @@ impl TextEditor {
                     }
 
                     // draw the text editor
+                    let visible_lines = self.visible_lines(viewport, font);
                     let cursor_rect = self.draw(
                         offset,
                         content_size.x,
-                        viewport,
+                        visible_lines.clone(),
                         response.has_focus(),
                         drop_point,
                         source,
@@ -128,6 +176,23 @@ impl TextEditor {
                         );
                     }
 
+                    // pin the headers of the blocks the top of the view is in
+                    if self.sticky_scroll {
+                        if let Some(point) = self.show_sticky_headers(
+                            *visible_lines.start(),
+                            offset,
+                            viewport,
+                            blocks_theme,
+                            font,
+                            ui,
+                        ) {
+                            self.select_and_reveal(TextRange::new_cursor(point), source);
+                        }
+                    }
+
+                    // describe the text and cursor to screen readers
+                    self.update_accessibility(&response, offset, source, font, ui);
+
                     // draw completion popup
                     // TODO: use the cursor rect from the draw function as the origin
                     if self.completion_popup.has_completions() {
@@ -137,6 +202,7 @@ impl TextEditor {
                                 self.completion_popup.calc_origin(
                                     self.selections.selection().start,
                                     self.blocks.padding(),
+                                    source.text(),
                                     font,
                                 ) + offset,
                                 self.completion_popup.calc_size(font),
@@ -156,6 +222,41 @@ impl TextEditor {
                         }
                     }
 
+                    // draw surround popup below the cursor
+                    if self.surround_popup.is_open() {
+                        let pos = text_coord_to_pt(
+                            self.selections.selection().end,
+                            self.blocks.padding(),
+                            source.text(),
+                            font,
+                        ) + offset
+                            + Vec2::new(0.0, font.size.y);
+                        if let Some(choice) = self.surround_popup.show(ui, pos, source.lang.config)
+                        {
+                            self.run_surround_choice(choice, source);
+                        }
+                    }
+
+                    // draw rename popup below the variable at the cursor
+                    if self.rename_popup.is_open() {
+                        let pos = text_coord_to_pt(
+                            self.selections.selection().end,
+                            self.blocks.padding(),
+                            source.text(),
+                            font,
+                        ) + offset
+                            + Vec2::new(0.0, font.size.y);
+                        let cursor = self.selections.selection().end;
+                        let new_name = self
+                            .rename_popup
+                            .show(ui, pos, |name| rename_problem(name, cursor, source));
+                        if let Some(new_name) = new_name {
+                            self.rename_local(&new_name, source);
+                        } else if !self.rename_popup.is_open() {
+                            self.focus_requested = true;
+                        }
+                    }
+
                     // draw diagnostic popup
                     if let Some(diagnostic_selection) = self.diagnostic_selection {
                         let diagnostic = &self.diagnostics[diagnostic_selection];
@@ -165,6 +266,7 @@ impl TextEditor {
                                     diagnostic,
                                     offset,
                                     self.blocks.padding(),
+                                    source.text(),
                                     font,
                                 ),
                                 self.diagnostic_popup.calc_size(diagnostic, font),
@@ -180,6 +282,7 @@ impl TextEditor {
                             documentation,
                             offset,
                             self.blocks.padding(),
+                            source.text(),
                             font,
                         );
                         let line_count = documentation.message.lines().count();
@@ -226,9 +329,9 @@ impl TextEditor {
         &self,
         offset: Vec2,
         content_width: f32,
-        viewport: Rect,
+        visible_lines: RangeInclusive<usize>,
         has_focus: bool,
-        block_drop_point: Option<TextPoint>,
+        block_drop_target: Option<DropTarget>,
         source: &Source,
         search_results: &Option<SearchResults>,
         blocks_theme: BlocksTheme,
@@ -237,13 +340,18 @@ impl TextEditor {
     ) -> Option<Rect> {
         // draw background
         let painter = ui.painter();
-        painter.rect_filled(painter.clip_rect(), 0.0, theme::BACKGROUND);
+        painter.rect_filled(painter.clip_rect(), 0.0, theme::current().editor.background);
 
         // draw search results
         if let Some(search_results) = search_results {
             search_results.draw(offset, self.blocks.padding(), source.text(), font, painter);
         }
 
+        // draw references to the variable at the cursor
+        if has_focus {
+            self.draw_local_highlights(offset, source, font, painter);
+        }
+
         // draw selection under text and blocks
         self.selections.draw_pseudo_selection(
             offset,
@@ -255,9 +363,6 @@ impl TextEditor {
         self.selections
             .draw_selection(offset, self.blocks.padding(), source.text(), font, painter);
 
-        // find which lines are visible in the viewport
-        let visible_lines = self.visible_lines(viewport, font);
-
         // draw text and blocks
         let block_padding = Vec2::new(OUTER_PAD + GUTTER_WIDTH, OUTER_PAD);
         let block_offset = block_padding + offset;
@@ -280,8 +385,8 @@ impl TextEditor {
         );
 
         // draw drag & drop insertion line
-        if let Some(drop_point) = block_drop_point {
-            self.draw_dropping_line(drop_point, content_width, offset, font, painter);
+        if let Some(drop_target) = block_drop_target {
+            self.draw_dropping_line(drop_target, content_width, offset, font, painter);
         }
 
         // draw diagnostic underlines
@@ -292,16 +397,25 @@ impl TextEditor {
 
         // draw cursor
         if has_focus {
-            Some(
-                self.selections
-                    .draw_cursor(offset, self.blocks.padding(), font, ui),
-            )
+            Some(self.selections.draw_cursor(
+                offset,
+                self.blocks.padding(),
+                source.text(),
+                font,
+                ui,
+            ))
         } else {
             None
         }
     }
 
-    fn update_text_if_needed(&mut self, source: &mut Source, response: &mut Response) {
+    fn update_text_if_needed(
+        &mut self,
+        source: &mut Source,
+        response: &mut Response,
+        viewport: Rect,
+        font: &MonospaceFont,
+    ) {
         if source.has_text_changed_since_last_check() {
             // mark response and changed
             response.mark_changed();
@@ -309,12 +423,32 @@ impl TextEditor {
             // get blocks
             {
                 let mut cursor = source.get_tree_cursor();
-                self.blocks =
-                    BlockTrees::for_ts_tree(&mut cursor, source.text(), source.lang.config);
+                self.blocks = BlockTrees::for_ts_tree(
+                    &mut cursor,
+                    source.text(),
+                    source.lang.config,
+                    font.tab_width,
+                );
             }
 
             // highlight text
             self.text_drawer.highlight_source(source);
+            self.local_highlights.clear();
+            self.minimap.clear();
+        }
+
+        // rewrap when the text or the space for it changes
+        let wrap_cols = self.soft_wrap.then(|| {
+            let width = viewport.width() - TOTAL_TEXT_X_OFFSET - OUTER_PAD - WRAP_R_PAD;
+            (width / font.size.x).floor().max(1.0) as usize
+        });
+        if self.blocks.padding().soft_wrap().max_cols() != wrap_cols {
+            let soft_wrap = match wrap_cols {
+                Some(cols) => SoftWrap::new(source.text(), cols, font.tab_width),
+                None => SoftWrap::none(),
+            };
+            self.blocks.set_soft_wrap(soft_wrap);
+            self.minimap.clear();
         }
     }
 
@@ -331,8 +465,12 @@ impl TextEditor {
                 top_line = line_num;
             }
 
+            // the bottom of the last row of the line
+            let padding = self.blocks.padding();
+            let rows_through =
+                padding.first_row(line_num) + padding.soft_wrap().row_count(line_num);
             current_height =
-                OUTER_PAD + line_cumulative_padding + (font.size.y * (line_num + 1) as f32);
+                OUTER_PAD + line_cumulative_padding + (font.size.y * rows_through as f32);
 
             if current_height > viewport.max.y {
                 bottom_line = Some(line_num);
@@ -356,7 +494,7 @@ impl TextEditor {
         dragged_block: &mut Option<DragSession>,
         source: &mut Source,
         font: &MonospaceFont,
-    ) -> Option<TextPoint> {
+    ) -> Option<DropTarget> {
         let mods = ui.input(|i| i.modifiers);
 
         if response.hovered() {
@@ -384,11 +522,13 @@ impl TextEditor {
                             .expand_selection(pos, self.blocks.padding(), source, font);
                     } else {
                         // if option is held, remove the current block from the source and place it in drag_block
+                        // (or with command held too, copy it there)
                         if mods.alt {
                             if dragged_block.is_none() {
-                                self.start_block_drag(pos, dragged_block, source, font);
+                                let copy = mods.command;
+                                self.start_block_drag(pos, copy, dragged_block, source, font);
                             }
-                        } else {
+                        } else if !self.select_block_with_border_at(pos, source, font) {
                             self.selections
                                 .mouse_clicked(pos, self.blocks.padding(), source, font);
                         }
@@ -397,12 +537,37 @@ impl TextEditor {
                     response.request_focus();
                 }
 
+                // handle double and triple clicks (which are reported on release)
+                if !mods.shift && !mods.alt && dragged_block.is_none() {
+                    let triple_clicked = response.triple_clicked();
+                    if (triple_clicked || response.double_clicked())
+                        && !self.select_block_with_border_at(pos, source, font)
+                    {
+                        if triple_clicked {
+                            self.selections.mouse_triple_clicked(
+                                pos,
+                                self.blocks.padding(),
+                                source,
+                                font,
+                            );
+                        } else {
+                            self.selections.mouse_double_clicked(
+                                pos,
+                                self.blocks.padding(),
+                                source,
+                                font,
+                            );
+                        }
+                    }
+                }
+
                 // handle mouse up
-                if dragged_block.is_some() {
+                if let Some(drag) = dragged_block {
                     let mouse_released = ui.input(|i| i.pointer.primary_released());
                     if mouse_released {
-                        let drop_point = self.find_drop_point(pos, source, font);
-                        self.drop_block(dragged_block, drop_point, source);
+                        // holding shift drops the block even where it wouldn't parse
+                        let target = self.find_drop_target(pos, drag, source, font);
+                        self.drop_block_if_valid(dragged_block, target, mods.shift, source);
                         response.request_focus();
                     }
                 }
@@ -421,6 +586,7 @@ impl TextEditor {
                         let coord = pt_to_unbounded_text_coord(
                             pointer_pos - offset,
                             self.blocks.padding(),
+                            source.text(),
                             font,
                         );
                         if !diagnostic.range.contains(coord, source.text()) {
@@ -432,6 +598,7 @@ impl TextEditor {
                     let coord = pt_to_unbounded_text_coord(
                         pointer_pos - offset,
                         self.blocks.padding(),
+                        source.text(),
                         font,
                     );
                     if !self.documentation.range.contains(coord, source.text()) {
@@ -445,6 +612,7 @@ impl TextEditor {
                     let coord = pt_to_unbounded_text_coord(
                         pointer_pos - offset,
                         self.blocks.padding(),
+                        source.text(),
                         font,
                     );
                     self.diagnostic_selection = self
@@ -456,6 +624,7 @@ impl TextEditor {
                     let coord = pt_to_unbounded_text_coord(
                         pointer_pos - offset,
                         self.blocks.padding(),
+                        source.text(),
                         font,
                     );
                     if coord.line < source.text().len_lines() - 1
@@ -468,15 +637,25 @@ impl TextEditor {
         };
 
         // figure out the block drop points
-        if response.contains_pointer() && dragged_block.is_some() {
-            ui.input(|i| i.pointer.latest_pos())
-                .map(|pointer_pos| self.find_drop_point(pointer_pos - offset, source, font))
-        } else {
-            None
+        let drag = dragged_block
+            .as_ref()
+            .filter(|_| response.contains_pointer())?;
+        let pointer_pos = ui.input(|i| i.pointer.latest_pos())?;
+        let target = self.find_drop_target(pointer_pos - offset, drag, source, font);
+        if !target.valid {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                response.id.with("invalid_drop"),
+                |ui| {
+                    ui.label("The code wouldn't parse with the block here.\nHold shift to drop it anyway.")
+                },
+            );
         }
+        Some(target)
     }
 
-    fn handle_input_events(&mut self, source: &mut Source, ui: &Ui) {
+    fn handle_input_events(&mut self, source: &mut Source, ui: &Ui, font: &MonospaceFont) {
         let mut events = ui.input(|i| i.filtered_events(&EVENT_FILTER));
 
         if self.ime_enabled {
@@ -488,6 +667,17 @@ impl TextEditor {
         for event in &events {
             match event {
                 Event::Copy => {
+                    // with alt held, copy with colors and blocks instead (and with shift, as an image)
+                    let modifiers = ui.input(|i| i.modifiers);
+                    if modifiers.alt {
+                        self.rich_copy_requested = Some(if modifiers.shift {
+                            CopyFormat::Image
+                        } else {
+                            CopyFormat::Html
+                        });
+                        continue;
+                    }
+
                     let char_range = self
                         .selections
                         .selection()
@@ -510,7 +700,7 @@ impl TextEditor {
                 }
 
                 Event::Paste(new_text) => {
-                    source.insert_str(new_text, &mut self.selections);
+                    source.paste(new_text, &mut self.selections);
                 }
 
                 Event::Text(new_text) => {
@@ -525,7 +715,10 @@ impl TextEditor {
                     pressed: true,
                     ..
                 } => {
-                    if !self.handle_selection_modifying_keypress(modifiers, *key, source) {
+                    if !self.handle_block_command_keypress(modifiers, *key, source)
+                        && !self.handle_local_variable_keypress(modifiers, *key, source)
+                        && !self.handle_selection_modifying_keypress(modifiers, *key, source, font)
+                    {
                         self.handle_text_modifying_keypress(modifiers, *key, source);
                     }
                 }
@@ -582,6 +775,35 @@ impl TextEditor {
                     source.redo(&mut self.selections);
                     self.completion_popup.clear();
                 }
+                ExternalCommand::Reindent => {
+                    source.reindent(&mut self.selections);
+                    self.completion_popup.clear();
+                }
+                ExternalCommand::ConvertIndentation { use_tabs } => {
+                    source.convert_indentation(*use_tabs, &mut self.selections);
+                    self.completion_popup.clear();
+                }
+                ExternalCommand::SetSoftWrap(enabled) => {
+                    self.soft_wrap = *enabled;
+                }
+                ExternalCommand::SetStickyScroll(enabled) => {
+                    self.sticky_scroll = *enabled;
+                }
+                ExternalCommand::SurroundWith => {
+                    self.surround_popup.open();
+                    self.focus_requested = true;
+                }
+                ExternalCommand::GoToLocalDefinition => {
+                    self.go_to_local_definition(source);
+                    self.focus_requested = true;
+                }
+                ExternalCommand::RenameLocal => {
+                    self.open_rename_popup(source);
+                }
+                ExternalCommand::UnwrapBlock => {
+                    self.run_block_command(BlockCommand::Unwrap, source);
+                    self.completion_popup.clear();
+                }
                 _ => {}
             }
         }
@@ -676,6 +898,12 @@ impl TextEditor {
                 self.completion_popup.clear();
             }
 
+            // Reindent
+            Key::I if modifiers.command && modifiers.shift => {
+                source.reindent(&mut self.selections);
+                self.completion_popup.clear();
+            }
+
             // Control hotkeys
             Key::H if modifiers.ctrl => {
                 source.delete(
@@ -752,6 +980,7 @@ impl TextEditor {
         modifiers: &Modifiers,
         key: Key,
         source: &mut Source,
+        font: &MonospaceFont,
     ) -> bool {
         match key {
             Key::A if modifiers.command => {
@@ -766,6 +995,43 @@ impl TextEditor {
                 true
             }
 
+            // block navigation
+            Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight
+                if modifiers.alt && modifiers.ctrl =>
+            {
+                let movement = match key {
+                    Key::ArrowUp => BlockMovement::PrevSibling,
+                    Key::ArrowDown => BlockMovement::NextSibling,
+                    Key::ArrowLeft => BlockMovement::Parent,
+                    Key::ArrowRight => BlockMovement::FirstChild,
+                    _ => unreachable!(),
+                };
+
+                if modifiers.shift {
+                    self.selections
+                        .move_selecting_by_block(movement, &self.blocks, source);
+                } else {
+                    self.selections
+                        .move_cursor_by_block(movement, &self.blocks, source);
+                }
+
+                self.completion_popup.clear();
+
+                true
+            }
+
+            // structural selection
+            Key::ArrowUp if modifiers.alt && modifiers.shift => {
+                self.selections.expand_structurally(source);
+                self.completion_popup.clear();
+                true
+            }
+            Key::ArrowDown if modifiers.alt && modifiers.shift => {
+                self.selections.shrink_structurally(source);
+                self.completion_popup.clear();
+                true
+            }
+
             Key::ArrowLeft | Key::ArrowRight => {
                 let direction = match key {
                     Key::ArrowLeft => HDir::Left,
@@ -817,8 +1083,26 @@ impl TextEditor {
                     };
 
                     let movement = TextMovement::vertical(unit, direction);
-
-                    if modifiers.shift {
+                    let soft_wrap = self.blocks.padding().soft_wrap();
+
+                    // move by the rows that lines are wrapped onto
+                    if unit == VUnit::Line && soft_wrap.is_enabled() {
+                        if modifiers.shift {
+                            self.selections.move_selecting_by_row(
+                                direction,
+                                soft_wrap,
+                                source,
+                                font.tab_width,
+                            );
+                        } else {
+                            self.selections.move_cursor_by_row(
+                                direction,
+                                soft_wrap,
+                                source,
+                                font.tab_width,
+                            );
+                        }
+                    } else if modifiers.shift {
                         self.selections.move_selecting(movement, source);
                     } else {
                         self.selections.move_cursor(movement, source);
@@ -845,18 +1129,23 @@ impl TextEditor {
         let max_chars = source
             .text()
             .lines()
-            .map(|l| l.len_chars())
+            .map(|l| l.display_col(l.len_chars(), font.tab_width))
             .max()
             .unwrap_or(0);
-        let max_line_len =
-            max_chars as f32 * font.size.x + (OUTER_PAD * 2.0) + GUTTER_WIDTH + TEXT_L_PAD + 40.0; // extra space for nesting blocks
-        let width = f32::max(viewport.width(), max_line_len);
-
-        // height is just height of text
-        let height = source.text().len_lines() as f32 * font.size.y
-            + OUTER_PAD
-            + self.blocks.padding().total()
-            + 200.0; // extra space for over-scroll
+        let max_line_len = max_chars as f32 * font.size.x
+            + (OUTER_PAD * 2.0)
+            + GUTTER_WIDTH
+            + TEXT_L_PAD
+            + WRAP_R_PAD;
+        let width = if self.soft_wrap {
+            viewport.width()
+        } else {
+            f32::max(viewport.width(), max_line_len)
+        };
+
+        // height is just height of text (including the rows of wrapped lines)
+        let rows = self.blocks.padding().first_row(source.text().len_lines());
+        let height = rows as f32 * font.size.y + OUTER_PAD + self.blocks.padding().total() + 200.0; // extra space for over-scroll
 
         Vec2::new(width, height)
     }
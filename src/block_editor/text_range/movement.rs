@@ -1,6 +1,10 @@
 use ropey::Rope;
 
-use crate::block_editor::rope_ext::{RopeExt, RopeSliceExt};
+use crate::block_editor::{
+    blocks::{Block, BlockTrees},
+    rope_ext::{RopeExt, RopeSliceExt},
+    soft_wrap::SoftWrap,
+};
 
 use super::{TextPoint, TextRange};
 
@@ -78,6 +82,23 @@ pub enum VDir {
     Down,
 }
 
+/// A movement between blocks. Unlike a `TextMovement`, it needs the block trees to be resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockMovement {
+    /// The start of the next block at the same level as the one around the cursor
+    NextSibling,
+
+    /// The start of the previous block at the same level as the one around the cursor
+    PrevSibling,
+
+    /// The header of the block around the cursor,
+    /// or the header of its parent if the cursor is already on the header line
+    Parent,
+
+    /// The start of the first block inside the block around the cursor
+    FirstChild,
+}
+
 impl TextRange {
     pub fn expanded_by(&self, movement: TextMovement, source: &Rope) -> TextRange {
         let new_cursor = self.find_movement_result(movement, source, true);
@@ -173,7 +194,13 @@ impl TextRange {
                     self.start
                 }
             } else {
-                TextPoint::new(cursor_pos.line, cursor_pos.col - 1)
+                // skip over the rest of the grapheme cluster
+                let line = source.line(cursor_pos.line);
+                let mut col = cursor_pos.col - 1;
+                while !line.is_cluster_boundary(col) {
+                    col -= 1;
+                }
+                TextPoint::new(cursor_pos.line, col)
             }
         } else {
             // just move cursor to start of selection
@@ -197,7 +224,13 @@ impl TextRange {
                     self.start
                 }
             } else {
-                TextPoint::new(cursor_pos.line, cursor_pos.col + 1)
+                // skip over the rest of the grapheme cluster
+                let line = source.line(cursor_pos.line);
+                let mut col = cursor_pos.col + 1;
+                while !line.is_cluster_boundary(col) {
+                    col += 1;
+                }
+                TextPoint::new(cursor_pos.line, col)
             }
         } else {
             // just move cursor to end of selection
@@ -314,3 +347,210 @@ impl TextRange {
         TextPoint::new(last_line, last_line_len)
     }
 }
+
+/* ---------------------------- Block Movements ----------------------------- */
+impl TextRange {
+    pub fn expanded_by_block(
+        &self,
+        movement: BlockMovement,
+        blocks: &BlockTrees,
+        source: &Rope,
+    ) -> TextRange {
+        let new_cursor = self.find_block_movement_result(movement, blocks, source);
+        TextRange::new(self.start, new_cursor)
+    }
+
+    /// Find where the movement goes, or stay in place if there is no block to move to
+    pub fn find_block_movement_result(
+        &self,
+        movement: BlockMovement,
+        blocks: &BlockTrees,
+        source: &Rope,
+    ) -> TextPoint {
+        let cursor_pos = self.end;
+
+        // treat the cursor being in the indent as being at the start of the line
+        // so that it is considered inside the block on that line
+        let indent = source.line(cursor_pos.line).whitespace_at_start();
+        let point = TextPoint::new(cursor_pos.line, usize::max(cursor_pos.col, indent));
+
+        let path: Vec<&Block> = blocks
+            .path_to(point, source)
+            .into_iter()
+            .map(|(level, idx)| &level[idx])
+            .collect();
+        let current = path.last().copied();
+        let parent = path.len().checked_sub(2).map(|idx| path[idx]);
+
+        // the lines to move away from
+        let (start, end) = current.map_or((point.line, point.line + 1), |block| {
+            (block.line, block.line + block.height)
+        });
+
+        let target = match movement {
+            BlockMovement::NextSibling => blocks
+                .children_of(parent)
+                .into_iter()
+                .find(|block| block.line >= end),
+            BlockMovement::PrevSibling => blocks
+                .children_of(parent)
+                .into_iter()
+                .rev()
+                .find(|block| block.line + block.height <= start),
+            BlockMovement::Parent => match current {
+                Some(block) if block.line == point.line => parent,
+                _ => current,
+            },
+            BlockMovement::FirstChild => {
+                current.and_then(|block| blocks.children_of(Some(block)).first().copied())
+            }
+        };
+
+        match target {
+            Some(block) => {
+                TextPoint::new(block.line, source.line(block.line).whitespace_at_start())
+            }
+            None => cursor_pos,
+        }
+    }
+
+    pub fn expanded_by_row(
+        &self,
+        direction: VDir,
+        soft_wrap: &SoftWrap,
+        source: &Rope,
+        tab_width: usize,
+    ) -> TextRange {
+        let new_cursor = self.find_row_movement_result(direction, soft_wrap, source, tab_width);
+        TextRange::new(self.start, new_cursor)
+    }
+
+    /// Find where moving up or down by a visual row goes when lines are soft wrapped.
+    /// The cursor keeps its column within the row, as far as the row it moves to allows.
+    pub fn find_row_movement_result(
+        &self,
+        direction: VDir,
+        soft_wrap: &SoftWrap,
+        source: &Rope,
+        tab_width: usize,
+    ) -> TextPoint {
+        // like moving by line, use the top or bottom of the selection
+        let cursor_pos = match direction {
+            VDir::Up => self.ordered().start,
+            VDir::Down => self.ordered().end,
+        };
+        let row = soft_wrap.row_in_line(cursor_pos);
+        let visual_col = soft_wrap.visual_col(cursor_pos, source, tab_width);
+
+        let (line, row) = match direction {
+            VDir::Up if row > 0 => (cursor_pos.line, row - 1),
+            VDir::Up if cursor_pos.line == 0 => return TextPoint::ZERO,
+            VDir::Up => (
+                cursor_pos.line - 1,
+                soft_wrap.row_count(cursor_pos.line - 1) - 1,
+            ),
+            VDir::Down if row + 1 < soft_wrap.row_count(cursor_pos.line) => {
+                (cursor_pos.line, row + 1)
+            }
+            VDir::Down if cursor_pos.line == source.len_lines() - 1 => {
+                // if on the last row, just move to the end of the line
+                let last_line = source.len_lines() - 1;
+                return TextPoint::new(last_line, source.line(last_line).len_chars());
+            }
+            VDir::Down => (cursor_pos.line + 1, 0),
+        };
+
+        let point = soft_wrap.point_at(line, row, visual_col, source, tab_width);
+        TextPoint::new(line, source.clamp_col(line, point.col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_editor::source::Source, lang::Language};
+
+    const CODE: &str =
+        "def a():\n    x = 1\n    if x:\n        y = 2\n    return x\n\ndef b():\n    pass\n";
+
+    fn blocks_for(code: &str) -> (Rope, BlockTrees) {
+        let source = Source::new(Rope::from_str(code), Language::for_file("test.py"));
+        let blocks = BlockTrees::for_ts_tree(
+            &mut source.get_tree_cursor(),
+            source.text(),
+            source.lang.config,
+            4,
+        );
+        (source.text().clone(), blocks)
+    }
+
+    #[test]
+    fn test_find_block_movement_result() {
+        let (text, blocks) = blocks_for(CODE);
+        let moved = |line, col, movement| {
+            TextRange::new_cursor(TextPoint::new(line, col))
+                .find_block_movement_result(movement, &blocks, &text)
+        };
+
+        // between siblings, to the start of their first line
+        assert_eq!(
+            moved(1, 6, BlockMovement::NextSibling),
+            TextPoint::new(2, 4)
+        );
+        assert_eq!(
+            moved(4, 6, BlockMovement::PrevSibling),
+            TextPoint::new(2, 4)
+        );
+        assert_eq!(
+            moved(0, 2, BlockMovement::NextSibling),
+            TextPoint::new(6, 0)
+        );
+
+        // the first and last siblings stay in place
+        assert_eq!(
+            moved(1, 6, BlockMovement::PrevSibling),
+            TextPoint::new(1, 6)
+        );
+        assert_eq!(
+            moved(4, 6, BlockMovement::NextSibling),
+            TextPoint::new(4, 6)
+        );
+        assert_eq!(
+            moved(7, 6, BlockMovement::NextSibling),
+            TextPoint::new(7, 6)
+        );
+
+        // to the header of the block around the cursor, then to its parent's
+        assert_eq!(moved(3, 10, BlockMovement::Parent), TextPoint::new(2, 4));
+        assert_eq!(moved(2, 6, BlockMovement::Parent), TextPoint::new(0, 0));
+
+        // into the first block inside, if there is one
+        assert_eq!(moved(2, 6, BlockMovement::FirstChild), TextPoint::new(3, 8));
+        assert_eq!(
+            moved(3, 10, BlockMovement::FirstChild),
+            TextPoint::new(3, 10)
+        );
+
+        // the cursor in the indent is on the line's block
+        assert_eq!(
+            moved(4, 0, BlockMovement::PrevSibling),
+            TextPoint::new(2, 4)
+        );
+    }
+
+    #[test]
+    fn test_expanded_by_block() {
+        let (text, blocks) = blocks_for(CODE);
+        let selection = TextRange::new(TextPoint::new(1, 4), TextPoint::new(1, 6));
+
+        // the selection keeps its start and moves its end
+        assert_eq!(
+            selection.expanded_by_block(BlockMovement::NextSibling, &blocks, &text),
+            TextRange::new(TextPoint::new(1, 4), TextPoint::new(2, 4))
+        );
+        assert_eq!(
+            selection.expanded_by_block(BlockMovement::PrevSibling, &blocks, &text),
+            selection
+        );
+    }
+}
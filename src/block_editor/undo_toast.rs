@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use egui::{Align2, Area, Context, Frame, Id, Vec2};
+
+use super::{source::Source, text_editor::TextEditor};
+
+/// A message at the bottom of the editor offering to undo what was just done (like deleting a block)
+pub struct UndoToast {
+    message: String,
+
+    /// the time the toast was first shown at
+    shown_at: Option<f64>,
+
+    /// the version of the syntax tree right after the action, so the toast can close
+    /// once anything else changes the text (and undo would undo that instead)
+    tree_version: usize,
+}
+
+/// What to do with the toast after it is shown
+pub enum ToastResult {
+    Keep,
+    Undo,
+    Close,
+}
+
+impl UndoToast {
+    /// How long the toast is shown for, in seconds
+    const DURATION: f64 = 5.0;
+
+    /// Offer to undo the action that was just applied to the source
+    pub fn new(message: impl Into<String>, source: &Source) -> Self {
+        Self {
+            message: message.into(),
+            shown_at: None,
+            tree_version: source.tree_version(),
+        }
+    }
+
+    /// If the action is still the last change to the text
+    pub fn is_current(&self, source: &Source) -> bool {
+        source.tree_version() == self.tree_version
+    }
+
+    /// Undo the action, unless the text has changed since. Returns if it was undone.
+    pub fn undo(&self, text_editor: &mut TextEditor, source: &mut Source) -> bool {
+        if !self.is_current(source) {
+            return false;
+        }
+        text_editor.undo(source);
+        true
+    }
+
+    pub fn show(&mut self, ctx: &Context, source: &Source) -> ToastResult {
+        if !self.is_current(source) {
+            return ToastResult::Close;
+        }
+
+        let now = ctx.input(|i| i.time);
+        let shown_at = *self.shown_at.get_or_insert(now);
+        let remaining = Self::DURATION - (now - shown_at);
+        if remaining <= 0.0 {
+            return ToastResult::Close;
+        }
+
+        // repaint when it runs out, even if nothing else happens
+        ctx.request_repaint_after(Duration::from_secs_f64(remaining));
+
+        let mut result = ToastResult::Keep;
+        Area::new(Id::new("undo_toast"))
+            .anchor(Align2::CENTER_BOTTOM, Vec2::new(0.0, -20.0))
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(&self.message);
+                        if ui.button("Undo").clicked() {
+                            result = ToastResult::Undo;
+                        }
+                        if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                            result = ToastResult::Close;
+                        }
+                    });
+                });
+            });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use ropey::Rope;
+
+    use super::*;
+    use crate::{
+        block_editor::{
+            source::{TextEdit, UndoStopCondition},
+            text_editor::selections::Selections,
+            text_range::{TextPoint, TextRange},
+        },
+        lang::Language,
+    };
+
+    fn apply(source: &mut Source, text: &str, range: TextRange) {
+        let edit = TextEdit::new(Cow::Borrowed(text), range);
+        source.apply_edit(
+            &edit,
+            UndoStopCondition::Always,
+            true,
+            &mut Selections::new(),
+        );
+    }
+
+    #[test]
+    fn test_undo_after_edit() {
+        let mut source = Source::new(
+            Rope::from_str("x = 1\nif x:\n    y = 2\n"),
+            Language::for_file("test.py"),
+        );
+        let mut text_editor = TextEditor::new();
+
+        // delete the if block
+        apply(
+            &mut source,
+            "",
+            TextRange::new(TextPoint::new(1, 0), TextPoint::new(3, 0)),
+        );
+        let toast = UndoToast::new("Block deleted", &source);
+        assert!(toast.is_current(&source));
+
+        // then type something else
+        apply(
+            &mut source,
+            "z",
+            TextRange::new_cursor(TextPoint::new(1, 0)),
+        );
+        assert!(!toast.is_current(&source));
+
+        // undoing from the toast leaves both the new edit and the deletion alone
+        assert!(!toast.undo(&mut text_editor, &mut source));
+        assert_eq!(source.text().to_string(), "x = 1\nz");
+
+        // but right after the deletion it brings the block back
+        let mut source = Source::new(
+            Rope::from_str("x = 1\nif x:\n    y = 2\n"),
+            Language::for_file("test.py"),
+        );
+        apply(
+            &mut source,
+            "",
+            TextRange::new(TextPoint::new(1, 0), TextPoint::new(3, 0)),
+        );
+        let toast = UndoToast::new("Block deleted", &source);
+        assert!(toast.undo(&mut text_editor, &mut source));
+        assert_eq!(source.text().to_string(), "x = 1\nif x:\n    y = 2\n");
+    }
+}
@@ -0,0 +1,296 @@
+//! Commands that run without opening a window, like `lilypad export main.py`
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Parser, Subcommand};
+use ropey::Rope;
+use serde_json::{json, Value};
+
+use crate::{
+    block_editor::{
+        export::{self, CodeView, ExportFont},
+        inspect,
+        source::Source,
+    },
+    lang::Language,
+    theme::{self, blocks_theme::BlocksTheme, Theme, ThemeFormat},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "lilypad",
+    about = "A block-based editor for text code",
+    long_about = "A block-based editor for text code. Run without a command to open the editor."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render files as SVG images of their code and blocks
+    Export(ExportArgs),
+
+    /// Print the blocks of a file as a tree
+    Blocks {
+        file: PathBuf,
+
+        /// Print the blocks as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the spans of a file captured by its language's highlight query
+    Highlight {
+        file: PathBuf,
+
+        /// Print the spans as JSON (with zero based lines and columns) instead
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check that every palette snippet parses without errors into the type of block it should
+    CheckPalettes,
+}
+
+impl Command {
+    /// Run the command, returning the process exit code
+    pub fn run(self) -> i32 {
+        let result = match self {
+            Command::Export(args) => export_files(&args),
+            Command::Blocks { file, json } => print_blocks(&file, json),
+            Command::Highlight { file, json } => print_highlights(&file, json),
+            Command::CheckPalettes => check_palettes(),
+        };
+        match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("error: {err}");
+                1
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// The source files to render
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// The directory to write the images to [default: next to each file]
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+
+    /// Also rasterise each image to a PNG
+    #[arg(long)]
+    png: bool,
+
+    /// Pixels per point in PNGs
+    #[arg(long, default_value_t = 2.0)]
+    scale: f32,
+
+    /// A built-in theme (dark, light or high_contrast) or a theme file
+    #[arg(long, default_value = "dark")]
+    theme: String,
+
+    /// How blocks are colored (syntax_colored, depth_grayscale, alternating_colored,
+    /// colorblind_safe), or none to leave them out
+    #[arg(long, default_value = "syntax_colored")]
+    blocks: String,
+
+    /// Number the lines
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Only render these lines, like 10-20 (counting from 1, inclusive)
+    #[arg(long, value_parser = parse_line_range)]
+    lines: Option<Range<usize>>,
+
+    /// The font size in points
+    #[arg(long, default_value_t = 14.0)]
+    font_size: f32,
+}
+
+fn export_files(args: &ExportArgs) -> Result<(), String> {
+    theme::set_current(load_theme(&args.theme)?);
+    let blocks_theme = match args.blocks.as_str() {
+        "none" => None,
+        name => Some(BlocksTheme::for_str(name)),
+    };
+    let font = ExportFont::monospace(args.font_size);
+
+    let mut failed = 0;
+    for file in &args.files {
+        let result = export_file(file, args, blocks_theme, &font);
+        if let Err(err) = result {
+            eprintln!("error: {}: {err}", file.display());
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} of {} files failed", args.files.len())),
+    }
+}
+
+fn export_file(
+    file: &Path,
+    args: &ExportArgs,
+    blocks_theme: Option<BlocksTheme>,
+    font: &ExportFont,
+) -> Result<(), String> {
+    let mut source = load_source(file)?;
+    let view = CodeView::for_source(&mut source, args.lines.clone(), blocks_theme)
+        .with_line_numbers(args.line_numbers);
+
+    let out_path = |extension: &str| {
+        let path = file.with_extension(extension);
+        match &args.out_dir {
+            Some(dir) => dir.join(path.file_name().unwrap_or_default()),
+            None => path,
+        }
+    };
+
+    let svg_path = out_path("svg");
+    write(&svg_path, export::file::svg::to_svg(&view, font).as_bytes())?;
+    println!("{}", svg_path.display());
+
+    if args.png {
+        let png = export::file::to_png(&view, font, args.scale)?;
+        let png_path = out_path("png");
+        write(&png_path, &png)?;
+        println!("{}", png_path.display());
+    }
+
+    Ok(())
+}
+
+fn print_blocks(file: &Path, json: bool) -> Result<(), String> {
+    let source = load_source(file)?;
+    if json {
+        println!("{:#}", inspect::blocks_json(&source));
+    } else {
+        print!("{}", inspect::blocks_tree(&source));
+    }
+    Ok(())
+}
+
+fn print_highlights(file: &Path, json: bool) -> Result<(), String> {
+    let source = load_source(file)?;
+    let spans = inspect::capture_spans(&source);
+
+    if json {
+        let spans: Vec<Value> = spans
+            .iter()
+            .map(|span| {
+                json!({
+                    "start": { "line": span.range.start.line, "col": span.range.start.col },
+                    "end": { "line": span.range.end.line, "col": span.range.end.col },
+                    "capture": span.capture,
+                    "color": span.color,
+                })
+            })
+            .collect();
+        println!("{:#}", Value::Array(spans));
+        return Ok(());
+    }
+
+    // like compiler messages, lines and columns count from 1
+    for span in spans {
+        let chars = span.range.char_range_in(source.text());
+        println!(
+            "{}:{}-{}:{} {} {:?}",
+            span.range.start.line + 1,
+            span.range.start.col + 1,
+            span.range.end.line + 1,
+            span.range.end.col + 1,
+            span.capture,
+            source.text().slice(chars).to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn check_palettes() -> Result<(), String> {
+    let (checked, problems) = inspect::check_palettes();
+    for problem in &problems {
+        println!(
+            "{} / {} / {}: {}",
+            problem.language, problem.palette, problem.snippet, problem.problem
+        );
+    }
+    println!(
+        "checked {checked} snippets, found {} problems",
+        problems.len()
+    );
+
+    match problems.len() {
+        0 => Ok(()),
+        count => Err(format!("{count} palette problems")),
+    }
+}
+
+/// Read and parse a source file, in the language for its extension
+fn load_source(file: &Path) -> Result<Source, String> {
+    let text = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+    Ok(Source::new(
+        Rope::from_str(&text),
+        Language::for_file(&file_name),
+    ))
+}
+
+fn load_theme(name: &str) -> Result<Theme, String> {
+    if let Some(theme) = Theme::built_in(name) {
+        return Ok(theme);
+    }
+    let contents =
+        fs::read_to_string(name).map_err(|err| format!("Failed to read theme {name}: {err}"))?;
+    Theme::parse(&contents, ThemeFormat::for_file(name))
+        .map_err(|err| format!("Invalid theme {name}: {err}"))
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, contents).map_err(|err| format!("Failed to write {}: {err}", path.display()))
+}
+
+/// Parse a range of lines like `10-20` or just `10` into the (zero based, exclusive) range of lines
+fn parse_line_range(range: &str) -> Result<Range<usize>, String> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let parse = |num: &str| {
+        num.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|num| *num > 0)
+            .ok_or_else(|| format!("{num:?} is not a line number"))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if end < start {
+        return Err(format!("the range {range} ends before it starts"));
+    }
+    Ok(start - 1..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("10-20"), Ok(9..20));
+        assert_eq!(parse_line_range("3"), Ok(2..3));
+        assert!(parse_line_range("0-2").is_err());
+        assert!(parse_line_range("5-2").is_err());
+        assert!(parse_line_range("a-b").is_err());
+    }
+}
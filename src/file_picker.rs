@@ -31,7 +31,8 @@ impl FilePicker {
                     );
                     let response = ui.interact(rect, id, Sense::hover());
 
-                    ui.painter().rect_filled(rect, 0.0, theme::POPUP_BACKGROUND);
+                    ui.painter()
+                        .rect_filled(rect, 0.0, theme::current().editor.popup_background);
 
                     if self.dir.is_some() {
                         for (idx, file) in self.files.iter().enumerate() {
@@ -43,7 +44,7 @@ impl FilePicker {
                                     ),
                                     SelectableRow::new(
                                         file,
-                                        theme::syntax::DEFAULT,
+                                        theme::current().syntax.default,
                                         self.selected_file == Some(idx),
                                         FontId::proportional(15.0),
                                     ),
@@ -1,6 +1,4 @@
-use egui::Color32;
-
-use crate::{block_editor::BlockType, theme::syntax::*};
+use crate::{block_editor::BlockType, theme::SyntaxColor};
 
 pub struct LanguageConfig {
     /// Name of the language. Used as an ID and potentially for UI
@@ -12,6 +10,17 @@ pub struct LanguageConfig {
     /// Tree-sitter highlight query
     pub(super) highlight_query: &'static str,
 
+    /// Tree-sitter query for indenting lines. See `IndentQuery` for the captures.
+    pub(super) indent_query: &'static str,
+
+    /// Tree-sitter query for finding local variables. See `LocalsQuery` for the captures.
+    /// Empty if the language has none.
+    pub(super) locals_query: &'static str,
+
+    /// Tree-sitter query for finding definitions for the outline. See `TagsQuery` for the captures.
+    /// Empty if the language has none.
+    pub(super) tags_query: &'static str,
+
     /// The character that starts a new scope (so should increase the indent)
     pub new_scope_char: NewScopeChar,
 
@@ -25,7 +34,7 @@ pub struct LanguageConfig {
     pub palettes: &'static [Palette],
 
     /// The highlight names to recognize and their associated colors
-    pub highlight: &'static [(&'static str, Color32)],
+    pub highlight: &'static [(&'static str, SyntaxColor)],
 }
 
 impl LanguageConfig {
@@ -42,6 +51,19 @@ impl LanguageConfig {
         }
     }
 
+    /// Every supported language
+    pub fn all() -> [&'static LanguageConfig; 7] {
+        [
+            &PYTHON_LANGUAGE,
+            &JAVA_LANGUAGE,
+            &CS_LANGUAGE,
+            &CPP_LANGUAGE,
+            &C_LANGUAGE,
+            &RUST_LANGUAGE,
+            &SYSTEMVERILOG_LANGUAGE,
+        ]
+    }
+
     pub fn tree_sitter(&self) -> tree_sitter::Language {
         tree_sitter::Language::new(self.ts_lang)
     }
@@ -88,11 +110,48 @@ pub struct StringNodeIDs {
 pub struct Snippet {
     pub id: &'static str,
     pub source: &'static str,
+
+    /// The type of the snippet's outermost block, checked by `lilypad check-palettes`,
+    /// or none if it isn't in a block (like some one line statements)
+    pub block_type: Option<BlockType>,
+
+    /// The line of the snippet that is replaced by the code it surrounds (for "Surround with…"),
+    /// if it can surround code
+    pub body_line: Option<usize>,
 }
 
 impl Snippet {
     pub const fn new(id: &'static str, source: &'static str) -> Snippet {
-        Snippet { id, source }
+        Snippet {
+            id,
+            source,
+            block_type: Some(BlockType::Generic),
+            body_line: None,
+        }
+    }
+
+    /// The snippet is expected to be a block of this type, instead of a generic one
+    pub const fn of_type(self, block_type: BlockType) -> Snippet {
+        Snippet {
+            block_type: Some(block_type),
+            ..self
+        }
+    }
+
+    /// The snippet isn't in a block, like a statement the language doesn't give one to
+    pub const fn without_block(self) -> Snippet {
+        Snippet {
+            block_type: None,
+            ..self
+        }
+    }
+
+    /// The snippet can surround code, which replaces the line (like the `pass` in an if)
+    pub const fn wraps(self, body_line: usize) -> Snippet {
+        Snippet {
+            body_line: Some(body_line),
+            ..self
+        }
     }
 }
 
@@ -100,6 +159,9 @@ const PYTHON_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "python",
     ts_lang: tree_sitter_python::LANGUAGE,
     highlight_query: tree_sitter_python::HIGHLIGHTS_QUERY,
+    indent_query: include_str!("./indents/python_indents.scm"),
+    locals_query: include_str!("./locals/python_locals.scm"),
+    tags_query: tree_sitter_python::TAGS_QUERY,
     new_scope_char: NewScopeChar::Colon,
     node_categorizer: |node| {
         use BlockType::*;
@@ -160,38 +222,56 @@ const PYTHON_LANGUAGE: LanguageConfig = LanguageConfig {
                 Snippet::new(
                     "class_declaration",
                     "class ClassName:\n    def __init__(self, param):\n        pass\n",
-                ),
-                Snippet::new("instance_method", "def method(self, param):\n    pass\n"),
+                )
+                .of_type(BlockType::Object),
+                Snippet::new("instance_method", "def method(self, param):\n    pass\n")
+                    .of_type(BlockType::FunctionDef)
+                    .wraps(1),
                 Snippet::new(
                     "static_method",
                     "@staticmethod\ndef method(param):\n    pass\n",
-                ),
+                )
+                .of_type(BlockType::FunctionDef),
                 Snippet::new("class_instance", "instance = ClassName()\n"),
             ],
         ),
         Palette::new(
             "Control",
             &[
-                Snippet::new("for", "for item in range(0, 10):\n    pass\n"),
-                Snippet::new("while", "while 0 == 0:\n    pass\n"),
+                Snippet::new("for", "for item in range(0, 10):\n    pass\n")
+                    .of_type(BlockType::For)
+                    .wraps(1),
+                Snippet::new("while", "while 0 == 0:\n    pass\n")
+                    .of_type(BlockType::While)
+                    .wraps(1),
                 Snippet::new("break", "break\n"),
                 Snippet::new("continue", "continue\n"),
-                Snippet::new("if", "if 0 < 0:\n    pass\n"),
-                Snippet::new("if_else", "if 0 < 0:\n    pass\nelse:\n    pass\n"),
+                Snippet::new("if", "if 0 < 0:\n    pass\n")
+                    .of_type(BlockType::If)
+                    .wraps(1),
+                Snippet::new("if_else", "if 0 < 0:\n    pass\nelse:\n    pass\n")
+                    .of_type(BlockType::If)
+                    .wraps(1),
                 Snippet::new(
                     "if_elif_else",
                     "if 0 < 0:\n    pass\nelif 0 > 0:\n    pass\nelse:\n    pass\n",
-                ),
+                )
+                .of_type(BlockType::If)
+                .wraps(1),
                 Snippet::new(
                     "try",
                     "try:\n    pass\nexcept:\n    pass\nelse:\n    pass\nfinally:\n    pass\n",
-                ),
+                )
+                .of_type(BlockType::Try)
+                .wraps(1),
             ],
         ),
         Palette::new(
             "Functions",
             &[
-                Snippet::new("function_def", "def function(args):\n    return\n"),
+                Snippet::new("function_def", "def function(args):\n    return\n")
+                    .of_type(BlockType::FunctionDef)
+                    .wraps(1),
                 Snippet::new("function_call", "function(args) \n"),
                 Snippet::new("return_val", "return value\n"),
                 Snippet::new("return", "return\n"),
@@ -233,6 +313,9 @@ const JAVA_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "java",
     ts_lang: tree_sitter_java::LANGUAGE,
     highlight_query: tree_sitter_java::HIGHLIGHTS_QUERY,
+    indent_query: include_str!("./indents/java_indents.scm"),
+    locals_query: include_str!("./locals/java_locals.scm"),
+    tags_query: tree_sitter_java::TAGS_QUERY,
     new_scope_char: NewScopeChar::Brace,
     node_categorizer: |node| {
         use BlockType::*;
@@ -291,17 +374,26 @@ const JAVA_LANGUAGE: LanguageConfig = LanguageConfig {
             Snippet::new(
                 "if",
                 "if (condition) {\n    \n} else if (condition) {\n    \n} else {\n    \n}\n",
-            ),
+            )
+            .of_type(BlockType::If)
+            .wraps(1),
             Snippet::new(
                 "class",
                 "public class MyClass {\n    public MyClass() {\n        \n    }\n}\n",
-            ),
-            Snippet::new("while", "while (condition) {\n    \n}\n"),
-            Snippet::new("method", "public void myMethod() {\n    \n}\n"),
+            )
+            .of_type(BlockType::Object),
+            Snippet::new("while", "while (condition) {\n    \n}\n")
+                .of_type(BlockType::While)
+                .wraps(1),
+            Snippet::new("method", "public void myMethod() {\n    \n}\n")
+                .of_type(BlockType::FunctionDef)
+                .wraps(1),
             Snippet::new(
                 "try",
                 "try {\n    \n} catch (Exception e) {\n    \n} finally {\n    \n}\n",
-            ),
+            )
+            .of_type(BlockType::Try)
+            .wraps(1),
         ],
     )],
     highlight: STANDARD_HIGHLIGHT,
@@ -311,6 +403,9 @@ const CS_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "c#",
     ts_lang: tree_sitter_c_sharp::LANGUAGE,
     highlight_query: tree_sitter_c_sharp::HIGHLIGHTS_QUERY,
+    indent_query: include_str!("./indents/c_sharp_indents.scm"),
+    locals_query: include_str!("./locals/c_sharp_locals.scm"),
+    tags_query: include_str!("./tags/c_sharp_tags.scm"),
     new_scope_char: NewScopeChar::Brace,
     node_categorizer: |node| {
         use BlockType::*;
@@ -369,17 +464,24 @@ const CS_LANGUAGE: LanguageConfig = LanguageConfig {
             Snippet::new(
                 "if",
                 "if (condition) {\n    \n} else if (condition) {\n    \n} else {\n    \n}\n",
-            ),
+            )
+            .of_type(BlockType::If)
+            .wraps(1),
             Snippet::new(
                 "class",
                 "public class MyClass {\n    public MyClass() {\n        \n    }\n}\n",
-            ),
-            Snippet::new("while", "while (condition) {\n    \n}\n"),
-            Snippet::new("func", "public void myFunction() {\n    \n}\n"),
+            )
+            .of_type(BlockType::Object),
+            Snippet::new("while", "while (condition) {\n    \n}\n")
+                .of_type(BlockType::While)
+                .wraps(1),
+            Snippet::new("func", "public void myFunction() {\n    \n}\n").wraps(1),
             Snippet::new(
                 "try",
                 "try {\n    \n} catch (Exception e) {\n    \n} finally {\n    \n}\n",
-            ),
+            )
+            .of_type(BlockType::Try)
+            .wraps(1),
         ],
     )],
     highlight: STANDARD_HIGHLIGHT,
@@ -389,6 +491,9 @@ const CPP_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "cpp",
     ts_lang: tree_sitter_cpp::LANGUAGE,
     highlight_query: tree_sitter_c::HIGHLIGHT_QUERY, // Temp fix, will work for most code but wont highlight classes properly
+    indent_query: include_str!("./indents/c_indents.scm"),
+    locals_query: include_str!("./locals/c_locals.scm"),
+    tags_query: tree_sitter_cpp::TAGS_QUERY,
     new_scope_char: NewScopeChar::Brace,
     node_categorizer: |node| {
         use BlockType::*;
@@ -457,6 +562,9 @@ const C_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "c",
     ts_lang: tree_sitter_c::LANGUAGE,
     highlight_query: tree_sitter_c::HIGHLIGHT_QUERY,
+    indent_query: include_str!("./indents/c_indents.scm"),
+    locals_query: include_str!("./locals/c_locals.scm"),
+    tags_query: tree_sitter_c::TAGS_QUERY,
     new_scope_char: NewScopeChar::Brace,
     node_categorizer: |node| {
         use BlockType::*;
@@ -513,6 +621,9 @@ const RUST_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "rust",
     ts_lang: tree_sitter_rust::LANGUAGE,
     highlight_query: tree_sitter_rust::HIGHLIGHTS_QUERY,
+    indent_query: include_str!("./indents/rust_indents.scm"),
+    locals_query: include_str!("./locals/rust_locals.scm"),
+    tags_query: tree_sitter_rust::TAGS_QUERY,
     new_scope_char: NewScopeChar::Brace,
     node_categorizer: |node| {
         use BlockType::*;
@@ -657,28 +768,28 @@ const RUST_LANGUAGE: LanguageConfig = LanguageConfig {
             &[
                 Snippet::new(
                     "if",
-                    "if condition {\n    // code\n} else if condition {\n    // code\n} else {\n    // code\n}",
-                ),
+                    "if condition {\n    // code\n} else if condition {\n    // code\n} else {\n    // code\n}\n",
+                ).of_type(BlockType::If).wraps(1),
                 Snippet::new(
                     "loop",
-                    "loop {\n    // code\n}",
-                ),
+                    "loop {\n    // code\n}\n",
+                ).of_type(BlockType::Object).wraps(1),
                 Snippet::new(
                     "while",
-                    "while condition {\n    // code\n}",
-                ),
+                    "while condition {\n    // code\n}\n",
+                ).of_type(BlockType::Object).wraps(1),
                 Snippet::new(
                     "fn",
-                    "fn my_function() {\n    // code\n}",
-                ),
+                    "fn my_function() {\n    // code\n}\n",
+                ).of_type(BlockType::FunctionDef).wraps(1),
                 Snippet::new(
                     "match",
-                    "match value {\n    Pattern1 => {None}\n    Pattern2 => {None}\n    _ => {}\n}",
-                ),
+                    "match value {\n    Pattern1 => {None}\n    Pattern2 => {None}\n    _ => {}\n}\n",
+                ).of_type(BlockType::Switch),
                 Snippet::new(
                     "result",
-                    "fn divide(a: i32, b: i32) -> Result<i32, String> {\n    if b == 0 {\n        Err(String::from(\"Cannot divide by zero\"))\n    } else {\n        Ok(a / b)\n    }\n}",
-                ),
+                    "fn divide(a: i32, b: i32) -> Result<i32, String> {\n    if b == 0 {\n        Err(String::from(\"Cannot divide by zero\"))\n    } else {\n        Ok(a / b)\n    }\n}\n",
+                ).of_type(BlockType::FunctionDef),
             ])],
     highlight: STANDARD_HIGHLIGHT,
 };
@@ -687,6 +798,9 @@ const SYSTEMVERILOG_LANGUAGE: LanguageConfig = LanguageConfig {
     name: "systemverilog",
     ts_lang: tree_sitter_systemverilog::LANGUAGE,
     highlight_query: include_str!("./highlights/system_verilog_highlights.scm"),
+    indent_query: include_str!("./indents/system_verilog_indents.scm"),
+    locals_query: "",
+    tags_query: "",
     new_scope_char: NewScopeChar::Begin,
     node_categorizer: |node| {
         use BlockType::*;
@@ -758,64 +872,64 @@ const SYSTEMVERILOG_LANGUAGE: LanguageConfig = LanguageConfig {
         Palette::new(
             "Modules & Classes",
             &[
-                Snippet::new("module", "module module_name(\n    // ports\n);\n    // module body\nendmodule\n"),
-                Snippet::new("interface", "interface interface_name;\n    // interface body\nendinterface\n"),
-                Snippet::new("class", "class class_name;\n    // class members\nendclass\n"),
-                Snippet::new("package", "package package_name;\n    // package contents\nendpackage\n"),
-                Snippet::new("program", "program program_name;\n    // program body\nendprogram\n"),
+                Snippet::new("module", "module module_name(\n    // ports\n);\n    // module body\nendmodule\n").of_type(BlockType::Object),
+                Snippet::new("interface", "interface interface_name;\n    // interface body\nendinterface\n").of_type(BlockType::Object),
+                Snippet::new("class", "class class_name;\n    // class members\nendclass\n").of_type(BlockType::Object),
+                Snippet::new("package", "package package_name;\n    // package contents\nendpackage\n").of_type(BlockType::Object),
+                Snippet::new("program", "program program_name;\n    // program body\nendprogram\n").of_type(BlockType::Object),
             ],
         ),
         Palette::new(
             "Control Flow",
             &[
-                Snippet::new("if", "if (condition) begin\n    // statements\nend\n"),
-                Snippet::new("if_else", "if (condition) begin\n    // if statements\nend else begin\n    // else statements\nend\n"),
-                Snippet::new("case", "case (expression)\n    value1: begin\n        // statements\n    end\n    default: begin\n        // default statements\n    end\nendcase\n"),
-                Snippet::new("for", "for (int i = 0; i < limit; i++) begin\n    // statements\nend\n"),
-                Snippet::new("foreach", "foreach (array[i]) begin\n    // statements\nend\n"),
-                Snippet::new("while", "while (condition) begin\n    // statements\nend\n"),
-                Snippet::new("do_while", "do begin\n    // statements\nend while (condition);\n"),
+                Snippet::new("if", "if (condition) begin\n    // statements\nend\n").without_block().wraps(1),
+                Snippet::new("if_else", "if (condition) begin\n    // if statements\nend else begin\n    // else statements\nend\n").without_block().wraps(1),
+                Snippet::new("case", "case (expression)\n    value1: begin\n        // statements\n    end\n    default: begin\n        // default statements\n    end\nendcase\n").without_block(),
+                Snippet::new("for", "for (int i = 0; i < limit; i++) begin\n    // statements\nend\n").of_type(BlockType::For).wraps(1),
+                Snippet::new("foreach", "foreach (array[i]) begin\n    // statements\nend\n").of_type(BlockType::For).wraps(1),
+                Snippet::new("while", "while (condition) begin\n    // statements\nend\n").of_type(BlockType::For).wraps(1),
+                Snippet::new("do_while", "do begin\n    // statements\nend while (condition);\n").of_type(BlockType::For).wraps(1),
             ],
         ),
         Palette::new(
             "Blocks & Processes",
             &[
-                Snippet::new("always_comb", "always_comb begin\n    // combinational logic\nend\n"),
-                Snippet::new("always_ff", "always_ff @(posedge clk) begin\n    // sequential logic\nend\n"),
-                Snippet::new("always_latch", "always_latch begin\n    // latch logic\nend\n"),
-                Snippet::new("initial", "initial begin\n    // initialization\nend\n"),
-                Snippet::new("final", "final begin\n    // finalization\nend\n"),
+                Snippet::new("always_comb", "always_comb begin\n    // combinational logic\nend\n").of_type(BlockType::Always).wraps(1),
+                Snippet::new("always_ff", "always_ff @(posedge clk) begin\n    // sequential logic\nend\n").of_type(BlockType::Always).wraps(1),
+                Snippet::new("always_latch", "always_latch begin\n    // latch logic\nend\n").of_type(BlockType::Always).wraps(1),
+                Snippet::new("initial", "initial begin\n    // initialization\nend\n").wraps(1),
+                Snippet::new("final", "final begin\n    // finalization\nend\n").wraps(1),
             ],
         ),
         Palette::new(
             "Verification",
             &[
-                Snippet::new("assert", "assert (condition) else $error(\"Assertion failed\");\n"),
-                Snippet::new("assume", "assume (condition);\n"),
-                Snippet::new("cover", "cover (condition);\n"),
-                Snippet::new("expect", "expect (condition) else $error(\"Expectation failed\");\n"),
-                Snippet::new("try_catch", "try begin\n    // risky operation\nend\ncatch begin\n    // error handling\nend\n"),
+                Snippet::new("assert", "assert (condition) else $error(\"Assertion failed\");\n").without_block(),
+                Snippet::new("assume", "assume (condition);\n").without_block(),
+                Snippet::new("cover", "cover (condition);\n").without_block(),
+                Snippet::new("expect", "expect (condition) else $error(\"Expectation failed\");\n").without_block(),
+                Snippet::new("try_catch", "assert (condition) begin\n    // risky operation\nend else begin\n    // error handling\nend\n").without_block(),
             ],
         ),
     ],
     highlight: STANDARD_HIGHLIGHT,
 };
 
-const STANDARD_HIGHLIGHT: &[(&str, Color32)] = &[
-    ("function", FUNCTION),
-    ("function.builtin", FUNCTION_BUILT_IN),
-    ("keyword", KEYWORD),
-    ("operator", OPERATOR),
-    ("property", PROPERTY),
-    ("punctuation.special", INTERPOLATION_SURROUNDING),
-    ("string", STRING),
-    ("type", TYPE),
-    ("variable", VARIABLE),
-    ("constructor", CONSTRUCTOR),
-    ("constant", CONSTANT),
-    ("constant.builtin", LITERAL),
-    ("number", LITERAL),
-    ("escape", ESCAPE_SEQUENCE),
-    ("comment", COMMENT),
-    ("embedded", DEFAULT), // treat inside of interpolation like top level
+const STANDARD_HIGHLIGHT: &[(&str, SyntaxColor)] = &[
+    ("function", SyntaxColor::Function),
+    ("function.builtin", SyntaxColor::FunctionBuiltIn),
+    ("keyword", SyntaxColor::Keyword),
+    ("operator", SyntaxColor::Operator),
+    ("property", SyntaxColor::Property),
+    ("punctuation.special", SyntaxColor::InterpolationSurrounding),
+    ("string", SyntaxColor::String),
+    ("type", SyntaxColor::Type),
+    ("variable", SyntaxColor::Variable),
+    ("constructor", SyntaxColor::Constructor),
+    ("constant", SyntaxColor::Constant),
+    ("constant.builtin", SyntaxColor::Literal),
+    ("number", SyntaxColor::Literal),
+    ("escape", SyntaxColor::EscapeSequence),
+    ("comment", SyntaxColor::Comment),
+    ("embedded", SyntaxColor::Default), // treat inside of interpolation like top level
 ];
@@ -5,6 +5,7 @@
 //   - remove all parsing (take in tree directly)
 //   - removed injections
 //   - support for ropey
+//   - the `local.ignore` capture and scope handling shared with `LocalsQuery`
 
 use ropey::RopeSlice;
 use std::{
@@ -19,6 +20,8 @@ use tree_sitter::{
     QueryMatch, Range, TextProvider, Tree,
 };
 
+use super::locals::scope_inherits;
+
 /// Indicates which highlight should be applied to a region of source code.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Highlight(pub usize);
@@ -49,6 +52,7 @@ pub struct HighlightConfiguration {
     local_def_capture_index: Option<u32>,
     local_def_value_capture_index: Option<u32>,
     local_ref_capture_index: Option<u32>,
+    local_ignore_capture_index: Option<u32>,
 }
 
 /// Performs syntax highlighting, recognizing a given list of highlight names.
@@ -289,6 +293,7 @@ impl HighlightConfiguration {
         let mut local_def_value_capture_index = None;
         let mut local_ref_capture_index = None;
         let mut local_scope_capture_index = None;
+        let mut local_ignore_capture_index = None;
         for (i, name) in query.capture_names().iter().enumerate() {
             let i = Some(i as u32);
             match *name {
@@ -296,6 +301,7 @@ impl HighlightConfiguration {
                 "local.definition-value" => local_def_value_capture_index = i,
                 "local.reference" => local_ref_capture_index = i,
                 "local.scope" => local_scope_capture_index = i,
+                "local.ignore" => local_ignore_capture_index = i,
                 _ => {}
             }
         }
@@ -314,6 +320,7 @@ impl HighlightConfiguration {
             local_def_value_capture_index,
             local_ref_capture_index,
             local_scope_capture_index,
+            local_ignore_capture_index,
         })
     }
 
@@ -532,23 +539,17 @@ impl Iterator for HighlightIter<'_> {
             // local variable info.
             let mut reference_highlight = None;
             let mut definition_highlight = None;
+            let mut ignored = false;
             while match_.pattern_index < layer.config.highlights_pattern_index {
                 // If the node represents a local scope, push a new local scope onto
                 // the scope stack.
                 if Some(capture.index) == layer.config.local_scope_capture_index {
                     definition_highlight = None;
-                    let mut scope = LocalScope {
-                        inherits: true,
+                    layer.scope_stack.push(LocalScope {
+                        inherits: scope_inherits(&layer.config.query, match_.pattern_index),
                         range: range.clone(),
                         local_defs: Vec::new(),
-                    };
-                    for prop in layer.config.query.property_settings(match_.pattern_index) {
-                        if prop.key.as_ref() == "local.scope-inherits" {
-                            scope.inherits =
-                                prop.value.as_ref().is_none_or(|r| r.as_ref() == "true");
-                        }
-                    }
-                    layer.scope_stack.push(scope);
+                    });
                 }
                 // If the node represents a definition, add a new definition to the
                 // local scope at the top of the scope stack.
@@ -572,10 +573,16 @@ impl Iterator for HighlightIter<'_> {
                     });
                     definition_highlight = scope.local_defs.last_mut().map(|s| &mut s.highlight);
                 }
+                // If the node is never a reference (like a field with the same name as a
+                // variable), skip the reference patterns after it.
+                else if Some(capture.index) == layer.config.local_ignore_capture_index {
+                    ignored = true;
+                }
                 // If the node represents a reference, then try to find the corresponding
                 // definition in the scope stack.
                 else if Some(capture.index) == layer.config.local_ref_capture_index
                     && definition_highlight.is_none()
+                    && !ignored
                 {
                     definition_highlight = None;
                     let name = self.source.0.byte_slice(range.clone());
@@ -668,7 +675,7 @@ impl Iterator for HighlightIter<'_> {
 
 /* ------- Rope + TS Text Provider  ------- */
 #[derive(Clone, Copy)]
-struct RopeProvider<'a>(pub RopeSlice<'a>);
+pub(super) struct RopeProvider<'a>(pub RopeSlice<'a>);
 
 impl<'a> TextProvider<&'a [u8]> for RopeProvider<'a> {
     type I = ChunksBytes<'a>;
@@ -682,7 +689,7 @@ impl<'a> TextProvider<&'a [u8]> for RopeProvider<'a> {
 }
 
 /// shim to convert rope chunks to bytes
-struct ChunksBytes<'a> {
+pub(super) struct ChunksBytes<'a> {
     chunks: ropey::iter::Chunks<'a>,
 }
 
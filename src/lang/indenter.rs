@@ -0,0 +1,254 @@
+use std::{collections::HashSet, ops::Range};
+
+use log::error;
+use ropey::Rope;
+use tree_sitter::{Node, Query, QueryCursor, StreamingIterator};
+
+use super::highlighter::RopeProvider;
+use crate::block_editor::rope_ext::RopeSliceExt;
+
+/// Finds the indent level of lines using a tree-sitter query (in the style of an `indents.scm` file).
+///
+/// The query uses two captures:
+/// - `@indent`: lines after the first line of the node are indented one more level
+/// - `@outdent`: a line that starts with the node is indented one less level
+///   (closing brackets, `end`, `else` clauses, etc.)
+pub struct IndentQuery {
+    query: Query,
+    indent_idx: u32,
+    outdent_idx: Option<u32>,
+}
+
+/// The captured nodes in part of the tree
+struct IndentCaptures<'tree> {
+    indents: Vec<Node<'tree>>,
+    outdent_starts: HashSet<usize>,
+}
+
+impl IndentQuery {
+    /// Returns None if the query is invalid or has no indent captures
+    pub fn new(language: &tree_sitter::Language, source: &str) -> Option<Self> {
+        let query = match Query::new(language, source) {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Invalid indent query: {err}");
+                return None;
+            }
+        };
+
+        let indent_idx = query.capture_index_for_name("indent")?;
+        let outdent_idx = query.capture_index_for_name("outdent");
+        Some(IndentQuery {
+            query,
+            indent_idx,
+            outdent_idx,
+        })
+    }
+
+    /// The indent level of each line in the range.
+    /// Lines that are blank, inside a multiline string or comment, or inside a syntax error are None.
+    pub fn line_levels(
+        &self,
+        root: Node,
+        source: &Rope,
+        lines: Range<usize>,
+    ) -> Vec<Option<usize>> {
+        let start_byte = source.line_to_byte(lines.start);
+        let end_byte = source.line_to_byte(usize::min(lines.end, source.len_lines()));
+        let captures = self.captures(root, source, start_byte..end_byte);
+
+        lines
+            .map(|line_num| {
+                let line = source.line(line_num);
+                let indent = line.whitespace_at_start();
+                if indent == line.len_chars_no_linebreak() {
+                    return None;
+                }
+
+                let line_start = source.line_to_byte(line_num);
+                let first_byte = source.char_to_byte(source.line_to_char(line_num) + indent);
+                if in_literal(root, line_start, first_byte) || in_error(root, first_byte) {
+                    return None;
+                }
+
+                let level = captures.level(line_num, first_byte, false);
+                if captures.outdent_starts.contains(&first_byte) {
+                    Some(level.saturating_sub(1))
+                } else {
+                    Some(level)
+                }
+            })
+            .collect()
+    }
+
+    /// The indent level for a new line inserted at the byte offset, or None if it is inside a syntax error
+    pub fn new_line_level(&self, root: Node, source: &Rope, byte: usize) -> Option<usize> {
+        // look at the node before the insertion point, since that is what was just typed
+        if in_error(root, byte.saturating_sub(1)) {
+            return None;
+        }
+
+        let captures = self.captures(root, source, byte.saturating_sub(1)..byte + 1);
+        let line = source.byte_to_line(byte) + 1;
+        Some(captures.level(line, byte, true))
+    }
+
+    /// If the line starts with an outdent node (such as a closing bracket)
+    pub fn line_starts_with_outdent(&self, root: Node, source: &Rope, line_num: usize) -> bool {
+        let indent = source.line(line_num).whitespace_at_start();
+        let first_byte = source.char_to_byte(source.line_to_char(line_num) + indent);
+        let captures = self.captures(root, source, first_byte..first_byte + 1);
+        captures.outdent_starts.contains(&first_byte)
+    }
+
+    fn captures<'tree>(
+        &self,
+        root: Node<'tree>,
+        source: &Rope,
+        bytes: Range<usize>,
+    ) -> IndentCaptures<'tree> {
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(bytes);
+
+        let mut indents = vec![];
+        let mut outdent_starts = HashSet::new();
+        let mut matches = cursor.matches(&self.query, root, RopeProvider(source.slice(..)));
+        while let Some(query_match) = matches.next() {
+            for capture in query_match.captures {
+                if capture.index == self.indent_idx {
+                    indents.push(capture.node);
+                } else if Some(capture.index) == self.outdent_idx {
+                    outdent_starts.insert(capture.node.start_byte());
+                }
+            }
+        }
+
+        IndentCaptures {
+            indents,
+            outdent_starts,
+        }
+    }
+}
+
+impl IndentCaptures<'_> {
+    /// Count the indent nodes that started on an earlier line and contain the byte offset.
+    /// Nodes starting on the same line only add a single level.
+    /// For new lines, nodes ending right at the offset still count unless they were closed with an outdent node.
+    fn level(&self, line: usize, byte: usize, for_new_line: bool) -> usize {
+        let mut indented_lines = HashSet::new();
+        for node in &self.indents {
+            let start_line = node.start_position().row;
+            let contains = node.end_byte() > byte
+                || (for_new_line && node.end_byte() == byte && !self.is_closed(node));
+            if start_line < line && node.start_byte() < byte && contains {
+                indented_lines.insert(start_line);
+            }
+        }
+        indented_lines.len()
+    }
+
+    /// If the last child of the node is an outdent token (like a closing bracket)
+    fn is_closed(&self, node: &Node) -> bool {
+        node.child(node.child_count().saturating_sub(1))
+            .is_some_and(|last| {
+                last.child_count() == 0 && self.outdent_starts.contains(&last.start_byte())
+            })
+    }
+}
+
+/// If the byte offset is within a syntax error
+fn in_error(root: Node, byte: usize) -> bool {
+    let mut node = root.descendant_for_byte_range(byte, byte);
+    while let Some(curr) = node {
+        if curr.is_error() || curr.is_missing() {
+            return true;
+        }
+        node = curr.parent();
+    }
+    false
+}
+
+/// If the line is a continuation of a multiline string or comment,
+/// in which case its indent is part of the literal and should not change
+fn in_literal(root: Node, line_start: usize, first_byte: usize) -> bool {
+    let mut node = root.descendant_for_byte_range(first_byte, first_byte);
+    while let Some(curr) = node {
+        if curr.start_byte() >= line_start {
+            node = curr.parent();
+            continue;
+        }
+        let kind = curr.kind();
+        if kind.contains("string") || kind.contains("comment") {
+            return true;
+        }
+        node = curr.parent();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::{config::LanguageConfig, Language};
+
+    const JAVA: &str = include_str!("../../test-files/JavaBadFormatting.java");
+
+    #[test]
+    fn test_line_levels() {
+        let mut lang = Language::for_file("test.java");
+        let tree = lang.parser.parse(JAVA, None).unwrap();
+        let query = lang.indent_query.as_ref().unwrap();
+        let rope = Rope::from_str(JAVA);
+        let levels = query.line_levels(tree.root_node(), &rope, 0..rope.len_lines());
+
+        #[rustfmt::skip]
+        assert_eq!(
+            levels,
+            vec![
+                Some(0), Some(1), Some(0), None,
+                Some(0), Some(1), Some(0), None,
+                Some(0), Some(1), None,
+                // a brace on its own line stays with the statement it is for
+                Some(1), Some(2), Some(2), Some(3), Some(2), Some(1), None,
+                // badly indented lines get the level they should have
+                Some(1), Some(2), Some(1), None,
+                Some(1), Some(2), Some(1), None,
+                Some(1), Some(1), Some(2), Some(1),
+                Some(0), None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_line_level() {
+        let mut lang = Language::for_file("test.java");
+        let tree = lang.parser.parse(JAVA, None).unwrap();
+        let query = lang.indent_query.as_ref().unwrap();
+        let rope = Rope::from_str(JAVA);
+        let level_after = |text: &str| {
+            let byte = JAVA.find(text).unwrap() + text.len();
+            query.new_line_level(tree.root_node(), &rope, byte)
+        };
+
+        // opening a block indents the next line, closing one doesn't
+        assert_eq!(level_after("public boolean test1() {"), Some(2));
+        assert_eq!(level_after("return false;"), Some(2));
+        assert_eq!(level_after("return false;\n    }"), Some(1));
+        assert_eq!(level_after("public void test3()"), Some(1));
+        assert_eq!(level_after("    return;\n    }\n}"), Some(0));
+
+        // inside an unclosed parenthesis
+        assert_eq!(level_after("System.out.println("), Some(4));
+    }
+
+    #[test]
+    fn test_queries_are_valid() {
+        for config in LanguageConfig::all() {
+            assert!(
+                IndentQuery::new(&config.tree_sitter(), config.indent_query).is_some(),
+                "invalid indent query for {}",
+                config.name
+            );
+        }
+    }
+}
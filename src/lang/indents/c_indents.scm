@@ -0,0 +1,19 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (compound_statement)
+  (field_declaration_list)
+  (declaration_list)
+  (enumerator_list)
+  (initializer_list)
+  (case_statement)
+  (argument_list)
+  (parameter_list)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
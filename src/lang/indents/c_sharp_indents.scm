@@ -0,0 +1,21 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (declaration_list)
+  (enum_member_declaration_list)
+  (accessor_list)
+  (block)
+  (switch_body)
+  (switch_section)
+  (switch_expression)
+  (initializer_expression)
+  (argument_list)
+  (parameter_list)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,23 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (class_body)
+  (interface_body)
+  (enum_body)
+  (annotation_type_body)
+  (constructor_body)
+  (block)
+  (switch_block)
+  (switch_block_statement_group)
+  (argument_list)
+  (formal_parameters)
+  (array_initializer)
+  (element_value_array_initializer)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,33 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (class_definition)
+  (function_definition)
+  (if_statement)
+  (for_statement)
+  (while_statement)
+  (try_statement)
+  (with_statement)
+  (match_statement)
+  (case_clause)
+  (argument_list)
+  (parameters)
+  (list)
+  (dictionary)
+  (set)
+  (tuple)
+  (parenthesized_expression)
+  (list_comprehension)
+  (dictionary_comprehension)
+] @indent
+
+[
+  (elif_clause)
+  (else_clause)
+  (except_clause)
+  (finally_clause)
+  ")"
+  "]"
+  "}"
+] @outdent
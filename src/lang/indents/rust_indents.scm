@@ -0,0 +1,25 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (ordered_field_declaration_list)
+  (enum_variant_list)
+  (field_initializer_list)
+  (match_block)
+  (match_arm)
+  (use_list)
+  (parameters)
+  (arguments)
+  (token_tree)
+  (array_expression)
+  (tuple_expression)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
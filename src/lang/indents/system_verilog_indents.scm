@@ -0,0 +1,21 @@
+; @indent: lines after the first line of the node are indented one more level
+; @outdent: a line starting with the node is indented one less level
+
+[
+  (module_declaration)
+  (class_declaration)
+  (function_declaration)
+  (task_declaration)
+  (case_statement)
+  (seq_block)
+] @indent
+
+[
+  "end"
+  "endmodule"
+  "endclass"
+  "endfunction"
+  "endtask"
+  "endcase"
+  ")"
+] @outdent
@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use log::error;
+use ropey::Rope;
+use tree_sitter::{Node, Query, QueryCursor, StreamingIterator};
+
+use super::highlighter::RopeProvider;
+
+/// Finds local variables and their references using a tree-sitter query (in the style of a `locals.scm` file).
+///
+/// The query uses the captures of the highlighter's locals queries:
+/// - `@local.scope`: a node that variables are defined in. References look in the enclosing scopes
+///   unless the pattern sets `local.scope-inherits` to false.
+/// - `@local.definition`: the name of a variable where it is defined
+/// - `@local.reference`: a name that can refer to a variable
+/// - `@local.ignore`: a name that never refers to a variable, even if matched as a reference (like a field)
+pub struct LocalsQuery {
+    query: Query,
+    scope_idx: u32,
+    definition_idx: u32,
+    reference_idx: u32,
+    ignore_idx: Option<u32>,
+}
+
+/// A local variable and every place its name is used, as byte ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariable {
+    /// where the variable is first defined
+    pub definition: Range<usize>,
+
+    /// every use of the name, including the definitions, in order
+    pub references: Vec<Range<usize>>,
+}
+
+struct Scope {
+    range: Range<usize>,
+    inherits: bool,
+    parent: Option<usize>,
+
+    /// the variables defined in the scope by name.
+    /// Defining a name again in the same scope (like reassigning it) is the same variable.
+    variables: HashMap<String, usize>,
+}
+
+/// A name used as a reference, and the variable it refers to (if it is a local variable)
+struct Reference {
+    name: String,
+    scope: usize,
+    variable: Option<usize>,
+}
+
+/// The scopes and variables of a tree, with the scope each variable is defined in
+struct Resolved {
+    scopes: Vec<Scope>,
+    variables: Vec<LocalVariable>,
+    variable_scopes: Vec<usize>,
+    references: Vec<Reference>,
+}
+
+impl LocalsQuery {
+    /// Returns None if the query is invalid or is missing a capture
+    pub fn new(language: &tree_sitter::Language, source: &str) -> Option<Self> {
+        if source.is_empty() {
+            return None;
+        }
+        let query = match Query::new(language, source) {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Invalid locals query: {err}");
+                return None;
+            }
+        };
+
+        Some(LocalsQuery {
+            scope_idx: query.capture_index_for_name("local.scope")?,
+            definition_idx: query.capture_index_for_name("local.definition")?,
+            reference_idx: query.capture_index_for_name("local.reference")?,
+            ignore_idx: query.capture_index_for_name("local.ignore"),
+            query,
+        })
+    }
+
+    /// The local variable with a definition or reference at the byte offset (including at its end)
+    pub fn variable_at(&self, root: Node, source: &Rope, byte: usize) -> Option<LocalVariable> {
+        self.variables(root, source).into_iter().find(|variable| {
+            variable
+                .references
+                .iter()
+                .any(|range| range.start <= byte && byte <= range.end)
+        })
+    }
+
+    /// Every local variable in the tree
+    pub fn variables(&self, root: Node, source: &Rope) -> Vec<LocalVariable> {
+        self.resolve(root, source).variables
+    }
+
+    /// If renaming the local variable at the byte offset to the name would change what a name refers to:
+    /// another variable with the name is defined in the same scope, one in a closer scope would take over
+    /// its references, or a reference to the name (like a global or builtin) would find it instead
+    pub fn name_conflicts(&self, root: Node, source: &Rope, byte: usize, name: &str) -> bool {
+        let resolved = self.resolve(root, source);
+        let Some(target) = resolved.variables.iter().position(|variable| {
+            variable
+                .references
+                .iter()
+                .any(|range| range.start <= byte && byte <= range.end)
+        }) else {
+            return false;
+        };
+        let target_scope = resolved.variable_scopes[target];
+        if resolved.scopes[target_scope]
+            .variables
+            .get(name)
+            .is_some_and(|&variable| variable != target)
+        {
+            return true;
+        }
+
+        resolved.references.iter().any(|reference| {
+            let visible = resolved.visible_scopes(reference.scope);
+            if reference.variable == Some(target) {
+                visible
+                    .take_while(|&idx| idx != target_scope)
+                    .any(|idx| resolved.scopes[idx].variables.contains_key(name))
+            } else if reference.name == name {
+                let found = reference
+                    .variable
+                    .map(|variable| resolved.variable_scopes[variable]);
+                visible
+                    .take_while(|&idx| Some(idx) != found)
+                    .any(|idx| idx == target_scope)
+            } else {
+                false
+            }
+        })
+    }
+
+    fn resolve(&self, root: Node, source: &Rope) -> Resolved {
+        let mut scopes = vec![Scope {
+            range: 0..usize::MAX,
+            inherits: false,
+            parent: None,
+            variables: HashMap::new(),
+        }];
+        let mut scope_stack = vec![0];
+        let mut variables: Vec<LocalVariable> = vec![];
+        let mut variable_scopes = vec![];
+        let mut references: Vec<(Range<usize>, usize)> = vec![];
+        let mut not_references: HashSet<Range<usize>> = HashSet::new();
+
+        // captures come in order of where they start, so scopes can be tracked with a stack
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(&self.query, root, RopeProvider(source.slice(..)));
+        while let Some((query_match, capture_idx)) = captures.next() {
+            let capture = query_match.captures[*capture_idx];
+            let range = capture.node.byte_range();
+            while range.start >= scopes[*scope_stack.last().unwrap()].range.end {
+                scope_stack.pop();
+            }
+            let scope_idx = *scope_stack.last().unwrap();
+
+            if capture.index == self.scope_idx {
+                scopes.push(Scope {
+                    range,
+                    inherits: scope_inherits(&self.query, query_match.pattern_index),
+                    parent: Some(scope_idx),
+                    variables: HashMap::new(),
+                });
+                scope_stack.push(scopes.len() - 1);
+            } else if capture.index == self.definition_idx {
+                let name = source.byte_slice(range.clone()).to_string();
+                match scopes[scope_idx].variables.get(&name) {
+                    Some(&variable) => variables[variable].references.push(range.clone()),
+                    None => {
+                        scopes[scope_idx].variables.insert(name, variables.len());
+                        variables.push(LocalVariable {
+                            definition: range.clone(),
+                            references: vec![range.clone()],
+                        });
+                        variable_scopes.push(scope_idx);
+                    }
+                }
+                not_references.insert(range);
+            } else if Some(capture.index) == self.ignore_idx {
+                not_references.insert(range);
+            } else if capture.index == self.reference_idx {
+                references.push((range, scope_idx));
+            }
+        }
+
+        let mut resolved = Resolved {
+            scopes,
+            variables,
+            variable_scopes,
+            references: vec![],
+        };
+
+        // look up references once every definition is known, so ones used before they're
+        // defined (like a global used in a function above it) are still found
+        for (range, scope) in references {
+            if not_references.contains(&range) {
+                continue;
+            }
+            let name = source.byte_slice(range.clone()).to_string();
+            let variable = resolved
+                .visible_scopes(scope)
+                .find_map(|idx| resolved.scopes[idx].variables.get(&name).copied());
+            if let Some(variable) = variable {
+                resolved.variables[variable].references.push(range.clone());
+            }
+            resolved.references.push(Reference {
+                name,
+                scope,
+                variable,
+            });
+        }
+
+        for variable in &mut resolved.variables {
+            variable.references.sort_by_key(|range| range.start);
+            variable.references.dedup();
+        }
+        resolved
+    }
+}
+
+impl Resolved {
+    /// The scope and the enclosing scopes a reference in it can see, closest first
+    fn visible_scopes(&self, scope: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(scope), |&idx| {
+            self.scopes[idx]
+                .parent
+                .filter(|_| self.scopes[idx].inherits)
+        })
+    }
+}
+
+/// If references in a `@local.scope` from the pattern can refer to variables in the enclosing scopes,
+/// which they can unless the pattern sets `local.scope-inherits` to false.
+/// Shared with the highlighter, so both find the same variables.
+pub(super) fn scope_inherits(query: &Query, pattern_index: usize) -> bool {
+    query
+        .property_settings(pattern_index)
+        .iter()
+        .find(|prop| prop.key.as_ref() == "local.scope-inherits")
+        .is_none_or(|prop| prop.value.as_deref().is_none_or(|v| v == "true"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::{config::LanguageConfig, Language};
+
+    fn find_variable(file: &str, source: &str, at: &str) -> Option<Vec<String>> {
+        let mut lang = Language::for_file(file);
+        let tree = lang.parser.parse(source, None).unwrap();
+        let query = lang.locals_query.as_ref().unwrap();
+        let rope = Rope::from_str(source);
+        let variable = query.variable_at(tree.root_node(), &rope, source.find(at).unwrap())?;
+        Some(
+            variable
+                .references
+                .iter()
+                .map(|range| format!("{}:{}", range.start, &source[range.clone()]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_python_locals() {
+        let source = "x = 1\ndef f(x):\n    y = x + 1\n    return y\nprint(x)\n";
+
+        // the parameter shadows the global inside the function
+        assert_eq!(
+            find_variable("test.py", source, "x):"),
+            Some(vec!["12:x".into(), "24:x".into()])
+        );
+        assert_eq!(
+            find_variable("test.py", source, "x = 1"),
+            Some(vec!["0:x".into(), "49:x".into()])
+        );
+
+        // names that aren't variables have none
+        assert_eq!(find_variable("test.py", source, "print"), None);
+
+        // attributes and keyword arguments with the same name are left out
+        let source = "x = 1\nobj.x = x\nf(x=x)\n";
+        assert_eq!(
+            find_variable("test.py", source, "x = 1"),
+            Some(vec!["0:x".into(), "14:x".into(), "20:x".into()])
+        );
+    }
+
+    #[test]
+    fn test_rust_locals() {
+        let source =
+            "fn f(a: i32) -> i32 {\n    let b = a * 2;\n    let c = |a| a + b;\n    b + c(a)\n}\n";
+
+        // the closure's parameter is a different variable
+        assert_eq!(
+            find_variable("test.rs", source, "a: i32"),
+            Some(vec!["5:a".into(), "34:a".into(), "74:a".into()])
+        );
+        assert_eq!(
+            find_variable("test.rs", source, "b ="),
+            Some(vec!["30:b".into(), "61:b".into(), "68:b".into()])
+        );
+    }
+
+    #[test]
+    fn test_name_conflicts() {
+        let source = "x = 1\ndef f(a):\n    b = a + x\n    return b\nprint(f(2))\n";
+        let mut lang = Language::for_file("test.py");
+        let tree = lang.parser.parse(source, None).unwrap();
+        let query = lang.locals_query.as_ref().unwrap();
+        let rope = Rope::from_str(source);
+        let conflicts = |at: &str, name: &str| {
+            query.name_conflicts(tree.root_node(), &rope, source.find(at).unwrap(), name)
+        };
+
+        assert!(!conflicts("a):", "y"));
+        assert!(!conflicts("a):", "a"));
+        assert!(!conflicts("b =", "c"));
+
+        // defined in the same scope
+        assert!(conflicts("a):", "b"));
+
+        // the parameter would hide the global used in the function
+        assert!(conflicts("a):", "x"));
+        assert!(conflicts("x = 1", "a"));
+
+        // a builtin used where the variable can be seen
+        assert!(conflicts("x = 1", "print"));
+    }
+
+    #[test]
+    fn test_queries_are_valid() {
+        for file in ["a.py", "a.java", "a.cs", "a.c", "a.cpp", "a.rs"] {
+            let config = LanguageConfig::for_file(file);
+            assert!(
+                LocalsQuery::new(&config.tree_sitter(), config.locals_query).is_some(),
+                "invalid locals query for {}",
+                config.name
+            );
+        }
+    }
+}
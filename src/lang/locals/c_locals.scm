@@ -0,0 +1,22 @@
+; @local.scope: a node that variables are defined in
+; @local.definition: the name of a variable where it is defined
+; @local.reference: a name that can refer to a variable
+; @local.ignore: a name that never refers to a variable, even if matched as a reference (like a field)
+
+[
+  (function_definition)
+  (compound_statement)
+  (for_statement)
+] @local.scope
+
+(parameter_declaration declarator: (identifier) @local.definition)
+(parameter_declaration declarator: (pointer_declarator declarator: (identifier) @local.definition))
+(parameter_declaration declarator: (array_declarator declarator: (identifier) @local.definition))
+(declaration declarator: (identifier) @local.definition)
+(declaration declarator: (init_declarator declarator: (identifier) @local.definition))
+(declaration declarator: (init_declarator declarator: (pointer_declarator declarator: (identifier) @local.definition)))
+(declaration declarator: (init_declarator declarator: (array_declarator declarator: (identifier) @local.definition)))
+(declaration declarator: (pointer_declarator declarator: (identifier) @local.definition))
+(declaration declarator: (array_declarator declarator: (identifier) @local.definition))
+
+(identifier) @local.reference
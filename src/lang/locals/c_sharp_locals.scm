@@ -0,0 +1,28 @@
+; @local.scope: a node that variables are defined in
+; @local.definition: the name of a variable where it is defined
+; @local.reference: a name that can refer to a variable
+; @local.ignore: a name that never refers to a variable, even if matched as a reference (like a field)
+
+[
+  (method_declaration)
+  (constructor_declaration)
+  (local_function_statement)
+  (lambda_expression)
+  (block)
+  (for_statement)
+  (foreach_statement)
+  (catch_clause)
+  (using_statement)
+] @local.scope
+
+(parameter name: (identifier) @local.definition)
+(lambda_expression parameters: (implicit_parameter) @local.definition)
+(variable_declarator name: (identifier) @local.definition)
+(foreach_statement left: (identifier) @local.definition)
+(catch_declaration name: (identifier) @local.definition)
+
+(method_declaration name: (identifier) @local.ignore)
+(class_declaration name: (identifier) @local.ignore)
+(member_access_expression name: (identifier) @local.ignore)
+
+(identifier) @local.reference
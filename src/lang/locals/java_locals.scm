@@ -0,0 +1,31 @@
+; @local.scope: a node that variables are defined in
+; @local.definition: the name of a variable where it is defined
+; @local.reference: a name that can refer to a variable
+; @local.ignore: a name that never refers to a variable, even if matched as a reference (like a field)
+
+[
+  (method_declaration)
+  (constructor_declaration)
+  (lambda_expression)
+  (block)
+  (for_statement)
+  (enhanced_for_statement)
+  (catch_clause)
+  (try_with_resources_statement)
+] @local.scope
+
+(formal_parameter name: (identifier) @local.definition)
+(spread_parameter (variable_declarator name: (identifier) @local.definition))
+(lambda_expression parameters: (identifier) @local.definition)
+(inferred_parameters (identifier) @local.definition)
+(local_variable_declaration declarator: (variable_declarator name: (identifier) @local.definition))
+(enhanced_for_statement name: (identifier) @local.definition)
+(catch_formal_parameter name: (identifier) @local.definition)
+(resource name: (identifier) @local.definition)
+
+(method_declaration name: (identifier) @local.ignore)
+(class_declaration name: (identifier) @local.ignore)
+(field_access field: (identifier) @local.ignore)
+(method_invocation name: (identifier) @local.ignore)
+
+(identifier) @local.reference
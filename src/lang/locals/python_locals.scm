@@ -0,0 +1,47 @@
+; @local.scope: a node that variables are defined in
+; @local.definition: the name of a variable where it is defined
+; @local.reference: a name that can refer to a variable
+; @local.ignore: a name that never refers to a variable, even if matched as a reference (like a field)
+
+[
+  (function_definition)
+  (lambda)
+  (class_definition)
+  (list_comprehension)
+  (dictionary_comprehension)
+  (set_comprehension)
+  (generator_expression)
+] @local.scope
+
+; parameters
+(parameters (identifier) @local.definition)
+(lambda_parameters (identifier) @local.definition)
+(default_parameter name: (identifier) @local.definition)
+(typed_parameter (identifier) @local.definition)
+(typed_default_parameter name: (identifier) @local.definition)
+(list_splat_pattern (identifier) @local.definition)
+(dictionary_splat_pattern (identifier) @local.definition)
+
+; assignments and loops
+(assignment left: (identifier) @local.definition)
+(assignment left: (pattern_list (identifier) @local.definition))
+(assignment left: (tuple_pattern (identifier) @local.definition))
+(for_statement left: (identifier) @local.definition)
+(for_statement left: (pattern_list (identifier) @local.definition))
+(for_statement left: (tuple_pattern (identifier) @local.definition))
+(for_in_clause left: (identifier) @local.definition)
+(for_in_clause left: (pattern_list (identifier) @local.definition))
+(named_expression name: (identifier) @local.definition)
+(as_pattern alias: (as_pattern_target (identifier) @local.definition))
+
+; imports
+(aliased_import alias: (identifier) @local.definition)
+(import_statement name: (dotted_name . (identifier) @local.definition))
+(import_from_statement name: (dotted_name (identifier) @local.definition))
+
+(function_definition name: (identifier) @local.ignore)
+(class_definition name: (identifier) @local.ignore)
+(attribute attribute: (identifier) @local.ignore)
+(keyword_argument name: (identifier) @local.ignore)
+
+(identifier) @local.reference
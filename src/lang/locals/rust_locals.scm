@@ -0,0 +1,40 @@
+; @local.scope: a node that variables are defined in
+; @local.definition: the name of a variable where it is defined
+; @local.reference: a name that can refer to a variable
+; @local.ignore: a name that never refers to a variable, even if matched as a reference (like a field)
+
+[
+  (function_item)
+  (closure_expression)
+  (block)
+  (for_expression)
+  (match_arm)
+  (if_expression)
+  (while_expression)
+] @local.scope
+
+; parameters
+(parameter pattern: (identifier) @local.definition)
+(self_parameter (self) @local.definition)
+(closure_parameters (identifier) @local.definition)
+
+; bindings
+(let_declaration pattern: (identifier) @local.definition)
+(let_condition pattern: (identifier) @local.definition)
+(tuple_pattern (identifier) @local.definition)
+(tuple_struct_pattern (identifier) @local.definition)
+(struct_pattern (field_pattern name: (shorthand_field_identifier) @local.definition))
+(field_pattern pattern: (identifier) @local.definition)
+(reference_pattern (identifier) @local.definition)
+(mut_pattern (identifier) @local.definition)
+(ref_pattern (identifier) @local.definition)
+(captured_pattern (identifier) @local.definition)
+(for_expression pattern: (identifier) @local.definition)
+(match_pattern (identifier) @local.definition)
+
+(scoped_identifier path: (identifier) @local.ignore)
+(scoped_identifier name: (identifier) @local.ignore)
+(function_item name: (identifier) @local.ignore)
+
+(identifier) @local.reference
+(self) @local.reference
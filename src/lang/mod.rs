@@ -1,5 +1,8 @@
 pub mod config;
 pub mod highlighter;
+pub mod indenter;
+pub mod locals;
+pub mod tags;
 pub mod tree_manager;
 
 use std::cell::RefCell;
@@ -8,10 +11,13 @@ use config::LanguageConfig;
 
 pub struct Language {
     pub config: &'static config::LanguageConfig,
-    _ts_language: tree_sitter::Language,
+    ts_language: tree_sitter::Language,
     pub parser: tree_sitter::Parser,
     pub highlighter: RefCell<highlighter::Highlighter>, // use ref cells because you can only have one mutable reference to a property of a struct at a time
     pub highlight_config: RefCell<highlighter::HighlightConfiguration>,
+    pub indent_query: Option<indenter::IndentQuery>,
+    pub locals_query: Option<locals::LocalsQuery>,
+    pub tags_query: Option<tags::TagsQuery>,
 }
 
 impl Language {
@@ -30,18 +36,29 @@ impl Language {
             config.name,
             config.highlight_query,
             "",
-            "",
+            config.locals_query,
         )
         .unwrap();
         highlight_config.configure(&config.highlight.iter().map(|x| x.0).collect::<Vec<&str>>());
 
         let highlighter = highlighter::Highlighter::new();
+        let indent_query = indenter::IndentQuery::new(&ts_language, config.indent_query);
+        let locals_query = locals::LocalsQuery::new(&ts_language, config.locals_query);
+        let tags_query = tags::TagsQuery::new(&ts_language, config.tags_query);
         Self {
             config,
-            _ts_language: ts_language,
+            ts_language,
             parser,
             highlighter: RefCell::new(highlighter),
             highlight_config: RefCell::new(highlight_config),
+            indent_query,
+            locals_query,
+            tags_query,
         }
     }
+
+    /// If the word is a keyword of the language (the grammar has a token for it)
+    pub fn is_keyword(&self, word: &str) -> bool {
+        self.ts_language.id_for_node_kind(word, false) != 0
+    }
 }
@@ -0,0 +1,187 @@
+use std::{collections::HashSet, ops::Range};
+
+use log::error;
+use ropey::Rope;
+use tree_sitter::{Node, Query, QueryCursor, StreamingIterator};
+
+use super::{config::LanguageConfig, highlighter::RopeProvider};
+use crate::block_editor::BlockType;
+
+/// Finds the definitions in a file (classes, functions, modules, etc.) using a tree-sitter query
+/// (in the style of a `tags.scm` file).
+///
+/// The query uses two captures:
+/// - `@definition.<kind>`: a definition, like `@definition.function` or `@definition.class`
+/// - `@name`: the name of the definition
+///
+/// Other captures (like `@reference.call`) are ignored.
+pub struct TagsQuery {
+    query: Query,
+    name_idx: u32,
+
+    /// the kind of definition for each capture index, if it is a definition
+    kinds: Vec<Option<String>>,
+}
+
+/// A definition found by the tags query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub name: String,
+
+    /// the kind of definition, like `function` or `class`
+    pub kind: String,
+
+    /// the block the definition is in (or just the definition, if it isn't in a block)
+    pub range: Range<usize>,
+    pub name_range: Range<usize>,
+    pub block_type: Option<BlockType>,
+}
+
+impl TagsQuery {
+    /// Returns None if the query is invalid or has no names
+    pub fn new(language: &tree_sitter::Language, source: &str) -> Option<Self> {
+        if source.is_empty() {
+            return None;
+        }
+        let query = match Query::new(language, source) {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Invalid tags query: {err}");
+                return None;
+            }
+        };
+
+        let name_idx = query.capture_index_for_name("name")?;
+        let kinds = query
+            .capture_names()
+            .iter()
+            .map(|name| name.strip_prefix("definition.").map(str::to_string))
+            .collect();
+        Some(TagsQuery {
+            query,
+            name_idx,
+            kinds,
+        })
+    }
+
+    /// The definitions in the tree, in the order they start
+    pub fn definitions(&self, root: Node, source: &Rope, lang: &LanguageConfig) -> Vec<Tag> {
+        self.definitions_in(root, source, lang, 0..usize::MAX)
+    }
+
+    /// The definitions that overlap the byte range, in the order they start
+    pub fn definitions_in(
+        &self,
+        root: Node,
+        source: &Rope,
+        lang: &LanguageConfig,
+        bytes: Range<usize>,
+    ) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = vec![];
+        let mut seen = HashSet::new();
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(bytes);
+        let mut matches = cursor.matches(&self.query, root, RopeProvider(source.slice(..)));
+        while let Some(query_match) = matches.next() {
+            let name = query_match
+                .captures
+                .iter()
+                .find(|capture| capture.index == self.name_idx);
+            let definition = query_match.captures.iter().find_map(|capture| {
+                let kind = self.kinds[capture.index as usize].as_ref()?;
+                Some((capture.node, kind))
+            });
+            let (Some(name), Some((node, kind))) = (name, definition) else {
+                continue;
+            };
+
+            // the same definition can match more than one pattern (like a method also being a function),
+            // in which case the first pattern wins
+            let block = enclosing_block(node, lang);
+            if !seen.insert(block.byte_range()) {
+                continue;
+            }
+
+            tags.push(Tag {
+                name: source.byte_slice(name.node.byte_range()).to_string(),
+                kind: kind.clone(),
+                range: block.byte_range(),
+                name_range: name.node.byte_range(),
+                block_type: lang.categorize_node(&block),
+            });
+        }
+
+        tags.sort_by_key(|tag| (tag.range.start, std::cmp::Reverse(tag.range.end)));
+        tags
+    }
+}
+
+/// The closest node that is drawn as a block, starting with the node itself.
+/// Definitions are often only part of their block (like a C function's declarator).
+fn enclosing_block<'tree>(node: Node<'tree>, lang: &LanguageConfig) -> Node<'tree> {
+    let mut curr = Some(node);
+    while let Some(candidate) = curr {
+        if lang.categorize_node(&candidate).is_some() {
+            return candidate;
+        }
+        curr = candidate.parent();
+    }
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::Language;
+
+    fn definitions(file: &str, source: &str) -> Vec<(String, String, Option<BlockType>)> {
+        let mut lang = Language::for_file(file);
+        let tree = lang.parser.parse(source, None).unwrap();
+        let query = lang.tags_query.as_ref().unwrap();
+        query
+            .definitions(tree.root_node(), &Rope::from_str(source), lang.config)
+            .into_iter()
+            .map(|tag| (tag.name, tag.kind, tag.block_type))
+            .collect()
+    }
+
+    #[test]
+    fn test_python_definitions() {
+        let source = "class A:\n    def f(self):\n        pass\n\ndef g():\n    pass\n";
+        assert_eq!(
+            definitions("test.py", source),
+            vec![
+                ("A".into(), "class".into(), Some(BlockType::Object)),
+                ("f".into(), "function".into(), Some(BlockType::FunctionDef)),
+                ("g".into(), "function".into(), Some(BlockType::FunctionDef)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_c_definitions() {
+        // the definition is the function's declarator, but it takes up the whole function's block
+        let source = "int main(void) {\n    return 0;\n}\n";
+        assert_eq!(
+            definitions("test.c", source),
+            vec![(
+                "main".into(),
+                "function".into(),
+                Some(BlockType::FunctionDef)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_queries_are_valid() {
+        for file in ["a.py", "a.java", "a.cs", "a.c", "a.cpp", "a.rs"] {
+            let lang = Language::for_file(file);
+            assert!(
+                lang.tags_query.is_some(),
+                "invalid tags query for {}",
+                lang.config.name
+            );
+        }
+    }
+}
@@ -0,0 +1,16 @@
+; @definition.<kind>: a definition to list in the outline, like a class or method
+; @name: the name of the definition
+
+(class_declaration name: (identifier) @name) @definition.class
+
+(interface_declaration name: (identifier) @name) @definition.interface
+
+(struct_declaration name: (identifier) @name) @definition.class
+
+(enum_declaration name: (identifier) @name) @definition.class
+
+(method_declaration name: (identifier) @name) @definition.method
+
+(constructor_declaration name: (identifier) @name) @definition.method
+
+(namespace_declaration name: (identifier) @name) @definition.module
@@ -1,10 +1,27 @@
-﻿use ropey::Rope;
-use tree_sitter::{InputEdit, Tree, TreeCursor};
+﻿use std::ops::Range;
+
+use ropey::Rope;
+use tree_sitter::{InputEdit, Node, Tree, TreeCursor};
 
 use super::Language;
 
 pub struct TreeManager {
     tree: Tree,
+
+    /// counts up each time the tree changes, so views of the tree know when to update
+    version: usize,
+
+    /// how the current version differs from the one before, or None if the text was replaced
+    last_change: Option<TreeChange>,
+}
+
+/// An edit to the tree, so views can update only the parts that changed
+pub struct TreeChange {
+    pub edit: InputEdit,
+
+    /// the byte ranges where the syntax is different after the edit
+    /// (besides the edit itself, since text can change without the syntax changing)
+    pub changed_ranges: Vec<Range<usize>>,
 }
 
 /* ------- Parsing  ------- */
@@ -13,6 +30,8 @@ impl TreeManager {
     pub fn new(lang: &mut Language) -> TreeManager {
         TreeManager {
             tree: lang.parser.parse("", None).unwrap(),
+            version: 0,
+            last_change: None,
         }
     }
 
@@ -20,18 +39,52 @@ impl TreeManager {
         self.tree.walk()
     }
 
+    pub fn root_node(&self) -> Node<'_> {
+        self.tree.root_node()
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// How the tree changed to reach the current version from the one before, if it was edited
+    pub fn last_change(&self) -> Option<&TreeChange> {
+        self.last_change.as_ref()
+    }
+
     pub fn replace(&mut self, source: &Rope, lang: &mut Language) {
         self.parse(source, false, lang);
+        self.last_change = None;
     }
 
     pub fn update(&mut self, source: &Rope, change: InputEdit, lang: &mut Language) {
         self.tree.edit(&change);
+        let old_tree = self.tree.clone();
         self.parse(source, true, lang);
+        self.last_change = Some(TreeChange {
+            edit: change,
+            changed_ranges: old_tree
+                .changed_ranges(&self.tree)
+                .map(|range| range.start_byte..range.end_byte)
+                .collect(),
+        });
+    }
+
+    /// Parse the source after a change without updating the tree (like to check an edit first)
+    pub fn parse_edited(&self, source: &Rope, change: InputEdit, lang: &mut Language) -> Tree {
+        let mut old_tree = self.tree.clone();
+        old_tree.edit(&change);
+        Self::parse_rope(source, Some(&old_tree), lang)
     }
 
     fn parse(&mut self, source: &Rope, use_old: bool, lang: &mut Language) {
-        self.tree = lang
-            .parser
+        let old_tree = if use_old { Some(&self.tree) } else { None };
+        self.tree = Self::parse_rope(source, old_tree, lang);
+        self.version += 1;
+    }
+
+    fn parse_rope(source: &Rope, old_tree: Option<&Tree>, lang: &mut Language) -> Tree {
+        lang.parser
             .parse_with_options(
                 &mut |byte, _| {
                     if byte <= source.len_bytes() {
@@ -42,10 +95,10 @@ impl TreeManager {
                         &[]
                     }
                 },
-                if use_old { Some(&self.tree) } else { None },
+                old_tree,
                 None,
             )
-            .unwrap();
+            .unwrap()
     }
 }
 
@@ -18,7 +18,8 @@ impl VSCodeCompletionItem {
     }
 
     pub fn color(&self) -> Color32 {
-        self.kind.map_or(theme::syntax::DEFAULT, |k| k.color())
+        self.kind
+            .map_or(theme::current().syntax.default, |k| k.color())
     }
 
     pub fn text_to_insert(&self) -> String {
@@ -109,10 +110,10 @@ impl VSCodeCompletionKind {
         use VSCodeCompletionKind::*;
 
         match self {
-            Class | Function | Method => theme::syntax::FUNCTION,
-            Constant | Variable | Property => theme::syntax::VARIABLE,
-            Keyword => theme::syntax::KEYWORD,
-            _ => theme::syntax::DEFAULT,
+            Class | Function | Method => theme::current().syntax.function,
+            Constant | Variable | Property => theme::current().syntax.variable,
+            Keyword => theme::current().syntax.keyword,
+            _ => theme::current().syntax.default,
         }
     }
 }
@@ -2,7 +2,7 @@ use egui::Color32;
 use serde::{Deserialize, Serialize};
 
 use crate::block_editor::text_range::{TextPoint, TextRange};
-use crate::vscode;
+use crate::{theme, vscode};
 
 #[derive(Deserialize, Debug, PartialEq, Clone)]
 pub struct Diagnostic {
@@ -41,14 +41,26 @@ pub enum DiagnosticSeverity {
 
 impl DiagnosticSeverity {
     pub fn color(&self) -> Color32 {
-        use crate::theme::diagnostic::*;
         use DiagnosticSeverity::*;
 
+        let colors = &theme::current().diagnostic;
         match self {
-            Error => ERROR,
-            Warning => WARNING,
-            Information => INFO,
-            Hint => HINT,
+            Error => colors.error,
+            Warning => colors.warning,
+            Information => colors.info,
+            Hint => colors.hint,
+        }
+    }
+
+    /// What the severity is called when read out by a screen reader
+    pub fn name(&self) -> &'static str {
+        use DiagnosticSeverity::*;
+
+        match self {
+            Error => "error",
+            Warning => "warning",
+            Information => "info",
+            Hint => "hint",
         }
     }
 }
@@ -1,17 +1,35 @@
 use egui::Color32;
 
-use super::one_dark;
 use crate::block_editor::BlockType;
 
 #[derive(Clone, Copy)]
 pub struct BlocksTheme {
     // Given a block type and a depth, return the color to use
     pub color_for: fn(BlockType, usize) -> Option<Color32>,
+
+    // Whether to also tell block types apart by their border pattern and a label
+    pub shape_cues: bool,
+
+    // Whether the theme draws the shape cues even when they are turned off
+    always_shape_cues: bool,
 }
 
 impl BlocksTheme {
     const fn new(color_for: fn(BlockType, usize) -> Option<Color32>) -> Self {
-        Self { color_for }
+        Self {
+            color_for,
+            shape_cues: false,
+            always_shape_cues: false,
+        }
+    }
+
+    pub fn with_shape_cues(self, shape_cues: bool) -> Self {
+        Self { shape_cues, ..self }
+    }
+
+    /// Whether to draw the border patterns and labels for this theme
+    pub fn draws_shape_cues(&self) -> bool {
+        self.shape_cues || self.always_shape_cues
     }
 
     pub fn for_str(string: &str) -> Self {
@@ -19,29 +37,41 @@ impl BlocksTheme {
             "syntax_colored" => SYNTAX_COLORED_BLOCKS,
             "depth_grayscale" => GRAYSCALE_DEPTH_BLOCKS,
             "alternating_colored" => ALTERNATING_COLORED_BLOCKS,
+            "colorblind_safe" => COLORBLIND_SAFE_BLOCKS,
             _ => SYNTAX_COLORED_BLOCKS,
         }
     }
 }
 
-static SYNTAX_COLORED_BLOCKS: BlocksTheme = BlocksTheme::new(|block_type, _| {
-    use crate::block_editor::BlockType::*;
+// colors each type of block as set by the current theme
+static SYNTAX_COLORED_BLOCKS: BlocksTheme =
+    BlocksTheme::new(|block_type, _| super::current().blocks.get(&block_type).copied());
 
-    match block_type {
-        Object => Some(Color32::from_rgb(255, 21, 21)),
-        FunctionDef => Some(Color32::from_rgb(163, 93, 213)),
-        While => Some(Color32::from_rgb(245, 163, 0)),
-        If => Some(Color32::from_rgb(103, 199, 40)),
-        For => Some(Color32::from_rgb(255, 131, 193)),
-        Try => Some(Color32::from_rgb(84, 129, 230)),
-        Always => Some(Color32::from_rgb(255, 99, 71)),
-        Switch => Some(Color32::from_rgb(255, 192, 203)),
-        Generic => Some(Color32::from_rgb(42, 189, 218)),
-        Error => Some(Color32::from_rgb(255, 0, 0)),
-        Comment => None,
-        Divider => None,
-    }
-});
+// the Okabe-Ito palette, which stays distinguishable with the common types of color blindness
+// (its black is the theme's text color so it shows on dark backgrounds, and the wine from
+// Paul Tol's muted palette makes up for it having fewer colors than there are block types).
+// the shape cues are always drawn, so the types don't depend on color alone
+static COLORBLIND_SAFE_BLOCKS: BlocksTheme = BlocksTheme {
+    always_shape_cues: true,
+    ..BlocksTheme::new(|block_type, _| {
+        use crate::block_editor::BlockType::*;
+
+        match block_type {
+            Object => Some(Color32::from_rgb(0x00, 0x72, 0xB2)),
+            FunctionDef => Some(Color32::from_rgb(0xCC, 0x79, 0xA7)),
+            While => Some(Color32::from_rgb(0xE6, 0x9F, 0x00)),
+            If => Some(Color32::from_rgb(0x00, 0x9E, 0x73)),
+            For => Some(Color32::from_rgb(0x56, 0xB4, 0xE9)),
+            Try => Some(Color32::from_rgb(0xF0, 0xE4, 0x42)),
+            Always => Some(Color32::from_rgb(0x88, 0x22, 0x55)),
+            Switch => Some(super::current().editor.interface_text),
+            Generic => Some(Color32::from_rgb(0x99, 0x99, 0x99)),
+            Error => Some(Color32::from_rgb(0xD5, 0x5E, 0x00)),
+            Comment => None,
+            Divider => None,
+        }
+    })
+};
 
 static GRAYSCALE_DEPTH_BLOCKS: BlocksTheme = BlocksTheme::new(|block_type, depth| {
     if block_type == BlockType::Divider || block_type == BlockType::Comment {
@@ -63,9 +93,47 @@ static ALTERNATING_COLORED_BLOCKS: BlocksTheme = BlocksTheme::new(|block_type, d
         return None;
     }
 
+    let syntax = &super::current().syntax;
     match depth % 2 {
-        0 => Some(one_dark::BLUE),
-        1 => Some(one_dark::GREEN),
+        0 => Some(syntax.function),
+        1 => Some(syntax.string),
         _ => unreachable!("mod 2"),
     }
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_editor::BlockType::*;
+
+    #[test]
+    fn test_colorblind_safe_colors_are_distinct() {
+        let colors: Vec<Color32> = [
+            Object,
+            FunctionDef,
+            While,
+            If,
+            For,
+            Try,
+            Always,
+            Switch,
+            Generic,
+            Error,
+            Comment,
+            Divider,
+        ]
+        .into_iter()
+        .filter_map(|block_type| (COLORBLIND_SAFE_BLOCKS.color_for)(block_type, 0))
+        .collect();
+        assert_eq!(colors.len(), 10);
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[i + 1..].contains(color), "{color:?} is used twice");
+        }
+
+        // the shape cues can't be turned off for it
+        assert!(COLORBLIND_SAFE_BLOCKS
+            .with_shape_cues(false)
+            .draws_shape_cues());
+        assert!(!SYNTAX_COLORED_BLOCKS.draws_shape_cues());
+    }
+}
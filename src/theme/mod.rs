@@ -1,68 +1,411 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, RwLock},
+};
+
 use egui::Color32;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::block_editor::BlockType;
 
 pub mod blocks_theme;
+pub mod vscode_theme;
+
+/// The colors used to draw the editor, loaded from a theme file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub name: String,
+
+    /// whether the theme has a dark background (used for the egui widgets)
+    pub dark: bool,
+
+    pub editor: EditorColors,
+    pub syntax: SyntaxColors,
+    pub diagnostic: DiagnosticColors,
+
+    /// the color of each type of block, types without a color have no outline
+    #[serde(deserialize_with = "block_colors")]
+    pub blocks: HashMap<BlockType, Color32>,
+}
+
+/// Colors of the editor chrome
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EditorColors {
+    #[serde(deserialize_with = "color")]
+    pub interface_text: Color32,
+    #[serde(deserialize_with = "color")]
+    pub background: Color32,
+    #[serde(deserialize_with = "color")]
+    pub cursor: Color32,
+    #[serde(deserialize_with = "color")]
+    pub selection: Color32,
+    #[serde(deserialize_with = "color")]
+    pub search_result: Color32,
+    #[serde(deserialize_with = "color")]
+    pub search_result_selected: Color32,
+    #[serde(deserialize_with = "color")]
+    pub pseudo_selection: Color32,
+    #[serde(deserialize_with = "color")]
+    pub popup_background: Color32,
+    #[serde(deserialize_with = "color")]
+    pub line_numbers: Color32,
+    #[serde(deserialize_with = "color")]
+    pub breakpoint: Color32,
+    #[serde(deserialize_with = "color")]
+    pub preview_breakpoint: Color32,
+    #[serde(deserialize_with = "color")]
+    pub stack_frame_selected: Color32,
+    #[serde(deserialize_with = "color")]
+    pub stack_frame_deepest: Color32,
+}
+
+/// The kinds of syntax that get their own color.
+/// Highlight captures are mapped to these in `LanguageConfig::highlight`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyntaxColor {
+    Function,
+    FunctionBuiltIn,
+    Keyword,
+    Operator,
+    Property,
+    InterpolationSurrounding,
+    String,
+    Type,
+    Variable,
+    Constructor,
+    Constant,
+    Literal,
+    EscapeSequence,
+    Comment,
+    Default,
+}
+
+/// Colors of the syntax highlighting
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyntaxColors {
+    #[serde(deserialize_with = "color")]
+    pub function: Color32,
+    #[serde(deserialize_with = "color")]
+    pub function_built_in: Color32,
+    #[serde(deserialize_with = "color")]
+    pub keyword: Color32,
+    #[serde(deserialize_with = "color")]
+    pub operator: Color32,
+    #[serde(deserialize_with = "color")]
+    pub property: Color32,
+    #[serde(deserialize_with = "color")]
+    pub interpolation_surrounding: Color32,
+    #[serde(deserialize_with = "color")]
+    pub string: Color32,
+    #[serde(deserialize_with = "color")]
+    pub r#type: Color32,
+    #[serde(deserialize_with = "color")]
+    pub variable: Color32,
+    #[serde(deserialize_with = "color")]
+    pub constructor: Color32,
+    #[serde(deserialize_with = "color")]
+    pub constant: Color32,
+    #[serde(deserialize_with = "color")]
+    pub literal: Color32,
+    #[serde(deserialize_with = "color")]
+    pub escape_sequence: Color32,
+    #[serde(deserialize_with = "color")]
+    pub comment: Color32,
+    #[serde(deserialize_with = "color")]
+    pub default: Color32,
+}
+
+impl SyntaxColors {
+    pub fn get(&self, syntax: SyntaxColor) -> Color32 {
+        use SyntaxColor::*;
 
-pub mod syntax {
-    use egui::Color32;
-
-    use super::one_dark::*;
-
-    pub const FUNCTION: Color32 = BLUE;
-    pub const FUNCTION_BUILT_IN: Color32 = CYAN;
-    pub const KEYWORD: Color32 = MAGENTA;
-    pub const OPERATOR: Color32 = WHITE;
-    pub const PROPERTY: Color32 = LIGHT_RED;
-    pub const INTERPOLATION_SURROUNDING: Color32 = LIGHT_YELLOW;
-    pub const STRING: Color32 = GREEN;
-    pub const TYPE: Color32 = LIGHT_YELLOW;
-    pub const VARIABLE: Color32 = LIGHT_RED;
-    pub const CONSTRUCTOR: Color32 = BLUE;
-    pub const CONSTANT: Color32 = DARK_YELLOW;
-    pub const LITERAL: Color32 = DARK_YELLOW;
-    pub const ESCAPE_SEQUENCE: Color32 = CYAN;
-    pub const COMMENT: Color32 = COMMENT_GREY;
-
-    pub const DEFAULT: Color32 = WHITE;
-}
-
-pub mod diagnostic {
-    use egui::Color32;
-
-    pub const ERROR: Color32 = Color32::from_rgb(0xC2, 0x40, 0x38);
-    pub const WARNING: Color32 = Color32::from_rgb(0xD1, 0x9A, 0x66);
-    pub const INFO: Color32 = Color32::from_rgb(0x37, 0x94, 0xFF);
-    pub const HINT: Color32 = Color32::from_rgb(0xA0, 0xA0, 0xA0);
-}
-
-pub const INTERFACE_TEXT: Color32 = one_dark::WHITE;
-pub const BACKGROUND: Color32 = one_dark::BLACK;
-pub const CURSOR: Color32 = Color32::from_rgb(0x52, 0x8B, 0xFF);
-pub const SELECTION: Color32 = Color32::from_rgba_premultiplied(0x56, 0x63, 0x78, 0x40);
-pub const SEARCH_RESULT: Color32 = Color32::from_rgb(0x3D, 0x45, 0x55);
-pub const SEARCH_RESULT_SELECTED: Color32 = Color32::from_rgb(0x97, 0x91, 0x8E);
-pub const PSEUDO_SELECTION: Color32 = Color32::from_rgba_premultiplied(0x32, 0x09, 0x03, 0x50);
-pub const POPUP_BACKGROUND: Color32 = Color32::from_rgb(0x1E, 0x22, 0x27);
-pub const LINE_NUMBERS: Color32 = one_dark::GUTTER_GREY;
-
-pub const BREAKPOINT: Color32 = Color32::from_rgb(0xFF, 0x45, 0x45);
-pub const PREVIEW_BREAKPOINT: Color32 = Color32::from_rgb(0x71, 0x1F, 0x1C);
-pub const STACK_FRAME_SELECTED: Color32 = Color32::from_rgb(0x00, 0xFF, 0x00);
-pub const STACK_FRAME_DEEPEST: Color32 = Color32::from_rgb(0xFF, 0xFF, 0x00);
+        match syntax {
+            Function => self.function,
+            FunctionBuiltIn => self.function_built_in,
+            Keyword => self.keyword,
+            Operator => self.operator,
+            Property => self.property,
+            InterpolationSurrounding => self.interpolation_surrounding,
+            String => self.string,
+            Type => self.r#type,
+            Variable => self.variable,
+            Constructor => self.constructor,
+            Constant => self.constant,
+            Literal => self.literal,
+            EscapeSequence => self.escape_sequence,
+            Comment => self.comment,
+            Default => self.default,
+        }
+    }
 
+    pub fn get_mut(&mut self, syntax: SyntaxColor) -> &mut Color32 {
+        use SyntaxColor::*;
+
+        match syntax {
+            Function => &mut self.function,
+            FunctionBuiltIn => &mut self.function_built_in,
+            Keyword => &mut self.keyword,
+            Operator => &mut self.operator,
+            Property => &mut self.property,
+            InterpolationSurrounding => &mut self.interpolation_surrounding,
+            String => &mut self.string,
+            Type => &mut self.r#type,
+            Variable => &mut self.variable,
+            Constructor => &mut self.constructor,
+            Constant => &mut self.constant,
+            Literal => &mut self.literal,
+            EscapeSequence => &mut self.escape_sequence,
+            Comment => &mut self.comment,
+            Default => &mut self.default,
+        }
+    }
+}
+
+/// Colors of diagnostics by severity
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiagnosticColors {
+    #[serde(deserialize_with = "color")]
+    pub error: Color32,
+    #[serde(deserialize_with = "color")]
+    pub warning: Color32,
+    #[serde(deserialize_with = "color")]
+    pub info: Color32,
+    #[serde(deserialize_with = "color")]
+    pub hint: Color32,
+}
+
+/// The format of a theme file
 #[allow(dead_code)]
-mod one_dark {
-    use egui::Color32;
-
-    pub const BLACK: Color32 = Color32::from_rgb(40, 44, 52);
-    pub const WHITE: Color32 = Color32::from_rgb(171, 178, 191);
-    pub const LIGHT_RED: Color32 = Color32::from_rgb(224, 108, 117);
-    pub const DARK_RED: Color32 = Color32::from_rgb(190, 80, 70);
-    pub const GREEN: Color32 = Color32::from_rgb(152, 195, 121);
-    pub const LIGHT_YELLOW: Color32 = Color32::from_rgb(229, 192, 123);
-    pub const DARK_YELLOW: Color32 = Color32::from_rgb(209, 154, 102);
-    pub const BLUE: Color32 = Color32::from_rgb(97, 175, 239);
-    pub const MAGENTA: Color32 = Color32::from_rgb(198, 120, 221);
-    pub const CYAN: Color32 = Color32::from_rgb(86, 182, 194);
-    pub const GUTTER_GREY: Color32 = Color32::from_rgb(76, 82, 99);
-    pub const COMMENT_GREY: Color32 = Color32::from_rgb(92, 99, 112);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeFormat {
+    Json,
+    Toml,
+}
+
+#[allow(dead_code)]
+impl ThemeFormat {
+    /// The format of a theme file based on its extension (JSON if it isn't TOML)
+    pub fn for_file(file_name: &str) -> Self {
+        if file_name.to_lowercase().ends_with(".toml") {
+            ThemeFormat::Toml
+        } else {
+            ThemeFormat::Json
+        }
+    }
+}
+
+const DARK: &str = include_str!("themes/dark.toml");
+const LIGHT: &str = include_str!("themes/light.toml");
+const HIGH_CONTRAST: &str = include_str!("themes/high_contrast.toml");
+
+impl Theme {
+    /// Parse a theme file
+    pub fn parse(contents: &str, format: ThemeFormat) -> Result<Theme, String> {
+        match format {
+            ThemeFormat::Json => serde_json::from_str(contents).map_err(|err| err.to_string()),
+            ThemeFormat::Toml => toml::from_str(contents).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// One of the themes that comes with Lilypad (dark, light, or high_contrast)
+    pub fn built_in(name: &str) -> Option<Theme> {
+        let contents = match name {
+            "dark" => DARK,
+            "light" => LIGHT,
+            "high_contrast" => HIGH_CONTRAST,
+            _ => return None,
+        };
+        Some(Self::parse(contents, ThemeFormat::Toml).expect("built in themes are valid"))
+    }
+
+    pub fn dark() -> Theme {
+        Self::built_in("dark").unwrap()
+    }
+}
+
+static CURRENT: LazyLock<RwLock<Arc<Theme>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Theme::dark())));
+
+/// The theme the editor is currently drawn with
+pub fn current() -> Arc<Theme> {
+    CURRENT.read().unwrap().clone()
+}
+
+/// Switch the theme, the new colors are used from the next time anything is drawn
+pub fn set_current(theme: Theme) {
+    *CURRENT.write().unwrap() = Arc::new(theme);
+}
+
+/// A color in a theme file, either a hex color like `#RRGGBB` or `#RRGGBBAA`,
+/// or `{ premultiplied = "#RRGGBBAA" }` for one whose channels are already multiplied by its alpha
+/// (which can be brighter than any hex color with the same alpha)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeColor {
+    Hex(String),
+    Premultiplied { premultiplied: String },
+}
+
+impl ThemeColor {
+    fn parse<E: de::Error>(self) -> Result<Color32, E> {
+        let (hex, color) = match self {
+            ThemeColor::Hex(hex) => {
+                let color = parse_hex(&hex);
+                (hex, color)
+            }
+            ThemeColor::Premultiplied { premultiplied } => {
+                let color = hex_channels(&premultiplied)
+                    .map(|[r, g, b, a]| Color32::from_rgba_premultiplied(r, g, b, a));
+                (premultiplied, color)
+            }
+        };
+        color.ok_or_else(|| E::custom(format!("invalid color {hex:?}")))
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+    ThemeColor::deserialize(deserializer)?.parse()
+}
+
+fn block_colors<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<BlockType, Color32>, D::Error> {
+    let colors = HashMap::<BlockType, ThemeColor>::deserialize(deserializer)?;
+    colors
+        .into_iter()
+        .map(|(block_type, color)| Ok((block_type, color.parse()?)))
+        .collect()
+}
+
+/// Parse a hex color in any of the forms VS Code accepts (`#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`)
+pub fn parse_hex(hex: &str) -> Option<Color32> {
+    let [r, g, b, a] = hex_channels(hex)?;
+    Some(Color32::from_rgba_unmultiplied(r, g, b, a))
+}
+
+/// The red, green, blue, and alpha of a hex color (the alpha is 255 if it is left out)
+fn hex_channels(hex: &str) -> Option<[u8; 4]> {
+    let digits = hex.strip_prefix('#')?;
+    if !digits.is_ascii() {
+        return None;
+    }
+
+    // expand the short forms so each channel is two digits
+    let digits = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return None,
+    };
+    let channel = |idx: usize| u8::from_str_radix(&digits[idx * 2..idx * 2 + 2], 16).ok();
+
+    let alpha = if digits.len() == 8 { channel(3)? } else { 255 };
+    Some([channel(0)?, channel(1)?, channel(2)?, alpha])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_themes() {
+        for name in ["dark", "light", "high_contrast"] {
+            let theme = Theme::built_in(name).unwrap();
+            assert!(theme.blocks.contains_key(&BlockType::FunctionDef));
+            assert!(!theme.blocks.contains_key(&BlockType::Divider));
+        }
+        assert!(Theme::built_in("sepia").is_none());
+
+        // the same selection colors as before there were theme files
+        let dark = Theme::dark();
+        assert_eq!(
+            dark.editor.selection,
+            Color32::from_rgba_premultiplied(0x56, 0x63, 0x78, 0x40)
+        );
+        assert_eq!(
+            dark.editor.pseudo_selection,
+            Color32::from_rgba_premultiplied(0x32, 0x09, 0x03, 0x50)
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let dark = Theme::dark();
+        let json = DARK_JSON.replace("BACKGROUND", "#000000");
+        let theme = Theme::parse(&json, ThemeFormat::Json).unwrap();
+        assert_eq!(theme.editor.background, Color32::BLACK);
+        assert_eq!(theme.syntax.keyword, dark.syntax.keyword);
+        assert_eq!(
+            theme.blocks[&BlockType::If],
+            Color32::from_rgb(0x67, 0xC7, 0x28)
+        );
+
+        // invalid colors are reported
+        let json = DARK_JSON.replace("BACKGROUND", "black");
+        let err = Theme::parse(&json, ThemeFormat::Json).unwrap_err();
+        assert!(err.contains("invalid color \"black\""), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("#FF8000"), Some(Color32::from_rgb(255, 128, 0)));
+        assert_eq!(
+            parse_hex("#FF800080"),
+            Some(Color32::from_rgba_unmultiplied(255, 128, 0, 128))
+        );
+        assert_eq!(parse_hex("#F80"), Some(Color32::from_rgb(255, 136, 0)));
+        assert_eq!(parse_hex("FF8000"), None);
+        assert_eq!(parse_hex("#FF800"), None);
+        assert_eq!(parse_hex("#GG8000"), None);
+    }
+
+    const DARK_JSON: &str = r##"{
+        "name": "Dark (JSON)",
+        "dark": true,
+        "editor": {
+            "interface_text": "#ABB2BF",
+            "background": "BACKGROUND",
+            "cursor": "#528BFF",
+            "selection": "#5C6A8280",
+            "search_result": "#3D4555",
+            "search_result_selected": "#97918E",
+            "pseudo_selection": "#A01C0A50",
+            "popup_background": "#1E2227",
+            "line_numbers": "#4C5263",
+            "breakpoint": "#FF4545",
+            "preview_breakpoint": "#711F1C",
+            "stack_frame_selected": "#00FF00",
+            "stack_frame_deepest": "#FFFF00"
+        },
+        "syntax": {
+            "function": "#61AFEF",
+            "function_built_in": "#56B6C2",
+            "keyword": "#C678DD",
+            "operator": "#ABB2BF",
+            "property": "#E06C75",
+            "interpolation_surrounding": "#E5C07B",
+            "string": "#98C379",
+            "type": "#E5C07B",
+            "variable": "#E06C75",
+            "constructor": "#61AFEF",
+            "constant": "#D19A66",
+            "literal": "#D19A66",
+            "escape_sequence": "#56B6C2",
+            "comment": "#5C6370",
+            "default": "#ABB2BF"
+        },
+        "diagnostic": {
+            "error": "#C24038",
+            "warning": "#D19A66",
+            "info": "#3794FF",
+            "hint": "#A0A0A0"
+        },
+        "blocks": {
+            "if": "#67C728"
+        }
+    }"##;
 }
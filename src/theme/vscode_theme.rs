@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+use serde::Deserialize;
+
+use super::{parse_hex, SyntaxColor, Theme};
+
+/// A VS Code color theme, sent by the extension so Lilypad matches the rest of the workbench
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VSCodeTheme {
+    name: String,
+
+    /// the built in theme to use for anything the VS Code theme doesn't set
+    base: String,
+
+    #[serde(default)]
+    token_colors: Vec<TokenColor>,
+
+    /// workbench colors by id, like `editor.background`
+    #[serde(default)]
+    colors: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenColor {
+    /// rules without a scope set the default colors
+    scope: Option<ScopeSelectors>,
+    settings: TokenSettings,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ScopeSelectors {
+    /// comma separated selectors
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenSettings {
+    foreground: Option<String>,
+}
+
+/// The TextMate scopes that most closely match the tree-sitter captures of each color
+/// (see `STANDARD_HIGHLIGHT`), in order of preference
+const SYNTAX_SCOPES: &[(SyntaxColor, &[&str])] = &[
+    // function
+    (SyntaxColor::Function, &["entity.name.function"]),
+    // function.builtin
+    (
+        SyntaxColor::FunctionBuiltIn,
+        &["support.function.builtin", "entity.name.function"],
+    ),
+    // keyword
+    (SyntaxColor::Keyword, &["keyword.control", "storage.type"]),
+    // operator
+    (SyntaxColor::Operator, &["keyword.operator"]),
+    // property
+    (
+        SyntaxColor::Property,
+        &["variable.other.property", "support.variable.property"],
+    ),
+    // punctuation.special
+    (
+        SyntaxColor::InterpolationSurrounding,
+        &[
+            "punctuation.definition.template-expression",
+            "punctuation.section.embedded",
+        ],
+    ),
+    // string
+    (SyntaxColor::String, &["string.quoted"]),
+    // type
+    (SyntaxColor::Type, &["entity.name.type", "support.type"]),
+    // variable
+    (SyntaxColor::Variable, &["variable.other.readwrite"]),
+    // constructor
+    (
+        SyntaxColor::Constructor,
+        &["entity.name.function.constructor", "entity.name.type.class"],
+    ),
+    // constant
+    (
+        SyntaxColor::Constant,
+        &["variable.other.constant", "constant.other"],
+    ),
+    // constant.builtin and number
+    (
+        SyntaxColor::Literal,
+        &["constant.numeric", "constant.language"],
+    ),
+    // escape
+    (SyntaxColor::EscapeSequence, &["constant.character.escape"]),
+    // comment
+    (SyntaxColor::Comment, &["comment.line"]),
+];
+
+impl VSCodeTheme {
+    /// The color of the most specific rule matching the scope.
+    /// Only simple selectors are supported, so rules for descendant scopes
+    /// (like `source.python string`) and exclusions are ignored.
+    fn scope_color(&self, scope: &str) -> Option<Color32> {
+        let mut best: Option<(usize, Color32)> = None;
+        for rule in &self.token_colors {
+            let Some(color) = rule.settings.foreground.as_deref().and_then(parse_hex) else {
+                continue;
+            };
+            for selector in rule.selectors() {
+                if !selector_matches(selector, scope) {
+                    continue;
+                }
+                // later rules win ties, like in VS Code
+                let specificity = selector.split('.').count();
+                if best.is_none_or(|(best_specificity, _)| specificity >= best_specificity) {
+                    best = Some((specificity, color));
+                }
+            }
+        }
+        best.map(|(_, color)| color)
+    }
+
+    /// The foreground of the rule without a scope
+    fn default_foreground(&self) -> Option<Color32> {
+        self.token_colors
+            .iter()
+            .rev()
+            .filter(|rule| rule.scope.is_none())
+            .find_map(|rule| rule.settings.foreground.as_deref().and_then(parse_hex))
+    }
+
+    fn color(&self, id: &str) -> Option<Color32> {
+        self.colors.get(id).and_then(|hex| parse_hex(hex))
+    }
+}
+
+impl TokenColor {
+    fn selectors(&self) -> Vec<&str> {
+        match &self.scope {
+            Some(ScopeSelectors::One(selectors)) => selectors.split(',').map(str::trim).collect(),
+            Some(ScopeSelectors::Many(selectors)) => selectors.iter().map(|s| s.trim()).collect(),
+            None => vec![],
+        }
+    }
+}
+
+/// Whether the selector is the scope or one of its parents (`string` matches `string.quoted`)
+fn selector_matches(selector: &str, scope: &str) -> bool {
+    if selector.is_empty() || selector.contains(char::is_whitespace) {
+        return false;
+    }
+    match scope.strip_prefix(selector) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+impl Theme {
+    /// Create a theme from a VS Code color theme.
+    /// Anything it doesn't set (including the block colors) comes from its built in base theme.
+    #[allow(dead_code)]
+    pub fn from_vscode(vscode: &VSCodeTheme) -> Theme {
+        let mut theme = Theme::built_in(&vscode.base).unwrap_or_else(Theme::dark);
+        theme.name = vscode.name.clone();
+
+        // syntax
+        for (syntax, scopes) in SYNTAX_SCOPES {
+            if let Some(color) = scopes.iter().find_map(|scope| vscode.scope_color(scope)) {
+                *theme.syntax.get_mut(*syntax) = color;
+            }
+        }
+        if let Some(color) = vscode
+            .color("editor.foreground")
+            .or_else(|| vscode.default_foreground())
+        {
+            theme.syntax.default = color;
+        }
+
+        // editor chrome
+        let editor = &mut theme.editor;
+        let chrome = [
+            (&mut editor.interface_text, "editor.foreground"),
+            (&mut editor.background, "editor.background"),
+            (&mut editor.cursor, "editorCursor.foreground"),
+            (&mut editor.selection, "editor.selectionBackground"),
+            (
+                &mut editor.search_result,
+                "editor.findMatchHighlightBackground",
+            ),
+            (
+                &mut editor.search_result_selected,
+                "editor.findMatchBackground",
+            ),
+            (&mut editor.popup_background, "editorWidget.background"),
+            (&mut editor.line_numbers, "editorLineNumber.foreground"),
+            (&mut editor.breakpoint, "debugIconBreakpointForeground"),
+        ];
+        for (color, id) in chrome {
+            if let Some(vscode_color) = vscode.color(id) {
+                *color = vscode_color;
+            }
+        }
+
+        // diagnostics
+        let diagnostic = &mut theme.diagnostic;
+        let severities = [
+            (&mut diagnostic.error, "editorError.foreground"),
+            (&mut diagnostic.warning, "editorWarning.foreground"),
+            (&mut diagnostic.info, "editorInfo.foreground"),
+            (&mut diagnostic.hint, "editorHint.foreground"),
+        ];
+        for (color, id) in severities {
+            if let Some(vscode_color) = vscode.color(id) {
+                *color = vscode_color;
+            }
+        }
+
+        theme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_matches() {
+        assert!(selector_matches("string", "string.quoted"));
+        assert!(selector_matches("string.quoted", "string.quoted"));
+        assert!(!selector_matches("string.quoted", "string"));
+        assert!(!selector_matches("str", "string.quoted"));
+        assert!(!selector_matches("source.python string", "string.quoted"));
+        assert!(!selector_matches("", "string"));
+    }
+
+    #[test]
+    fn test_from_vscode() {
+        let vscode: VSCodeTheme = serde_json::from_str(
+            r##"{
+                "name": "Test Light",
+                "base": "light",
+                "tokenColors": [
+                    { "settings": { "foreground": "#111111" } },
+                    { "scope": "keyword", "settings": { "foreground": "#0000FF" } },
+                    { "scope": "keyword.operator", "settings": { "foreground": "#222222" } },
+                    { "scope": ["string", "comment"], "settings": { "foreground": "#A31515" } },
+                    { "scope": "comment.line, comment.block", "settings": { "foreground": "#008000" } },
+                    { "scope": "source.python string", "settings": { "foreground": "#FF0000" } },
+                    { "scope": "entity.name.function", "settings": { "fontStyle": "bold" } }
+                ],
+                "colors": {
+                    "editor.background": "#FFFFFF",
+                    "editorCursor.foreground": "#000",
+                    "editorError.foreground": "not a color"
+                }
+            }"##,
+        )
+        .unwrap();
+        let theme = Theme::from_vscode(&vscode);
+        let light = Theme::built_in("light").unwrap();
+
+        assert_eq!(theme.name, "Test Light");
+
+        // more specific rules win
+        assert_eq!(theme.syntax.keyword, Color32::from_rgb(0, 0, 0xFF));
+        assert_eq!(theme.syntax.operator, Color32::from_rgb(0x22, 0x22, 0x22));
+        assert_eq!(theme.syntax.string, Color32::from_rgb(0xA3, 0x15, 0x15));
+        assert_eq!(theme.syntax.comment, Color32::from_rgb(0, 0x80, 0));
+
+        // the rule without a scope is the default
+        assert_eq!(theme.syntax.default, Color32::from_rgb(0x11, 0x11, 0x11));
+
+        // anything missing or invalid comes from the base theme
+        assert_eq!(theme.syntax.function, light.syntax.function);
+        assert_eq!(theme.diagnostic.error, light.diagnostic.error);
+        assert_eq!(theme.blocks, light.blocks);
+
+        assert_eq!(theme.editor.background, Color32::WHITE);
+        assert_eq!(theme.editor.cursor, Color32::BLACK);
+    }
+}
@@ -31,7 +31,7 @@ impl Widget for SelectableRow<'_> {
         // highlight background if selected or hovered
         let painter = ui.painter();
         if self.selected || response.hovered() {
-            painter.rect_filled(rect, 0.0, theme::SELECTION);
+            painter.rect_filled(rect, 0.0, theme::current().editor.selection);
         }
 
         painter.text(
@@ -38,6 +38,18 @@ mod actual {
 
         #[wasm_bindgen(js_name = registerBreakpoints)]
         pub fn register_breakpoints(lines: Vec<usize>);
+
+        #[wasm_bindgen(js_name = copyHtml)]
+        pub fn copy_html(html: &str, text: &str);
+
+        #[wasm_bindgen(js_name = copyImage)]
+        pub fn copy_image(png: &[u8]);
+
+        #[wasm_bindgen(js_name = importPalettes)]
+        pub fn import_palettes();
+
+        #[wasm_bindgen(js_name = exportPalettes)]
+        pub fn export_palettes(contents: &str);
     }
 
     #[allow(dead_code)]
@@ -61,6 +73,14 @@ mod shim {
     pub fn execute_command(_: String, _: wasm_bindgen::JsValue) {}
     pub fn execute_workspace_edit(_: wasm_bindgen::JsValue) {}
     pub fn register_breakpoints(_: Vec<usize>) {}
+    #[allow(dead_code)]
+    pub fn copy_html(_: &str, _: &str) {}
+    #[allow(dead_code)]
+    pub fn copy_image(_: &[u8]) {}
+    #[allow(dead_code)]
+    pub fn import_palettes() {}
+    #[allow(dead_code)]
+    pub fn export_palettes(_: &str) {}
     pub fn log_event(_: &'static str, _: HashMap<&'static str, &str>) {}
 }
 
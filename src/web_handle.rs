@@ -6,13 +6,17 @@ use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
 
 use crate::block_editor::{
-    source::TextEdit, text_editor::StackFrameLines, text_range::TextRange, ExternalCommand,
+    export::CopyFormat,
+    source::{IndentStyle, TextEdit},
+    text_editor::StackFrameLines,
+    text_range::TextRange,
+    ExternalCommand,
 };
 use crate::lsp::{
     completion::VSCodeCompletionItem,
     diagnostics::{Diagnostic, VSCodeCodeAction},
 };
-use crate::theme::blocks_theme::BlocksTheme;
+use crate::theme::{blocks_theme::BlocksTheme, vscode_theme::VSCodeTheme, Theme, ThemeFormat};
 use crate::vscode;
 use crate::LilypadWeb;
 
@@ -96,9 +100,17 @@ impl LilypadWebHandle {
             modifiers,
         } = event
         {
-            // pass through hotkeys (other than undo/redo/find) and function keys
+            // pass through hotkeys (other than undo/redo/find and block commands) and function keys
             if modifiers.any() && !modifiers.shift_only() {
-                matches!(key, Key::Z | Key::Y | Key::F) == false
+                let handled_by_editor = match key {
+                    Key::Z | Key::Y | Key::F => true,
+                    Key::OpenBracket | Key::CloseBracket => true,
+                    Key::ArrowUp | Key::ArrowDown => modifiers.alt,
+                    Key::ArrowLeft | Key::ArrowRight => modifiers.alt && modifiers.ctrl,
+                    Key::B | Key::D | Key::K | Key::I => modifiers.shift,
+                    _ => false,
+                };
+                !handled_by_editor
             } else {
                 matches!(
                     key,
@@ -163,6 +175,20 @@ impl LilypadWebHandle {
         }
     }
 
+    #[wasm_bindgen]
+    pub fn add_font(&self, family: String, data: Vec<u8>) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::AddFont { family, data })
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
     #[wasm_bindgen]
     pub fn apply_edit(&self, json: JsValue) {
         #[derive(serde::Deserialize)]
@@ -199,6 +225,104 @@ impl LilypadWebHandle {
         }
     }
 
+    /// Show the type of each block with its border pattern and a label, not just its color
+    #[wasm_bindgen]
+    pub fn set_block_shape_cues(&self, enabled: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetBlockShapeCues(enabled))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Show or hide the outline of the file's definitions beside the editor
+    #[wasm_bindgen]
+    pub fn set_outline_visible(&self, visible: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetOutlineVisible(visible))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Show or hide the minimap of the document beside the editor
+    #[wasm_bindgen]
+    pub fn set_minimap_visible(&self, visible: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetMinimapVisible(visible))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Speak the line and surroundings of the cursor as it moves (for when a screen reader is in use)
+    #[wasm_bindgen]
+    pub fn set_screen_reader(&self, enabled: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetScreenReader(enabled))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Switch to one of the built in themes (dark, light, or high_contrast)
+    #[wasm_bindgen]
+    pub fn set_theme(&self, name: String) {
+        let Some(theme) = Theme::built_in(&name) else {
+            error!("Unknown theme {name}");
+            return;
+        };
+        self.send_theme(theme);
+    }
+
+    /// Switch to a theme loaded from the contents of a JSON or TOML theme file
+    #[wasm_bindgen]
+    pub fn load_theme(&self, file_name: String, contents: String) {
+        match Theme::parse(&contents, ThemeFormat::for_file(&file_name)) {
+            Ok(theme) => self.send_theme(theme),
+            Err(err) => error!("Failed to load theme {file_name}: {err}"),
+        }
+    }
+
+    /// Switch to the colors of a VS Code color theme
+    #[wasm_bindgen]
+    pub fn set_vscode_theme(&self, theme_json: JsValue) {
+        match serde_wasm_bindgen::from_value::<VSCodeTheme>(theme_json) {
+            Ok(vscode_theme) => self.send_theme(Theme::from_vscode(&vscode_theme)),
+            Err(err) => error!("Failed to read VS Code theme: {err}"),
+        }
+    }
+
+    fn send_theme(&self, theme: Theme) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::SetTheme(theme)).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
     #[wasm_bindgen]
     pub fn set_diagnostics(&self, json: JsValue) {
         let mut diagnostics: Vec<Diagnostic> =
@@ -318,4 +442,172 @@ impl LilypadWebHandle {
             error!("No command sender");
         }
     }
+
+    #[wasm_bindgen]
+    pub fn reindent(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::Reindent).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Copy the selection with its syntax colors (and the blocks if `blocks` is set) as html
+    #[wasm_bindgen]
+    pub fn copy_as_html(&self, blocks: bool) {
+        self.copy_rich(CopyFormat::Html, blocks);
+    }
+
+    /// Copy the selection with its syntax colors (and the blocks if `blocks` is set) as a PNG image
+    #[wasm_bindgen]
+    pub fn copy_as_image(&self, blocks: bool) {
+        self.copy_rich(CopyFormat::Image, blocks);
+    }
+
+    fn copy_rich(&self, format: CopyFormat, blocks: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::CopyRich { format, blocks })
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Add the palettes in a palettes file (TOML, or JSON if the name ends in .json)
+    #[wasm_bindgen]
+    pub fn import_palettes(&self, name: String, contents: String) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::ImportPalettes { name, contents })
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn export_palettes(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::ExportPalettes).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_indent_style(&self, use_tabs: bool, tab_size: usize, detect: bool) {
+        if let Some(sender) = &self.command_sender {
+            let style = IndentStyle {
+                use_tabs,
+                width: tab_size.max(1),
+            };
+            if sender
+                .send(ExternalCommand::SetIndentStyle { style, detect })
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn convert_indentation(&self, use_tabs: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::ConvertIndentation { use_tabs })
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_soft_wrap(&self, enabled: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::SetSoftWrap(enabled)).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Pin the headers of the blocks enclosing the top of the view while scrolling
+    #[wasm_bindgen]
+    pub fn set_sticky_scroll(&self, enabled: bool) {
+        if let Some(sender) = &self.command_sender {
+            if sender
+                .send(ExternalCommand::SetStickyScroll(enabled))
+                .is_err()
+            {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Open the menu of blocks to surround the selection with
+    #[wasm_bindgen]
+    pub fn surround_with(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::SurroundWith).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Remove the header of the block at the cursor, keeping its body
+    #[wasm_bindgen]
+    pub fn unwrap_block(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::UnwrapBlock).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Move the cursor to where the local variable at the cursor is defined
+    #[wasm_bindgen]
+    pub fn go_to_local_definition(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::GoToLocalDefinition).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
+
+    /// Open the box for renaming the local variable at the cursor
+    #[wasm_bindgen]
+    pub fn rename_local(&self) {
+        if let Some(sender) = &self.command_sender {
+            if sender.send(ExternalCommand::RenameLocal).is_err() {
+                error!("Failed to send command");
+            }
+        } else {
+            error!("No command sender");
+        }
+    }
 }